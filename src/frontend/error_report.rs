@@ -0,0 +1,36 @@
+use std::fmt;
+
+use super::parse::recursive_descent::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoxErrorReport {
+    pub line_number: usize,
+    pub location: String,
+    pub message: String,
+}
+
+impl LoxErrorReport {
+    pub fn new(line_number: usize, location: String, message: String) -> LoxErrorReport {
+        LoxErrorReport {
+            line_number,
+            location,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for LoxErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Line: {}, Loc: {}, Message: {}",
+            self.line_number, self.location, self.message,
+        )
+    }
+}
+
+impl From<ParseError> for LoxErrorReport {
+    fn from(err: ParseError) -> Self {
+        LoxErrorReport::new(err.token.line_number, err.token.lexeme, err.message)
+    }
+}
@@ -1,28 +1,315 @@
-use super::run;
+use std::{
+    env, fs, io,
+    time::{Duration, Instant},
+};
+
+use super::{
+    lex::token::KEYWORDS,
+    parse::{
+        ast_printer, recursive_descent::Parser, resolver::Resolver,
+        tree_walk_interpreter::Interpreter,
+    },
+    parse_source, run_in_interpreter, run_parsed, scan, syntax_error, LoxScriptError, Parsed,
+};
 use rustyline::error::ReadlineError;
 use rustyline::{DefaultEditor, Result};
 
+/**
+ * A line typed at the prompt which controls the REPL, rather than being run as Lox source
+ */
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    Exit,
+    Help,
+    Reset,
+    Load(&'a str),
+    Ast(&'a str),
+    Time(&'a str),
+    Rerun,
+    Unknown(&'a str),
+}
+
+/**
+ * The environment variable which replaces the default prompt
+ */
+const PROMPT_VARIABLE: &str = "LOXIDE_PROMPT";
+const DEFAULT_PROMPT: &str = "lox > ";
+
 pub fn run_interactive() -> Result<()> {
     let mut rl = DefaultEditor::new()?;
+    let mut stdout = io::stdout();
+    let mut interpreter = Interpreter::new(&mut stdout);
+    let prompt = resolve_prompt(env::var(PROMPT_VARIABLE).ok());
+    // The last source which parsed successfully, which `:rerun` runs again
+    let mut last = None;
 
     loop {
-        let readline = rl.readline("lox > ");
+        let readline = rl.readline(&prompt);
 
         match readline {
             Ok(line) => {
                 rl.add_history_entry(line.as_str())?;
-                run(&line);
-            }
-            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
-                println!("Exiting...");
-                break;
+
+                let result = match parse_command(&line) {
+                    Some(Command::Exit) => break,
+                    Some(command) => run_command(command, &mut interpreter, &last),
+                    None => run_line(&line, &mut interpreter, &mut last),
+                };
+
+                if let Err(err) = result {
+                    println!("{}", err);
+                }
             }
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
             Err(err) => {
                 println!("Error: {:?}", err);
-                break;
+                return Ok(());
             }
         }
     }
 
+    println!("Exiting...");
     Ok(())
 }
+
+/**
+ * Uses the prompt from the environment variable if it is set, or the default otherwise
+ */
+fn resolve_prompt(variable: Option<String>) -> String {
+    variable.unwrap_or_else(|| DEFAULT_PROMPT.to_string())
+}
+
+/**
+ * Recognises a REPL command. Returns None if the line should be run as Lox source.
+ */
+fn parse_command(line: &str) -> Option<Command<'_>> {
+    let line = line.trim();
+
+    if line == "exit" {
+        return Some(Command::Exit);
+    }
+
+    let command = line.strip_prefix(':')?;
+    let (name, argument) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+
+    Some(match name {
+        "help" => Command::Help,
+        "reset" => Command::Reset,
+        "load" => Command::Load(argument),
+        "ast" => Command::Ast(argument),
+        "time" => Command::Time(argument),
+        "rerun" => Command::Rerun,
+        _ => Command::Unknown(name),
+    })
+}
+
+/**
+ * Runs a line of Lox source, remembering it for `:rerun` if it parses
+ */
+fn run_line(
+    line: &str,
+    interpreter: &mut Interpreter,
+    last: &mut Option<Parsed>,
+) -> std::result::Result<(), LoxScriptError> {
+    let parsed = last.insert(parse_source(line, true)?);
+    run_parsed(parsed, interpreter)
+}
+
+fn run_command(
+    command: Command,
+    interpreter: &mut Interpreter,
+    last: &Option<Parsed>,
+) -> std::result::Result<(), LoxScriptError> {
+    match command {
+        Command::Exit => {}
+        Command::Help => println!("{}", help()),
+        Command::Reset => interpreter.reset(),
+        Command::Load(file_path) => {
+            run_in_interpreter(&fs::read_to_string(file_path)?, interpreter)?
+        }
+        Command::Ast(source) => println!("{}", print_expression_ast(source)?),
+        Command::Time(source) => println!("Took {:?}", time_expression(source, interpreter)?),
+        Command::Rerun => match last {
+            Some(parsed) => run_parsed(parsed, interpreter)?,
+            None => println!("Nothing to rerun yet."),
+        },
+        Command::Unknown(name) => println!(
+            "Unknown command ':{}'. Type ':help' for a list of commands.",
+            name
+        ),
+    }
+
+    Ok(())
+}
+
+/**
+ * Lists the REPL commands and the Lox keywords
+ */
+fn help() -> String {
+    let mut keywords: Vec<&str> = KEYWORDS.keys().copied().collect();
+    keywords.sort_unstable();
+
+    format!(
+        "Commands:
+    exit            Leave the REPL.
+    :load <file>    Run a script, keeping anything it defines.
+    :reset          Forget everything defined so far.
+    :ast <expr>     Print the parsed syntax tree of an expression instead of evaluating it.
+    :time <expr>    Evaluate an expression and print how long it took.
+    :rerun          Run the last source entered again, without parsing it again.
+    :help           Show this message.
+
+Keywords: {}",
+        keywords.join(", ")
+    )
+}
+
+/**
+ * Parses the source as an expression, printing its AST rather than evaluating it
+ */
+fn print_expression_ast(source: &str) -> std::result::Result<String, LoxScriptError> {
+    scan(source).and_then(|tokens| {
+        Parser::new(tokens)
+            .parse_expression()
+            .map(|expr| ast_printer::print(&expr))
+            .map_err(|err| LoxScriptError::Syntax(vec![err.into()]))
+    })
+}
+
+/**
+ * Evaluates the expression, displaying its value, and returns how long evaluating it took
+ */
+fn time_expression(
+    source: &str,
+    interpreter: &mut Interpreter,
+) -> std::result::Result<Duration, LoxScriptError> {
+    let expr = scan(source).and_then(|tokens| {
+        Parser::new(tokens)
+            .parse_expression()
+            .map_err(|err| LoxScriptError::Syntax(vec![err.into()]))
+    })?;
+    Resolver::resolve_expression(&expr).map_err(syntax_error)?;
+
+    let start = Instant::now();
+    interpreter.display(&expr)?;
+
+    Ok(start.elapsed())
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn test_ast_command_prints_expression() {
+        assert_eq!(print_expression_ast("1 + 2 * 3").unwrap(), "(+ 1 (* 2 3))");
+    }
+
+    #[test]
+    fn test_ast_command_reports_parse_errors() {
+        let ast = print_expression_ast("1 +");
+
+        assert!(matches!(ast, Err(LoxScriptError::Syntax(_))));
+    }
+
+    #[rstest]
+    #[case::help(":help", Some(Command::Help))]
+    #[case::padded("  :help  ", Some(Command::Help))]
+    #[case::exit("exit", Some(Command::Exit))]
+    #[case::reset(":reset", Some(Command::Reset))]
+    #[case::load(":load  script.lox", Some(Command::Load("script.lox")))]
+    #[case::ast(":ast 1 + 2", Some(Command::Ast("1 + 2")))]
+    #[case::time(":time fib(20)", Some(Command::Time("fib(20)")))]
+    #[case::rerun(":rerun", Some(Command::Rerun))]
+    #[case::unknown(":nope", Some(Command::Unknown("nope")))]
+    #[case::source("print 1;", None)]
+    #[case::help_in_source("print \":help\";", None)]
+    fn test_parse_command(#[case] line: &str, #[case] expected: Option<Command>) {
+        assert_eq!(parse_command(line), expected);
+    }
+
+    #[test]
+    fn test_time_command_evaluates_expression() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+
+        let duration = match parse_command(":time 1 + 2") {
+            Some(Command::Time(source)) => time_expression(source, &mut interpreter),
+            other => panic!("Expected a :time command, got {:?}", other),
+        };
+        drop(interpreter);
+
+        assert!(duration.is_ok());
+        assert_eq!(output, b"3\n");
+    }
+
+    #[test]
+    fn test_time_command_reports_errors() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+
+        let syntax = time_expression("1 +", &mut interpreter);
+        let runtime = time_expression("-nil", &mut interpreter);
+
+        assert!(matches!(syntax, Err(LoxScriptError::Syntax(_))));
+        assert!(matches!(runtime, Err(LoxScriptError::Runtime(_))));
+    }
+
+    #[test]
+    fn test_rerun_applies_last_source_again() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+        let mut last = None;
+
+        run_line("var x = 1;", &mut interpreter, &mut last).unwrap();
+        run_line("x = x * 2", &mut interpreter, &mut last).unwrap();
+        run_command(Command::Rerun, &mut interpreter, &last).unwrap();
+        run_command(Command::Rerun, &mut interpreter, &last).unwrap();
+        run_line("print x;", &mut interpreter, &mut last).unwrap();
+        drop(interpreter);
+
+        assert_eq!(String::from_utf8(output).unwrap(), "2\n4\n8\n8\n");
+    }
+
+    #[test]
+    fn test_rerun_keeps_last_source_that_parsed() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+        let mut last = None;
+
+        run_line("var x = 1;", &mut interpreter, &mut last).unwrap();
+        run_line("x += 1;", &mut interpreter, &mut last).unwrap();
+        assert!(run_line("x +;", &mut interpreter, &mut last).is_err());
+        run_command(Command::Rerun, &mut interpreter, &last).unwrap();
+        run_line("print x;", &mut interpreter, &mut last).unwrap();
+        drop(interpreter);
+
+        assert_eq!(String::from_utf8(output).unwrap(), "3\n");
+    }
+
+    #[rstest]
+    #[case::unset(None, "lox > ")]
+    #[case::set(Some("> "), "> ")]
+    #[case::empty(Some(""), "")]
+    fn test_resolve_prompt(#[case] variable: Option<&str>, #[case] expected: &str) {
+        assert_eq!(resolve_prompt(variable.map(str::to_string)), expected);
+    }
+
+    #[test]
+    fn test_help_lists_commands_and_keywords() {
+        let help = help();
+
+        for command in [
+            "exit", ":load", ":reset", ":ast", ":time", ":rerun", ":help",
+        ] {
+            assert!(help.contains(command), "missing {}", command);
+        }
+        assert!(help.contains(
+            "and, break, case, class, const, continue, default, do, else, false, for, fun, if, nil, or"
+        ));
+    }
+}
@@ -4,38 +4,50 @@ use std::string::String;
 use unicode_segmentation::GraphemeIndices;
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::frontend::error_report::LoxErrorReport;
 use crate::frontend::lex::token::TokenType;
 
 use super::token::Literal;
-use super::token::LoxTokenError;
+use super::token::Span;
 use super::token::Token;
 use super::token::TokenType::*;
 use super::token::KEYWORDS;
 
-pub type TokenResult = Result<Token, LoxTokenError>;
+pub type TokenResult = Result<Token, LoxErrorReport>;
 
 pub struct Scanner {
     line_number: usize,
     lexeme_start: usize,
-    lexeme_current: usize,
+    // The byte offset just past the last grapheme of the current lexeme
+    lexeme_end: usize,
     tokens: Vec<TokenResult>,
 }
 
 impl Scanner {
-    pub fn scan_tokens(source: &str) -> Vec<TokenResult> {
+    /**
+     * Scans the source into tokens. Comments are skipped unless `keep_comments` is set,
+     * in which case they are kept as comment tokens for tools such as formatters.
+     */
+    pub fn scan_tokens(source: &str, keep_comments: bool) -> Vec<TokenResult> {
         let mut scanner = Scanner {
             line_number: 0,
             lexeme_start: 0,
-            lexeme_current: 0,
+            lexeme_end: 0,
             tokens: Vec::new(),
         };
 
         // Get an iterator over the graphemes in the line
         let mut grapheme_iter = UnicodeSegmentation::grapheme_indices(source, true).peekable();
 
+        // A shebang on the first line lets scripts be run directly on Unix, so it is skipped.
+        // The line break is left for the newline arm below.
+        if source.starts_with("#!") {
+            while grapheme_iter.next_if(|(_, g)| !is_newline(g)).is_some() {}
+        }
+
         while let Some((grapheme_idx, g)) = grapheme_iter.next() {
             scanner.lexeme_start = grapheme_idx;
-            scanner.lexeme_current = grapheme_idx;
+            scanner.lexeme_end = grapheme_idx + g.len();
 
             let mut add_if_next_matches =
                 |expected: &str, on_true: TokenType, on_false: TokenType| {
@@ -52,13 +64,28 @@ impl Scanner {
                 ")" => scanner.add_token(RightParen, source),
                 "{" => scanner.add_token(LeftBrace, source),
                 "}" => scanner.add_token(RightBrace, source),
+                "[" => scanner.add_token(LeftBracket, source),
+                "]" => scanner.add_token(RightBracket, source),
                 "," => scanner.add_token(Comma, source),
-                "." => scanner.add_token(Dot, source),
-                "-" => scanner.add_token(Minus, source),
-                "+" => scanner.add_token(Plus, source),
+                "." => {
+                    // Counts the dots after this one, so `..` and `...` are single tokens
+                    let dots = grapheme_iter
+                        .clone()
+                        .take(2)
+                        .take_while(|(_, g)| *g == ".")
+                        .count();
+
+                    for _ in 0..dots {
+                        scanner.next_matches(&mut grapheme_iter, ".");
+                    }
+                    match dots {
+                        0 => scanner.add_token(Dot, source),
+                        1 => scanner.add_token(DotDot, source),
+                        _ => scanner.add_token(DotDotDot, source),
+                    }
+                }
                 ";" => scanner.add_token(Semicolon, source),
-                "*" => scanner.add_token(Star, source),
-                "?" => scanner.add_token(QuestionMark, source),
+                "?" => add_if_next_matches(":", QuestionColon, QuestionMark),
                 ":" => scanner.add_token(Colon, source),
 
                 // One or two character tokens
@@ -66,18 +93,47 @@ impl Scanner {
                 "=" => add_if_next_matches("=", EqualEqual, Equal),
                 "<" => add_if_next_matches("=", LessEqual, Less),
                 ">" => add_if_next_matches("=", GreaterEqual, Greater),
+                "*" => add_if_next_matches("=", StarEqual, Star),
+
+                // Compound assignment, increment or decrement
+                "+" | "-" => {
+                    let (compound, postfix, single) = match g {
+                        "+" => (PlusEqual, PlusPlus, Plus),
+                        _ => (MinusEqual, MinusMinus, Minus),
+                    };
+
+                    if scanner.next_matches(&mut grapheme_iter, "=") {
+                        scanner.add_token(compound, source)
+                    } else if scanner.next_matches(&mut grapheme_iter, g) {
+                        scanner.add_token(postfix, source)
+                    } else {
+                        scanner.add_token(single, source)
+                    }
+                }
 
                 // Comments or division
                 "/" => {
                     if scanner.next_matches(&mut grapheme_iter, "/") {
-                        while grapheme_iter.next_if(|(_, g)| *g != "\n").is_some() {}
-                        scanner.line_number += 1;
+                        let mut end = scanner.lexeme_end;
+
+                        // The line break itself is left for the newline arm below
+                        while let Some((idx, g)) = grapheme_iter.next_if(|(_, g)| !is_newline(g)) {
+                            end = idx + g.len();
+                        }
+
+                        if keep_comments {
+                            let text = &source[grapheme_idx + 2..end];
+                            scanner.add_comment(source, end, text, scanner.line_number);
+                        }
                     } else if scanner.next_matches(&mut grapheme_iter, "*") {
                         // Multiline comment
                         // We keep track of depth to allow nested comments
+                        let start_line = scanner.line_number;
                         let mut depth = 1;
                         while let Some((_, g)) = grapheme_iter.next() {
-                            if g == "*" && scanner.next_matches(&mut grapheme_iter, "/") {
+                            if is_newline(g) {
+                                scanner.line_number += 1;
+                            } else if g == "*" && scanner.next_matches(&mut grapheme_iter, "/") {
                                 depth -= 1;
                                 if depth == 0 {
                                     break;
@@ -86,16 +142,28 @@ impl Scanner {
                                 depth += 1;
                             }
                         }
+
+                        if keep_comments {
+                            // An unterminated comment runs to the end of the source
+                            let (end, text_end) = match depth {
+                                0 => (scanner.lexeme_end, scanner.lexeme_end - "*/".len()),
+                                _ => (source.len(), source.len()),
+                            };
+                            let text = &source[grapheme_idx + 2..text_end];
+                            scanner.add_comment(source, end, text, start_line);
+                        }
+                    } else if scanner.next_matches(&mut grapheme_iter, "=") {
+                        scanner.add_token(SlashEqual, source)
                     } else {
                         scanner.add_token(Slash, source)
                     }
                 }
 
                 // Ignore whitespace
-                " " | "\r" | "\t" => {}
+                " " | "\t" => {}
 
                 // Newline
-                "\n" => scanner.line_number += 1,
+                _ if is_newline(g) => scanner.line_number += 1,
 
                 // String
                 "\"" => scanner.parse_string(&mut grapheme_iter, source),
@@ -107,7 +175,7 @@ impl Scanner {
                 _ if is_alpha(g) => scanner.parse_identifier(&mut grapheme_iter, source),
 
                 // Invalid token
-                _ => scanner.tokens.push(TokenResult::Err(LoxTokenError::new(
+                _ => scanner.tokens.push(TokenResult::Err(LoxErrorReport::new(
                     scanner.line_number,
                     String::new(),
                     format!(
@@ -123,6 +191,7 @@ impl Scanner {
             String::new(),
             None,
             scanner.line_number,
+            Span::new(source.len(), source.len()),
         )));
         scanner.tokens
     }
@@ -131,7 +200,14 @@ impl Scanner {
      * Gets the lexeme from the current line
      */
     fn get_lexeme(&self, src: &str) -> String {
-        src[self.lexeme_start..self.lexeme_current + 1].to_string()
+        src[self.lexeme_start..self.lexeme_end].to_string()
+    }
+
+    /**
+     * Gets the span of the current lexeme in the source
+     */
+    fn get_span(&self) -> Span {
+        Span::new(self.lexeme_start, self.lexeme_end)
     }
 
     /**
@@ -143,6 +219,7 @@ impl Scanner {
             self.get_lexeme(src),
             None,
             self.line_number,
+            self.get_span(),
         )))
     }
 
@@ -155,6 +232,21 @@ impl Scanner {
             self.get_lexeme(src),
             Some(literal),
             self.line_number,
+            self.get_span(),
+        )))
+    }
+
+    /**
+     * Adds a comment token ending at the given position, with the comment's text
+     * (without its delimiters) as its literal
+     */
+    fn add_comment(&mut self, src: &str, end: usize, text: &str, line_number: usize) {
+        self.tokens.push(TokenResult::Ok(Token::new(
+            Comment,
+            src[self.lexeme_start..end].to_string(),
+            Some(Literal::String(text.to_string())),
+            line_number,
+            Span::new(self.lexeme_start, end),
         )))
     }
 
@@ -166,48 +258,72 @@ impl Scanner {
         grapheme_iter: &mut Peekable<GraphemeIndices>,
         expected: &str,
     ) -> bool {
-        if let Some((_, nxt)) = grapheme_iter.peek() {
-            if *nxt == expected {
-                if let Some((next_idx, _)) = grapheme_iter.next() {
-                    self.lexeme_current = next_idx;
-                    return true;
-                }
+        match grapheme_iter.next_if(|(_, nxt)| *nxt == expected) {
+            Some((next_idx, nxt)) => {
+                self.lexeme_end = next_idx + nxt.len();
+                true
             }
+            None => false,
         }
-
-        false
     }
 
     /**
      * Parses a string from the current position
      * Assumes that the current position is a quote
-     * If the string is unterminated, an error is added to the list of tokens
+     * A backslash at the end of a line joins it to the next line without a newline
+     * `\u{XXXX}` is replaced with the character with that hex code point
+     * If the string is unterminated or has an invalid escape, an error is added to the list of tokens
      */
     fn parse_string(&mut self, grapheme_iter: &mut Peekable<GraphemeIndices>, src: &str) {
-        for (next_idx, g) in grapheme_iter.by_ref() {
-            self.lexeme_current = next_idx;
+        let mut value = String::new();
+        let mut escape_error = None;
+
+        while let Some((next_idx, g)) = grapheme_iter.next() {
+            self.lexeme_end = next_idx + g.len();
 
-            if g == "\n" {
+            if g == "\\" && grapheme_iter.next_if(|(_, g)| is_newline(g)).is_some() {
                 self.line_number += 1;
                 continue;
             }
 
-            if g == "\"" {
-                // Trim the quotes
-                self.lexeme_start += 1;
-                self.lexeme_current -= 1;
+            if g == "\\" && grapheme_iter.next_if(|(_, g)| *g == "u").is_some() {
+                match parse_unicode_escape(grapheme_iter) {
+                    Ok(c) => value.push(c),
+                    Err(message) => escape_error = escape_error.or(Some(message)),
+                }
+                continue;
+            }
 
-                self.add_literal_token(String, Literal::String(self.get_lexeme(src)), src);
+            if is_newline(g) {
+                self.line_number += 1;
+            }
+
+            if g == "\"" {
+                if let Some(message) = escape_error {
+                    self.tokens.push(TokenResult::Err(LoxErrorReport::new(
+                        self.line_number,
+                        String::new(),
+                        message,
+                    )));
+                    return;
+                }
 
-                // Reset the start and current
-                self.lexeme_current += 1;
-                self.lexeme_start -= 1;
+                // The lexeme excludes the quotes, but the span includes them
+                self.tokens.push(TokenResult::Ok(Token::new(
+                    String,
+                    src[self.lexeme_start + 1..next_idx].to_string(),
+                    Some(Literal::String(value)),
+                    self.line_number,
+                    self.get_span(),
+                )));
 
                 return;
             }
+
+            value.push_str(g);
         }
 
-        self.tokens.push(TokenResult::Err(LoxTokenError::new(
+        self.tokens.push(TokenResult::Err(LoxErrorReport::new(
             self.line_number,
             String::new(),
             format!(
@@ -221,13 +337,20 @@ impl Scanner {
      * Parses a number from the current position
      * Assumes that the current position is a digit
      * Advances the iterator to the end of the number
-     * Allows for a single decimal point, but not leading or trailing
+     * Allows for a single decimal point, but not leading or trailing.
+     * So `.5` is a dot followed by the number 5, `1.` is the number 1 followed by a dot,
+     * and `1..2` is the number 1, two dots, then the number 2.
      */
     fn parse_number(&mut self, grapheme_iter: &mut Peekable<GraphemeIndices>, src: &str) {
         let mut has_decimal = false;
-        while let Some((next_idx, g)) = grapheme_iter.peek() {
-            if *g == "." {
-                if has_decimal {
+        while let Some(&(next_idx, g)) = grapheme_iter.peek() {
+            if g == "." {
+                // The decimal point must have a digit after it
+                let mut lookahead = grapheme_iter.clone();
+                lookahead.next();
+                let digit_follows = matches!(lookahead.peek(), Some((_, g)) if is_digit(g));
+
+                if has_decimal || !digit_follows {
                     break;
                 }
 
@@ -236,14 +359,14 @@ impl Scanner {
                 break;
             }
 
-            self.lexeme_current = *next_idx;
+            self.lexeme_end = next_idx + g.len();
             grapheme_iter.next();
         }
 
         let parsed_number = self.get_lexeme(src).parse::<f64>();
 
         if parsed_number.is_err() {
-            self.tokens.push(TokenResult::Err(LoxTokenError::new(
+            self.tokens.push(TokenResult::Err(LoxErrorReport::new(
                 self.line_number,
                 String::new(),
                 format!(
@@ -263,7 +386,7 @@ impl Scanner {
                 break;
             }
 
-            self.lexeme_current = *next_idx;
+            self.lexeme_end = *next_idx + g.len();
             grapheme_iter.next();
         }
 
@@ -275,19 +398,54 @@ impl Scanner {
 }
 
 /**
- * Checks if the given string is a digit (0-9)
+ * Decodes the `{XXXX}` following a `\u` in a string into the character with that hex code point
+ * Only consumes the graphemes which could be part of the escape
  */
-fn is_digit(g: &str) -> bool {
-    let char = g.chars().next();
+fn parse_unicode_escape(grapheme_iter: &mut Peekable<GraphemeIndices>) -> Result<char, String> {
+    if grapheme_iter.next_if(|(_, g)| *g == "{").is_none() {
+        return Err("Expected '{' after '\\u' in unicode escape.".to_string());
+    }
 
-    match char {
-        Some(c) => c.is_ascii_digit(),
-        None => false,
+    let mut digits = String::new();
+    while let Some((_, g)) =
+        grapheme_iter.next_if(|(_, g)| g.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        digits.push_str(g);
+    }
+
+    if grapheme_iter.next_if(|(_, g)| *g == "}").is_none() {
+        return Err("Expected '}' to close unicode escape.".to_string());
     }
+
+    if digits.is_empty() || digits.len() > 6 {
+        return Err("Unicode escape must have between 1 and 6 hex digits.".to_string());
+    }
+
+    u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("Invalid unicode code point '{}'.", digits))
+}
+
+/**
+ * Checks if the given grapheme is a line break, in Unix (`\n`), Windows (`\r\n`) or old Mac (`\r`) style.
+ * `\r\n` is a single grapheme, so it only counts as one line.
+ */
+fn is_newline(g: &str) -> bool {
+    matches!(g, "\n" | "\r\n" | "\r")
 }
 
 /**
- * Checks if the given string is an alpha character (a-z, A-Z, _)
+ * Checks if the given grapheme is a digit (0-9) on its own, without any combining marks
+ */
+fn is_digit(g: &str) -> bool {
+    matches!(g.as_bytes(), [b] if b.is_ascii_digit())
+}
+
+/**
+ * Checks if the given grapheme can start an identifier.
+ * A grapheme is judged by its first character, which must be a letter or `_`.
+ * Any combining marks after it are part of the same grapheme, so belong to the identifier too.
  */
 fn is_alpha(g: &str) -> bool {
     let char = g.chars().next();
@@ -299,7 +457,8 @@ fn is_alpha(g: &str) -> bool {
 }
 
 /**
- * Checks if the given string is an alphanumeric character (a-z, A-Z, 0-9, _)
+ * Checks if the given grapheme can continue an identifier.
+ * Like `is_alpha`, but the first character may also be a digit.
  */
 fn is_alphanumeric(g: &str) -> bool {
     let char = g.chars().next();
@@ -325,9 +484,19 @@ mod test {
         for c in "abcdefghijklmnopqrstuvwxyz$&~".chars() {
             assert!(!is_digit(&c.to_string()));
         }
+
+        // A digit followed by combining marks, such as a keycap, is not a plain digit
+        assert!(!is_digit("1\u{fe0f}\u{20e3}"));
+        assert!(!is_digit("\u{663}"));
     }
 
     #[rstest]
+    #[case::elvis(
+        "a ?: b ? c : d",
+        vec![(Identifier, "a"), (QuestionColon, "?:"), (Identifier, "b"), (QuestionMark, "?"), (Identifier, "c"), (Colon, ":"), (Identifier, "d"), (Eof, "")])]
+    #[case::brackets(
+        "[]",
+        vec![(LeftBracket, "["), (RightBracket, "]"), (Eof, "")])]
     #[case::simple_digits(
         "1 < 3 + 4",
         vec![(Number, "1"), (Less, "<"), (Number, "3"), (Plus, "+"), (Number, "4"), (Eof, "")])]
@@ -337,11 +506,29 @@ mod test {
     #[case::decimal_number(
         "1.234",
         vec![(Number, "1.234"), (Eof, "")])]
+    #[case::compound_assignment(
+        "x += 1 -= 2 *= 3 /= 4",
+        vec![(Identifier, "x"), (PlusEqual, "+="), (Number, "1"), (MinusEqual, "-="), (Number, "2"), (StarEqual, "*="), (Number, "3"), (SlashEqual, "/="), (Number, "4"), (Eof, "")])]
+    #[case::increment_decrement(
+        "x++ - -y--",
+        vec![(Identifier, "x"), (PlusPlus, "++"), (Minus, "-"), (Minus, "-"), (Identifier, "y"), (MinusMinus, "--"), (Eof, "")])]
+    #[case::leading_dot(
+        ".5",
+        vec![(Dot, "."), (Number, "5"), (Eof, "")])]
+    #[case::trailing_dot(
+        "1.",
+        vec![(Number, "1"), (Dot, "."), (Eof, "")])]
+    #[case::double_dot(
+        "1..2",
+        vec![(Number, "1"), (DotDot, ".."), (Number, "2"), (Eof, "")])]
+    #[case::ellipsis(
+        "(...xs) .. ....",
+        vec![(LeftParen, "("), (DotDotDot, "..."), (Identifier, "xs"), (RightParen, ")"), (DotDot, ".."), (DotDotDot, "..."), (Dot, "."), (Eof, "")])]
     #[case::complex_decimal_number(
         "1.234.567.123",
         vec![(Number, "1.234"), (Dot, "."), (Number, "567.123"), (Eof, "")])]
     fn test_scan_tokens(#[case] input: &str, #[case] expected: Vec<(TokenType, &str)>) {
-        let tokens = Scanner::scan_tokens(input);
+        let tokens = Scanner::scan_tokens(input, false);
 
         assert_eq!(tokens.len(), expected.len());
 
@@ -371,8 +558,26 @@ mod test {
     #[case::identifer_starting_with_underscore(
         "_a",
         vec![(Identifier, "_a"), (Eof, "")])]
+    #[case::accented_precomposed(
+        "café",
+        vec![(Identifier, "café"), (Eof, "")])]
+    #[case::accented_combining(
+        "cafe\u{301}",
+        vec![(Identifier, "cafe\u{301}"), (Eof, "")])]
+    #[case::mixed_normalisation(
+        "e\u{301}t\u{e9}",
+        vec![(Identifier, "e\u{301}t\u{e9}"), (Eof, "")])]
+    #[case::underscore_with_combining(
+        "_\u{301}",
+        vec![(Identifier, "_\u{301}"), (Eof, "")])]
+    #[case::greek_with_digit(
+        "π2",
+        vec![(Identifier, "π2"), (Eof, "")])]
+    #[case::devanagari(
+        "नमस्ते",
+        vec![(Identifier, "नमस्ते"), (Eof, "")])]
     fn test_scan_tokens_identifier(#[case] input: &str, #[case] expected: Vec<(TokenType, &str)>) {
-        let tokens = Scanner::scan_tokens(input);
+        let tokens = Scanner::scan_tokens(input, false);
 
         assert_eq!(tokens.len(), expected.len());
 
@@ -423,6 +628,24 @@ mod test {
     #[case::keyword_super(
         "super",
         vec![(Super, "super"), (Eof, "")])]
+    #[case::keyword_switch(
+        "switch",
+        vec![(Switch, "switch"), (Eof, "")])]
+    #[case::keyword_case(
+        "case",
+        vec![(Case, "case"), (Eof, "")])]
+    #[case::keyword_default(
+        "default",
+        vec![(Default, "default"), (Eof, "")])]
+    #[case::keyword_break(
+        "break",
+        vec![(Break, "break"), (Eof, "")])]
+    #[case::keyword_continue(
+        "continue",
+        vec![(Continue, "continue"), (Eof, "")])]
+    #[case::keyword_do(
+        "do",
+        vec![(Do, "do"), (Eof, "")])]
     #[case::keyword_this(
         "this",
         vec![(This, "this"), (Eof, "")])]
@@ -436,7 +659,7 @@ mod test {
         "while",
         vec![(While, "while"), (Eof, "")])]
     fn test_scan_tokens_keyword(#[case] input: &str, #[case] expected: Vec<(TokenType, &str)>) {
-        let tokens = Scanner::scan_tokens(input);
+        let tokens = Scanner::scan_tokens(input, false);
 
         assert_eq!(tokens.len(), expected.len());
 
@@ -459,7 +682,7 @@ mod test {
     )]
     #[case::nested_block_comment("/* This is a block comment /* With a nested block comment */ */")]
     fn test_scan_tokens_comments(#[case] input: &str) {
-        let tokens = Scanner::scan_tokens(input);
+        let tokens = Scanner::scan_tokens(input, false);
 
         assert_eq!(tokens.len(), 1);
         let token = tokens[0].clone().unwrap();
@@ -467,4 +690,240 @@ mod test {
         // Assert that the token is an EOF token
         assert_eq!(token.token_type, Eof);
     }
+
+    #[rstest]
+    #[case::newline("\"a\nb\"", "a\nb")]
+    #[case::line_continuation("\"a\\\nb\"", "ab")]
+    #[case::line_continuation_keeps_indentation("\"a \\\n  b\"", "a   b")]
+    #[case::backslash_not_before_newline("\"a\\b\"", "a\\b")]
+    fn test_scan_string_line_continuation(#[case] input: &str, #[case] expected: &str) {
+        let tokens = Scanner::scan_tokens(input, false);
+        let token = tokens[0].clone().unwrap();
+
+        assert_eq!(token.token_type, String);
+        assert_eq!(token.literal, Some(Literal::String(expected.to_string())));
+
+        // Both forms still count the line break
+        assert_eq!(
+            tokens[1].clone().unwrap().line_number,
+            input.matches('\n').count()
+        );
+    }
+
+    #[rstest]
+    #[case::no_indent("print x;", 1, 6)]
+    #[case::tab_one("\tprint x;", 1, 7)]
+    #[case::tab_width_four("\tprint x;", 4, 10)]
+    #[case::two_tabs("\t\tprint x;", 4, 14)]
+    #[case::spaces_unaffected("    print x;", 8, 10)]
+    #[case::later_line("print 1;\n\tprint x;", 4, 10)]
+    #[case::carriage_return("print 1;\r\tprint x;", 4, 10)]
+    #[case::crlf("print 1;\r\n\tprint x;", 4, 10)]
+    #[case::graphemes("\"é🦀\" + x;", 4, 7)]
+    fn test_column(#[case] source: &str, #[case] tab_width: usize, #[case] expected: usize) {
+        let tokens: Vec<Token> = Scanner::scan_tokens(source, false)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+        let x = tokens.iter().find(|token| token.lexeme == "x").unwrap();
+
+        assert_eq!(x.span.column(source, tab_width), expected);
+    }
+
+    #[rstest]
+    #[case::unix("\n")]
+    #[case::windows("\r\n")]
+    fn test_shebang_is_skipped(#[case] newline: &str) {
+        let source = format!("#!/usr/bin/env loxide{}print 1;", newline);
+        let tokens: Vec<Token> = Scanner::scan_tokens(&source, false)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        let scanned: Vec<(TokenType, &str, usize)> = tokens
+            .iter()
+            .map(|t| (t.token_type.clone(), t.lexeme.as_str(), t.line_number))
+            .collect();
+        assert_eq!(
+            scanned,
+            vec![
+                (Print, "print", 1),
+                (Number, "1", 1),
+                (Semicolon, ";", 1),
+                (Eof, "", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shebang_only_on_first_line() {
+        let tokens = Scanner::scan_tokens("print 1;\n#!/usr/bin/env loxide", false);
+
+        assert!(tokens.iter().any(|t| t.is_err()));
+    }
+
+    #[rstest]
+    #[case::lone_combining_mark("\u{301}a")]
+    #[case::keycap("1\u{fe0f}\u{20e3}")]
+    #[case::emoji("🦀")]
+    fn test_scan_invalid_identifier_start(#[case] input: &str) {
+        let tokens = Scanner::scan_tokens(input, false);
+
+        assert!(tokens[0].is_err());
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let source = "var answer = \"forty two\";";
+        let tokens: Vec<Token> = Scanner::scan_tokens(source, false)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        let identifier = &tokens[1];
+        assert_eq!(identifier.lexeme, "answer");
+        assert_eq!(identifier.span, Span::new(4, 10));
+        assert_eq!(
+            &source[identifier.span.start..identifier.span.end],
+            "answer"
+        );
+
+        let string = &tokens[3];
+        assert_eq!(string.span, Span::new(13, 24));
+        assert_eq!(&source[string.span.start..string.span.end], "\"forty two\"");
+
+        let eof = tokens.last().unwrap();
+        assert_eq!(eof.span, Span::new(source.len(), source.len()));
+    }
+
+    #[test]
+    fn test_multi_byte_lexemes() {
+        let source = "var café = \"naïve 🦀\"; π /* ü */ // ✓";
+        let tokens: Vec<Token> = Scanner::scan_tokens(source, true)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+        assert_eq!(
+            lexemes,
+            vec![
+                "var",
+                "café",
+                "=",
+                "naïve 🦀",
+                ";",
+                "π",
+                "/* ü */",
+                "// ✓",
+                ""
+            ]
+        );
+
+        for token in &tokens[..tokens.len() - 1] {
+            let spanned = &source[token.span.start..token.span.end];
+            match token.token_type {
+                String => assert_eq!(spanned, format!("\"{}\"", token.lexeme)),
+                _ => assert_eq!(spanned, token.lexeme),
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::unix("\n")]
+    #[case::windows("\r\n")]
+    #[case::old_mac("\r")]
+    fn test_line_endings(#[case] newline: &str) {
+        let source = [
+            "a",
+            "b",
+            "",
+            "c // comment",
+            "d /* block",
+            "*/ e \"x",
+            "y\" f",
+        ]
+        .join(newline);
+
+        let lines: Vec<usize> = Scanner::scan_tokens(&source, false)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .filter(|t| t.token_type == Identifier)
+            .map(|t| t.line_number)
+            .collect();
+
+        assert_eq!(lines, vec![0, 1, 3, 4, 5, 6]);
+    }
+
+    #[rstest]
+    #[case::windows("\r\n")]
+    #[case::old_mac("\r")]
+    fn test_line_continuation_with_line_endings(#[case] newline: &str) {
+        let source = format!("\"a\\{}b\" c", newline);
+        let tokens: Vec<Token> = Scanner::scan_tokens(&source, false)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        assert_eq!(tokens[0].literal, Some(Literal::String("ab".to_string())));
+        assert_eq!(tokens[1].line_number, 1);
+    }
+
+    #[rstest]
+    #[case::emoji("\"\\u{1F600}\"", "\u{1F600}")]
+    #[case::ascii("\"a\\u{62}c\"", "abc")]
+    #[case::leading_zeros("\"\\u{00e9}\"", "\u{e9}")]
+    fn test_scan_unicode_escape(#[case] input: &str, #[case] expected: &str) {
+        let token = Scanner::scan_tokens(input, false)[0].clone().unwrap();
+
+        assert_eq!(token.literal, Some(Literal::String(expected.to_string())));
+    }
+
+    #[rstest]
+    #[case::surrogate("\"\\u{D800}\"", "Invalid unicode code point 'D800'.")]
+    #[case::out_of_range("\"\\u{110000}\"", "Invalid unicode code point '110000'.")]
+    #[case::missing_brace("\"\\u1F600\"", "Expected '{' after '\\u' in unicode escape.")]
+    #[case::unclosed("\"\\u{1F600\"", "Expected '}' to close unicode escape.")]
+    #[case::no_digits("\"\\u{}\"", "Unicode escape must have between 1 and 6 hex digits.")]
+    #[case::too_many_digits(
+        "\"\\u{0000041}\"",
+        "Unicode escape must have between 1 and 6 hex digits."
+    )]
+    fn test_scan_invalid_unicode_escape(#[case] input: &str, #[case] expected: &str) {
+        let tokens = Scanner::scan_tokens(input, false);
+
+        assert_eq!(tokens[0].clone().unwrap_err().message, expected);
+        // The rest of the string is skipped rather than scanned as code
+        assert_eq!(tokens.len(), 2);
+    }
+
+    #[rstest]
+    #[case::line("a // note\nb", "// note", " note", 0)]
+    #[case::line_at_end("a // é", "// é", " é", 0)]
+    #[case::block("a /* one\ntwo */ b", "/* one\ntwo */", " one\ntwo ", 0)]
+    #[case::nested("a /* x /* y */ z */ b", "/* x /* y */ z */", " x /* y */ z ", 0)]
+    #[case::unterminated("a\n/* open", "/* open", " open", 1)]
+    fn test_scan_comment_tokens(
+        #[case] input: &str,
+        #[case] lexeme: &str,
+        #[case] text: &str,
+        #[case] line_number: usize,
+    ) {
+        let tokens: Vec<Token> = Scanner::scan_tokens(input, true)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        let comment = &tokens[1];
+        assert_eq!(comment.token_type, Comment);
+        assert_eq!(comment.lexeme, lexeme);
+        assert_eq!(&input[comment.span.start..comment.span.end], lexeme);
+        assert_eq!(comment.literal, Some(Literal::String(text.to_string())));
+        assert_eq!(comment.line_number, line_number);
+
+        // Comments are skipped by default
+        assert!(Scanner::scan_tokens(input, false)
+            .into_iter()
+            .all(|t| t.unwrap().token_type != Comment));
+    }
 }
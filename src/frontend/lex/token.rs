@@ -1,6 +1,12 @@
-use std::fmt::{self, Display};
+use std::{cell::RefCell, cmp::Ordering, collections::BTreeMap, fmt::Display, rc::Rc};
 
 use phf::phf_map;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::frontend::parse::{
+    callable::Callable,
+    class::{LoxClass, LoxInstance},
+};
 
 #[derive(PartialEq, Eq, PartialOrd, Debug, Clone)]
 pub enum TokenType {
@@ -9,6 +15,8 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
     Dot,
     Minus,
@@ -19,15 +27,24 @@ pub enum TokenType {
     QuestionMark,
     Colon,
 
-    // One or Two Character Tokens
+    // One or More Character Tokens
     Bang,
     BangEqual,
+    DotDot,
+    DotDotDot,
     Equal,
     EqualEqual,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
+    MinusEqual,
+    MinusMinus,
+    PlusEqual,
+    PlusPlus,
+    QuestionColon,
+    SlashEqual,
+    StarEqual,
 
     // Literals
     Identifier,
@@ -36,7 +53,13 @@ pub enum TokenType {
 
     // Keywords
     And,
+    Break,
+    Case,
     Class,
+    Const,
+    Continue,
+    Default,
+    Do,
     Else,
     False,
     Fun,
@@ -47,17 +70,97 @@ pub enum TokenType {
     Print,
     Return,
     Super,
+    Switch,
     This,
     True,
     Var,
     While,
 
+    // Only produced when the scanner is asked to keep comments
+    Comment,
+
     Eof,
 }
 
+impl Display for TokenType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::Minus => "-",
+            TokenType::Plus => "+",
+            TokenType::Semicolon => ";",
+            TokenType::Slash => "/",
+            TokenType::Star => "*",
+            TokenType::QuestionMark => "?",
+            TokenType::Colon => ":",
+            TokenType::Bang => "!",
+            TokenType::BangEqual => "!=",
+            TokenType::DotDot => "..",
+            TokenType::DotDotDot => "...",
+            TokenType::Equal => "=",
+            TokenType::EqualEqual => "==",
+            TokenType::Greater => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Less => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::MinusEqual => "-=",
+            TokenType::MinusMinus => "--",
+            TokenType::PlusEqual => "+=",
+            TokenType::PlusPlus => "++",
+            TokenType::QuestionColon => "?:",
+            TokenType::SlashEqual => "/=",
+            TokenType::StarEqual => "*=",
+            TokenType::And => "and",
+            TokenType::Break => "break",
+            TokenType::Case => "case",
+            TokenType::Class => "class",
+            TokenType::Const => "const",
+            TokenType::Continue => "continue",
+            TokenType::Default => "default",
+            TokenType::Do => "do",
+            TokenType::Else => "else",
+            TokenType::False => "false",
+            TokenType::Fun => "fun",
+            TokenType::For => "for",
+            TokenType::If => "if",
+            TokenType::Nil => "nil",
+            TokenType::Or => "or",
+            TokenType::Print => "print",
+            TokenType::Return => "return",
+            TokenType::Super => "super",
+            TokenType::Switch => "switch",
+            TokenType::This => "this",
+            TokenType::True => "true",
+            TokenType::Var => "var",
+            TokenType::While => "while",
+            // Tokens without a fixed spelling are described by their kind
+            TokenType::Identifier => return write!(f, "Identifier"),
+            TokenType::String => return write!(f, "String"),
+            TokenType::Number => return write!(f, "Number"),
+            TokenType::Comment => return write!(f, "comment"),
+            TokenType::Eof => return write!(f, "end of file"),
+        };
+
+        write!(f, "'{}'", symbol)
+    }
+}
+
 pub const KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and" => TokenType::And,
+    "break" => TokenType::Break,
+    "case" => TokenType::Case,
     "class" => TokenType::Class,
+    "const" => TokenType::Const,
+    "continue" => TokenType::Continue,
+    "default" => TokenType::Default,
+    "do" => TokenType::Do,
     "else" => TokenType::Else,
     "false" => TokenType::False,
     "for" => TokenType::For,
@@ -68,37 +171,213 @@ pub const KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "print" => TokenType::Print,
     "return" => TokenType::Return,
     "super" => TokenType::Super,
+    "switch" => TokenType::Switch,
     "this" => TokenType::This,
     "true" => TokenType::True,
     "var" => TokenType::Var,
     "while" => TokenType::While,
 };
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 pub enum Literal {
     Identifier(String),
     String(String),
     Number(f64),
     Boolean(bool),
+    Callable(Rc<dyn Callable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    // Lists and maps are shared, like instances, so changes made through one variable
+    // are seen through any other holding the same list or map
+    List(Rc<RefCell<Vec<Option<Literal>>>>),
+    Map(Rc<RefCell<BTreeMap<MapKey, Option<Literal>>>>),
 }
 
-impl Display for Literal {
+impl Literal {
+    pub fn list(elements: Vec<Option<Literal>>) -> Literal {
+        Literal::List(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn map(entries: BTreeMap<MapKey, Option<Literal>>) -> Literal {
+        Literal::Map(Rc::new(RefCell::new(entries)))
+    }
+}
+
+/**
+ * A value that can be used as a key in a map. Only strings and numbers are allowed.
+ */
+#[derive(Debug, Clone)]
+pub enum MapKey {
+    Number(f64),
+    String(String),
+}
+
+impl MapKey {
+    /**
+     * Converts the value to a key, or returns None if it cannot be used as one
+     */
+    pub fn from_literal(value: &Option<Literal>) -> Option<MapKey> {
+        match value {
+            // `-0` and `0` are equal, so they should find the same entry
+            Some(Literal::Number(n)) if *n == 0.0 => Some(MapKey::Number(0.0)),
+            Some(Literal::Number(n)) => Some(MapKey::Number(*n)),
+            Some(Literal::String(s)) => Some(MapKey::String(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl Ord for MapKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (MapKey::Number(l), MapKey::Number(r)) => l.total_cmp(r),
+            (MapKey::String(l), MapKey::String(r)) => l.cmp(r),
+            (MapKey::Number(_), MapKey::String(_)) => Ordering::Less,
+            (MapKey::String(_), MapKey::Number(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for MapKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for MapKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MapKey {}
+
+impl Display for MapKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Literal::Identifier(s) => write!(f, "{}", s),
-            Literal::String(s) => write!(f, "{}", s),
-            Literal::Number(n) => write!(f, "{}", n),
-            Literal::Boolean(b) => write!(f, "{}", b),
+            MapKey::Number(n) => write!(f, "{}", n),
+            MapKey::String(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::Identifier(l), Literal::Identifier(r)) => l == r,
+            (Literal::String(l), Literal::String(r)) => l == r,
+            (Literal::Number(l), Literal::Number(r)) => l == r,
+            (Literal::Boolean(l), Literal::Boolean(r)) => l == r,
+            (Literal::Callable(l), Literal::Callable(r)) => Rc::ptr_eq(l, r),
+            (Literal::Class(l), Literal::Class(r)) => Rc::ptr_eq(l, r),
+            (Literal::Instance(l), Literal::Instance(r)) => Rc::ptr_eq(l, r),
+            (Literal::List(l), Literal::List(r)) => Rc::ptr_eq(l, r),
+            (Literal::Map(l), Literal::Map(r)) => Rc::ptr_eq(l, r),
+            _ => false,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+impl Display for Literal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", display_literal(self, &mut Vec::new()))
+    }
+}
+
+/**
+ * How deeply lists and maps nested inside each other are shown before the rest is left out as `...`
+ */
+const MAX_DISPLAY_DEPTH: usize = 32;
+
+/**
+ * Formats a value, showing a list or map that contains itself as `[...]` or `{...}` where it appears inside itself,
+ * and anything nested more than `MAX_DISPLAY_DEPTH` deep as `...`.
+ * `enclosing` holds the lists and maps being formatted around the value.
+ */
+fn display_literal(literal: &Literal, enclosing: &mut Vec<*const ()>) -> String {
+    match literal {
+        Literal::Identifier(s) => s.clone(),
+        Literal::String(s) => s.clone(),
+        Literal::Number(n) => n.to_string(),
+        Literal::Boolean(b) => b.to_string(),
+        Literal::Callable(c) => c.to_string(),
+        Literal::Class(c) => c.to_string(),
+        Literal::Instance(i) => i.borrow().to_string(),
+        Literal::List(_) | Literal::Map(_) if enclosing.len() >= MAX_DISPLAY_DEPTH => {
+            "...".to_string()
+        }
+        Literal::List(list) if enclosing.contains(&Rc::as_ptr(list).cast()) => "[...]".to_string(),
+        Literal::List(list) => {
+            enclosing.push(Rc::as_ptr(list).cast());
+            let elements: Vec<String> = list
+                .borrow()
+                .iter()
+                .map(|element| display_value(element, enclosing))
+                .collect();
+            enclosing.pop();
+
+            format!("[{}]", elements.join(", "))
+        }
+        Literal::Map(map) if enclosing.contains(&Rc::as_ptr(map).cast()) => "{...}".to_string(),
+        Literal::Map(map) => {
+            enclosing.push(Rc::as_ptr(map).cast());
+            let entries: Vec<String> = map
+                .borrow()
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, display_value(value, enclosing)))
+                .collect();
+            enclosing.pop();
+
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+fn display_value(value: &Option<Literal>, enclosing: &mut Vec<*const ()>) -> String {
+    match value {
+        Some(literal) => display_literal(literal, enclosing),
+        None => "nil".to_string(),
+    }
+}
+
+/**
+ * The byte offsets of a token in the source, from `start` up to but not including `end`
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /**
+     * The column the span starts at in the source, counting from 0.
+     * Each grapheme counts as one column, except tabs, which count as `tab_width`
+     * so that the column lines up in editors using wider tabs.
+     */
+    pub fn column(&self, source: &str, tab_width: usize) -> usize {
+        let line_start = source[..self.start]
+            .rfind(['\n', '\r'])
+            .map_or(0, |index| index + 1);
+
+        source[line_start..self.start]
+            .graphemes(true)
+            .map(|g| if g == "\t" { tab_width } else { 1 })
+            .sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Literal>,
     pub line_number: usize,
+    pub span: Span,
 }
 
 impl Token {
@@ -107,39 +386,14 @@ impl Token {
         lexeme: String,
         literal: Option<Literal>,
         line_number: usize,
+        span: Span,
     ) -> Token {
         Token {
             token_type,
             lexeme,
             literal,
             line_number,
+            span,
         }
     }
 }
-
-#[derive(Debug, Clone)]
-pub struct LoxTokenError {
-    pub line_number: usize,
-    pub location: String,
-    pub message: String,
-}
-
-impl LoxTokenError {
-    pub fn new(line_number: usize, location: String, message: String) -> LoxTokenError {
-        LoxTokenError {
-            line_number,
-            location,
-            message,
-        }
-    }
-}
-
-impl fmt::Display for LoxTokenError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "Line: {}, Loc: {}, Message: {}",
-            self.line_number, self.location, self.message,
-        )
-    }
-}
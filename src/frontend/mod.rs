@@ -1,68 +1,379 @@
+mod error_report;
 mod interactive;
 mod lex;
 mod parse;
+mod script_error;
 
-use std::{error::Error, fs};
+use std::{
+    fs,
+    io::{self, Write},
+};
 
+pub use self::error_report::LoxErrorReport;
 pub use self::interactive::run_interactive;
-pub use self::lex::token::Token;
+pub use self::lex::token::{Literal, Span, Token};
+pub use self::parse::callable::HostFn;
+pub use self::parse::tree_walk_interpreter::{Interpreter, InterpreterConfig, RuntimeError};
+pub use self::script_error::LoxScriptError;
 
 use self::{
     lex::scanner::Scanner,
-    parse::{recursive_descent::Parser, tree_walk_interpreter::interpret},
+    parse::{
+        ast_printer,
+        expression::Expression,
+        recursive_descent::{ParseError, Parser},
+        resolver::Resolver,
+        statement::Statement,
+    },
 };
 
-pub fn run_file(file_path: &str) -> Result<(), Box<dyn Error>> {
+pub fn run_file(file_path: &str) -> Result<(), LoxScriptError> {
     let input = fs::read_to_string(file_path)?;
-    run(&input);
-    Ok(())
+    run_with_output(&input, &mut io::stdout())
+}
+
+/**
+ * Scans the file into tokens without running it
+ */
+pub fn scan_file(file_path: &str) -> Result<Vec<Token>, LoxScriptError> {
+    let input = fs::read_to_string(file_path)?;
+    scan(&input)
+}
+
+/**
+ * Parses the source without running it, printing each statement's AST on its own line
+ */
+pub fn print_ast(lox_str: &str) -> Result<String, LoxScriptError> {
+    let statements = parse(Parser::new(scan(lox_str)?))?;
+
+    Ok(statements
+        .iter()
+        .map(|statement| ast_printer::print_statement(statement) + "\n")
+        .collect())
+}
+
+/**
+ * Parses the source without running it, printing the statements as an indented tree
+ */
+pub fn print_ast_tree(lox_str: &str) -> Result<String, LoxScriptError> {
+    let statements = parse(Parser::new(scan(lox_str)?))?;
+
+    Ok(ast_printer::print_tree(&statements))
+}
+
+/**
+ * Scans and parses the file without running it, reporting every syntax error found,
+ * or giving back any warnings if there were no errors
+ */
+pub fn check_file(file_path: &str) -> Result<Vec<LoxErrorReport>, LoxScriptError> {
+    check(&fs::read_to_string(file_path)?)
 }
 
-pub fn run(lox_str: &str) {
-    let tokens = Scanner::scan_tokens(lox_str);
+/**
+ * Scans, parses and resolves the source without running it, reporting every error found.
+ * If there were no errors, any warnings, such as for unreachable code, are given back instead.
+ */
+pub fn check(lox_str: &str) -> Result<Vec<LoxErrorReport>, LoxScriptError> {
+    let (tokens, errors): (Vec<_>, Vec<_>) = Scanner::scan_tokens(lox_str, false)
+        .into_iter()
+        .partition(|t| t.is_ok());
 
-    let had_error = tokens.iter().any(|t| t.is_err());
+    let mut reports: Vec<LoxErrorReport> = errors.into_iter().map(|e| e.unwrap_err()).collect();
 
-    if had_error {
-        for error_report in tokens.iter().filter(|t| t.is_err()) {
-            let error_report = error_report.as_ref().unwrap_err();
-            println!(
-                "Error on line {}: {}",
-                error_report.line_number, error_report.message
-            );
+    // Parse whatever was scanned successfully, so parser errors are reported alongside the lexer's
+    let tokens = tokens.into_iter().map(|t| t.unwrap()).collect();
+    let resolved = Parser::new(tokens)
+        .parse()
+        .and_then(|statements| Resolver::resolve(&statements));
+    let warnings = match resolved {
+        Ok(warnings) => warnings,
+        Err(errors) => {
+            reports.extend(errors.into_iter().map(LoxErrorReport::from));
+            Vec::new()
         }
+    };
+
+    if reports.is_empty() {
+        return Ok(warnings.into_iter().map(LoxErrorReport::from).collect());
     }
 
-    // unwrap the tokens
-    let tokens: Vec<_> = tokens.into_iter().map(|t| t.unwrap()).collect();
+    reports.sort_by_key(|report| report.line_number);
+    Err(LoxScriptError::Syntax(reports))
+}
+
+pub fn run(lox_str: &str) -> Result<(), LoxScriptError> {
+    run_with_output(lox_str, &mut io::stdout())
+}
+
+/**
+ * Runs the source, capturing everything it prints into a string rather than stdout
+ */
+pub fn run_to_string(lox_str: &str) -> Result<String, LoxScriptError> {
+    let mut output = Vec::new();
+    run_with_output(lox_str, &mut output)?;
+    Ok(String::from_utf8_lossy(&output).into_owned())
+}
+
+/**
+ * Runs the source, writing everything it prints to the given output sink
+ */
+pub fn run_with_output(lox_str: &str, output: &mut dyn Write) -> Result<(), LoxScriptError> {
+    let mut interpreter = Interpreter::new(output);
+    run_in_interpreter(lox_str, &mut interpreter)
+}
+
+/**
+ * Runs the source with an existing interpreter, so any state it defines is kept for later runs
+ */
+pub fn run_in_interpreter(
+    lox_str: &str,
+    interpreter: &mut Interpreter,
+) -> Result<(), LoxScriptError> {
+    run_source(lox_str, interpreter, false)
+}
+
+/**
+ * Source which has been parsed and resolved, ready to be run
+ */
+enum Parsed {
+    // A lone expression, as typed at the prompt, which is evaluated and its value displayed
+    Expression(Expression),
+    Statements(Vec<Statement>),
+}
+
+/**
+ * Runs the source with an existing interpreter. Interactive input may leave off the final ';'
+ */
+fn run_source(
+    lox_str: &str,
+    interpreter: &mut Interpreter,
+    interactive: bool,
+) -> Result<(), LoxScriptError> {
+    run_parsed(&parse_source(lox_str, interactive)?, interpreter)
+}
+
+fn parse_source(lox_str: &str, interactive: bool) -> Result<Parsed, LoxScriptError> {
+    let tokens = scan(lox_str)?;
+
+    // At the prompt, a lone expression is evaluated and its value displayed
+    if interactive {
+        if let Ok(expr) = Parser::new(tokens.clone()).parse_expression() {
+            Resolver::resolve_expression(&expr).map_err(syntax_error)?;
+            return Ok(Parsed::Expression(expr));
+        }
+    }
 
     // Parse the tokens into an AST
-    let mut parser = Parser::new(tokens);
-    let expr = parser.parse();
-
-    if let Err(err) = &expr {
-        println!("Error on line {}: {}", err.token.line_number, err.message);
-        return;
-    }
-
-    let result = interpret(&expr.unwrap());
-    match result {
-        Ok(value) => {
-            println!(
-                "{}",
-                match value {
-                    Some(_) => value.unwrap().to_string(),
-                    None => "nil".to_string(),
-                }
-            );
+    let statements = parse(Parser::with_interactive(tokens, interactive))?;
+    Resolver::resolve(&statements).map_err(syntax_error)?;
+
+    Ok(Parsed::Statements(statements))
+}
+
+fn run_parsed(parsed: &Parsed, interpreter: &mut Interpreter) -> Result<(), LoxScriptError> {
+    match parsed {
+        Parsed::Expression(expr) => interpreter.display(expr)?,
+        Parsed::Statements(statements) => interpreter.interpret(statements)?,
+    }
+
+    Ok(())
+}
+
+/**
+ * Parses the tokens into statements, reporting every syntax error found
+ */
+fn parse(mut parser: Parser) -> Result<Vec<Statement>, LoxScriptError> {
+    parser.parse().map_err(syntax_error)
+}
+
+fn syntax_error(errors: Vec<ParseError>) -> LoxScriptError {
+    LoxScriptError::Syntax(errors.into_iter().map(LoxErrorReport::from).collect())
+}
+
+/**
+ * Scans the source into tokens, reporting every invalid token found
+ */
+fn scan(lox_str: &str) -> Result<Vec<Token>, LoxScriptError> {
+    let tokens = Scanner::scan_tokens(lox_str, false);
+
+    let (tokens, errors): (Vec<_>, Vec<_>) = tokens.into_iter().partition(|t| t.is_ok());
+
+    if !errors.is_empty() {
+        return Err(LoxScriptError::Syntax(
+            errors.into_iter().map(|e| e.unwrap_err()).collect(),
+        ));
+    }
+
+    Ok(tokens.into_iter().map(|t| t.unwrap()).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, path::PathBuf, process};
+
+    use super::*;
+
+    fn write_script(name: &str, contents: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("loxide-{}-{}.lox", process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_crate_root_exports_frontend_run_file() {
+        let root: fn(&str) -> Result<(), crate::LoxScriptError> = crate::run_file;
+        let frontend: fn(&str) -> Result<(), LoxScriptError> = run_file;
+
+        assert_eq!(root as usize, frontend as usize);
+    }
+
+    #[test]
+    fn test_run_file_interprets_script() {
+        let valid = write_script("valid", "print 1 + 2 * 3;");
+        let invalid = write_script("invalid", "1 +");
+
+        let valid_result = run_file(valid.to_str().unwrap());
+        let invalid_result = run_file(invalid.to_str().unwrap());
+
+        fs::remove_file(valid).unwrap();
+        fs::remove_file(invalid).unwrap();
+
+        assert!(valid_result.is_ok());
+        match invalid_result {
+            Err(LoxScriptError::Syntax(reports)) => {
+                assert_eq!(
+                    reports[0].message,
+                    "Expected Number, String, Identifier, 'true', 'false', 'nil', 'this', '(', '[', '{', 'fun' or 'if' but found end of file"
+                )
+            }
+            other => panic!("Expected a syntax error, got {:?}", other),
         }
-        Err(err) => {
-            print!("{}", err.message);
-            if let Some(token) = err.token {
-                println!(" [line {}]", token.line_number);
-            } else {
-                println!();
+    }
+
+    #[test]
+    fn test_check_reports_lexer_and_parser_errors() {
+        assert!(check("var a = 1;\nprint a;").is_ok());
+
+        match check("var = 1;\nprint \"a\" @;\nprint (;\nprint 1;") {
+            Err(LoxScriptError::Syntax(reports)) => {
+                let lines: Vec<usize> = reports.iter().map(|r| r.line_number).collect();
+                assert_eq!(lines, vec![0, 1, 2]);
             }
+            other => panic!("Expected a syntax error, got {:?}", other),
         }
     }
+
+    #[test]
+    fn test_check_gives_back_warnings() {
+        let warnings = check("fun f() {\n  return 1;\n  print 2;\n}").unwrap();
+
+        assert_eq!(
+            warnings,
+            vec![LoxErrorReport::new(
+                1,
+                "return".to_string(),
+                "Code after 'return' is unreachable.".to_string()
+            )]
+        );
+        assert_eq!(check("fun f() { return 1; }").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_warnings_do_not_stop_source_running() {
+        let source = "fun f() { return 1; print 2; } print f();";
+
+        assert_eq!(run_to_string(source).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn test_check_does_not_run_source() {
+        assert!(check("print -\"a\";").is_ok());
+    }
+
+    #[test]
+    fn test_run_to_string_captures_print_output() {
+        assert_eq!(run_to_string("print 1; print 2;").unwrap(), "1\n2\n");
+    }
+
+    #[test]
+    fn test_run_with_output_writes_to_sink() {
+        let mut output: Vec<u8> = Vec::new();
+
+        run_with_output("print \"a\" + \"b\"; 1 + 1; print 2 * 3;", &mut output).unwrap();
+
+        assert_eq!(output, b"ab\n6\n");
+    }
+
+    #[test]
+    fn test_interactive_displays_expression_value() {
+        let mut output = Vec::new();
+        run_source("1 + 2 * 3", &mut Interpreter::new(&mut output), true).unwrap();
+
+        assert_eq!(output, b"7\n");
+    }
+
+    #[test]
+    fn test_file_mode_requires_statements() {
+        let script = write_script("expression", "1 + 2");
+        let result = run_file(script.to_str().unwrap());
+        fs::remove_file(script).unwrap();
+
+        match result {
+            Err(LoxScriptError::Syntax(reports)) => {
+                assert_eq!(reports[0].message, "Expected ';' but found end of file")
+            }
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+        assert_eq!(run_to_string("{}").unwrap(), "");
+    }
+
+    #[test]
+    fn test_run_to_string_reports_runtime_error() {
+        let result = run_to_string("print 1; print -\"a\";");
+
+        assert!(matches!(result, Err(LoxScriptError::Runtime(_))));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        assert_eq!(run_to_string("var x = 1; x += 4; print x;").unwrap(), "5\n");
+        assert_eq!(
+            run_to_string("var x = 10; x -= 2; x *= 3; x /= 4; print x;").unwrap(),
+            "6\n"
+        );
+        assert_eq!(
+            run_to_string("var s = \"a\"; s += \"b\"; print s;").unwrap(),
+            "ab\n"
+        );
+    }
+
+    #[test]
+    fn test_compound_assignment_requires_variable_target() {
+        match run_to_string("1 += 2;") {
+            Err(LoxScriptError::Syntax(reports)) => {
+                assert_eq!(reports[0].message, "Invalid assignment target.")
+            }
+            other => panic!("Expected a syntax error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_runtime_error_span() {
+        let source = "var a = 1;\nprint a + nil;";
+
+        match run_to_string(source) {
+            Err(LoxScriptError::Runtime(err)) => {
+                let span = err.span().unwrap();
+                assert_eq!(&source[span.start..span.end], "+");
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_run_file_reports_missing_file() {
+        let result = run_file("this/file/does/not/exist.lox");
+
+        assert!(matches!(result, Err(LoxScriptError::Io(_))));
+    }
 }
@@ -1,32 +1,432 @@
-use crate::frontend::lex::token::Literal;
+use crate::frontend::lex::token::{Literal, Token};
 
-use super::expression::*;
+use super::{
+    expression::*,
+    statement::{FunctionDeclaration, Statement},
+    with_stack,
+};
 
-#[allow(dead_code)]
 pub fn print(expr: &Expression) -> String {
+    with_stack(|| print_expression(expr))
+}
+
+fn print_expression(expr: &Expression) -> String {
     match expr {
+        Expression::Assign { name, value } => {
+            parenthesise(&format!("= {}", name.lexeme), vec![value])
+        }
         Expression::Binary {
             left,
             operator,
             right,
         } => parenthesise(&operator.lexeme, vec![left, right]),
+        Expression::Block {
+            statements, value, ..
+        } => {
+            let mut parts: Vec<String> = statements.iter().map(print_statement).collect();
+            parts.extend(value.iter().map(|value| print(value)));
+
+            format!("(block {})", parts.join(" "))
+        }
+        Expression::Call {
+            callee,
+            arguments,
+            keyword_arguments,
+            ..
+        } => {
+            let mut parts = vec![print(callee)];
+            parts.extend(arguments.iter().map(print));
+            parts.extend(
+                keyword_arguments
+                    .iter()
+                    .map(|(name, argument)| format!("{}: {}", name.lexeme, print(argument))),
+            );
+            format!("(call {})", parts.join(" "))
+        }
         Expression::Ternary {
             condition,
             then_branch,
             else_branch,
+            ..
         } => parenthesise("ternary", vec![condition, then_branch, else_branch]),
-        Expression::Grouping(expr) => parenthesise("group", vec![expr]),
+        Expression::Get { object, name } => format!("(. {} {})", print(object), name.lexeme),
+        Expression::Grouping { expression, .. } => parenthesise("group", vec![expression]),
+        Expression::Function { declaration, .. } => print_function(declaration),
+        Expression::Index { object, index, .. } => parenthesise("index", vec![object, index]),
+        Expression::List { elements, .. } => parenthesise("list", elements.iter().collect()),
+        Expression::Slice {
+            object, start, end, ..
+        } => parenthesise("slice", vec![object, start, end]),
+        Expression::Logical {
+            left,
+            operator,
+            right,
+        } => parenthesise(&operator.lexeme, vec![left, right]),
+        Expression::Map { entries, .. } => parenthesise(
+            "map",
+            entries
+                .iter()
+                .flat_map(|(key, value)| [key, value])
+                .collect(),
+        ),
         Expression::Literal(expr) => match expr.as_ref() {
             Some(Literal::Identifier(id)) => id.clone(),
             Some(Literal::String(string)) => string.clone(),
             Some(Literal::Number(number)) => number.to_string(),
             Some(Literal::Boolean(boolean)) => boolean.to_string(),
+            Some(literal) => literal.to_string(),
             None => "nil".to_string(),
         },
+        Expression::Postfix { name, operator } => {
+            format!("({}{})", name.lexeme, operator.lexeme)
+        }
+        Expression::Set {
+            object,
+            name,
+            operator,
+            value,
+        } => format!(
+            "({}= (. {} {}) {})",
+            operator
+                .as_ref()
+                .map_or("", |operator| operator.lexeme.as_str()),
+            print(object),
+            name.lexeme,
+            print(value)
+        ),
+        Expression::This(_) => "this".to_string(),
         Expression::Unary { operator, right } => parenthesise(&operator.lexeme, vec![right]),
+        Expression::Variable(name) => name.lexeme.clone(),
+    }
+}
+
+pub fn print_statement(statement: &Statement) -> String {
+    match statement {
+        Statement::Block(statements) => parenthesise_statements("block", statements),
+        Statement::Class {
+            name,
+            methods,
+            getters,
+            class_methods,
+        } => {
+            let mut parts = vec![format!("class {}", name.lexeme)];
+            parts.extend(methods.iter().map(|method| print_function(method)));
+            parts.extend(getters.iter().map(|getter| print_getter(getter)));
+            parts.extend(
+                class_methods
+                    .iter()
+                    .map(|method| format!("(class {})", print_function(method))),
+            );
+
+            format!("({})", parts.join(" "))
+        }
+        Statement::Break { label, .. } => print_jump("break", label),
+        Statement::Const { name, initializer } => {
+            parenthesise(&format!("const {}", name.lexeme), vec![initializer])
+        }
+        Statement::Continue { label, .. } => print_jump("continue", label),
+        Statement::DoWhile {
+            body,
+            condition,
+            label,
+            ..
+        } => print_labeled(
+            label,
+            format!("(do {} {})", print_statement(body), print(condition)),
+        ),
+        Statement::Expression(expr) => parenthesise(";", vec![expr]),
+        Statement::ForIn {
+            variable,
+            iterable,
+            body,
+            label,
+        } => print_labeled(
+            label,
+            format!(
+                "(for {} in {} {})",
+                variable.lexeme,
+                print(iterable),
+                print_statement(body)
+            ),
+        ),
+        Statement::Function(declaration) => print_function(declaration),
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => match else_branch {
+            Some(else_branch) => format!(
+                "(if {} {} {})",
+                print(condition),
+                print_statement(then_branch),
+                print_statement(else_branch)
+            ),
+            None => format!("(if {} {})", print(condition), print_statement(then_branch)),
+        },
+        Statement::Return { value, .. } => parenthesise("return", value.iter().collect()),
+        Statement::Print(expr) => parenthesise("print", vec![expr]),
+        Statement::Switch {
+            value,
+            cases,
+            default,
+        } => {
+            let mut parts = vec![format!("switch {}", print(value))];
+            parts.extend(cases.iter().map(|(case_value, statements)| {
+                parenthesise_statements(&format!("case {}", print(case_value)), statements)
+            }));
+            parts.extend(
+                default
+                    .iter()
+                    .map(|statements| parenthesise_statements("default", statements)),
+            );
+
+            format!("({})", parts.join(" "))
+        }
+        Statement::Var { name, initializer } => parenthesise(
+            &format!("var {}", name.lexeme),
+            initializer.iter().collect(),
+        ),
+        Statement::While {
+            condition,
+            body,
+            label,
+            ..
+        } => print_labeled(
+            label,
+            format!("(while {} {})", print(condition), print_statement(body)),
+        ),
     }
 }
 
+/**
+ * Prints the statements as an indented tree, with one node per line and its children indented beneath it.
+ * Expressions are leaves of the tree, printed on one line as by `print`.
+ */
+pub fn print_tree(statements: &[Statement]) -> String {
+    let mut tree = String::new();
+    tree_statements(&mut tree, statements, 0);
+    tree
+}
+
+fn tree_statements(tree: &mut String, statements: &[Statement], depth: usize) {
+    for statement in statements {
+        tree_statement(tree, statement, depth);
+    }
+}
+
+fn tree_statement(tree: &mut String, statement: &Statement, depth: usize) {
+    let child = depth + 1;
+
+    match statement {
+        Statement::Block(statements) => {
+            tree_line(tree, depth, "block");
+            tree_statements(tree, statements, child);
+        }
+        Statement::Break { label, .. } => tree_line(tree, depth, &tree_jump("break", label)),
+        Statement::Class {
+            name,
+            methods,
+            getters,
+            class_methods,
+        } => {
+            tree_line(tree, depth, &format!("class {}", name.lexeme));
+            for method in methods {
+                tree_function(tree, "", method, child);
+            }
+            for getter in getters {
+                let name = getter.name.as_ref().map_or("", |name| name.lexeme.as_str());
+                tree_line(tree, child, &format!("get {}", name));
+                tree_statements(tree, &getter.body, child + 1);
+            }
+            for method in class_methods {
+                tree_function(tree, "class ", method, child);
+            }
+        }
+        Statement::Const { name, initializer } => {
+            tree_line(tree, depth, &format!("const {}", name.lexeme));
+            tree_line(tree, child, &print(initializer));
+        }
+        Statement::Continue { label, .. } => tree_line(tree, depth, &tree_jump("continue", label)),
+        Statement::DoWhile {
+            body,
+            condition,
+            label,
+            ..
+        } => {
+            tree_line(tree, depth, &tree_labeled(label, "do"));
+            tree_statement(tree, body, child);
+            tree_line(tree, child, &print(condition));
+        }
+        Statement::Expression(expr) => {
+            tree_line(tree, depth, "expression");
+            tree_line(tree, child, &print(expr));
+        }
+        Statement::ForIn {
+            variable,
+            iterable,
+            body,
+            label,
+        } => {
+            let header = format!("for {} in", variable.lexeme);
+            tree_line(tree, depth, &tree_labeled(label, &header));
+            tree_line(tree, child, &print(iterable));
+            tree_statement(tree, body, child);
+        }
+        Statement::Function(declaration) => tree_function(tree, "", declaration, depth),
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+            ..
+        } => {
+            tree_line(tree, depth, "if");
+            tree_line(tree, child, &print(condition));
+            tree_line(tree, child, "then");
+            tree_statement(tree, then_branch, child + 1);
+            if let Some(else_branch) = else_branch {
+                tree_line(tree, child, "else");
+                tree_statement(tree, else_branch, child + 1);
+            }
+        }
+        Statement::Print(expr) => {
+            tree_line(tree, depth, "print");
+            tree_line(tree, child, &print(expr));
+        }
+        Statement::Return { value, .. } => {
+            tree_line(tree, depth, "return");
+            if let Some(value) = value {
+                tree_line(tree, child, &print(value));
+            }
+        }
+        Statement::Switch {
+            value,
+            cases,
+            default,
+        } => {
+            tree_line(tree, depth, "switch");
+            tree_line(tree, child, &print(value));
+            for (case_value, statements) in cases {
+                tree_line(tree, child, &format!("case {}", print(case_value)));
+                tree_statements(tree, statements, child + 1);
+            }
+            if let Some(statements) = default {
+                tree_line(tree, child, "default");
+                tree_statements(tree, statements, child + 1);
+            }
+        }
+        Statement::Var { name, initializer } => {
+            tree_line(tree, depth, &format!("var {}", name.lexeme));
+            if let Some(initializer) = initializer {
+                tree_line(tree, child, &print(initializer));
+            }
+        }
+        Statement::While {
+            condition,
+            body,
+            label,
+            ..
+        } => {
+            tree_line(tree, depth, &tree_labeled(label, "while"));
+            tree_line(tree, child, &print(condition));
+            tree_statement(tree, body, child);
+        }
+    }
+}
+
+fn tree_function(tree: &mut String, prefix: &str, declaration: &FunctionDeclaration, depth: usize) {
+    tree_line(
+        tree,
+        depth,
+        &format!("{}{}", prefix, print_signature(declaration)),
+    );
+    tree_statements(tree, &declaration.body, depth + 1);
+}
+
+fn tree_jump(keyword: &str, label: &Option<Token>) -> String {
+    match label {
+        Some(label) => format!("{} {}", keyword, label.lexeme),
+        None => keyword.to_string(),
+    }
+}
+
+fn tree_labeled(label: &Option<Token>, keyword: &str) -> String {
+    match label {
+        Some(label) => format!("{}: {}", label.lexeme, keyword),
+        None => keyword.to_string(),
+    }
+}
+
+fn tree_line(tree: &mut String, depth: usize, text: &str) {
+    tree.push_str(&"  ".repeat(depth));
+    tree.push_str(text);
+    tree.push('\n');
+}
+
+fn print_jump(keyword: &str, label: &Option<Token>) -> String {
+    match label {
+        Some(label) => format!("({} {})", keyword, label.lexeme),
+        None => format!("({})", keyword),
+    }
+}
+
+fn print_labeled(label: &Option<Token>, printed_loop: String) -> String {
+    match label {
+        Some(label) => format!("({}: {})", label.lexeme, printed_loop),
+        None => printed_loop,
+    }
+}
+
+fn print_function(declaration: &FunctionDeclaration) -> String {
+    parenthesise_statements(&print_signature(declaration), &declaration.body)
+}
+
+/**
+ * Prints a function's name and parameters, such as `fun f(a b=2 ...rest)`
+ */
+fn print_signature(declaration: &FunctionDeclaration) -> String {
+    let mut params: Vec<String> = declaration
+        .params
+        .iter()
+        .zip(&declaration.defaults)
+        .map(|(param, default)| match default {
+            Some(default) => format!("{}={}", param.lexeme, print(default)),
+            None => param.lexeme.clone(),
+        })
+        .collect();
+    if let Some(rest) = &declaration.rest {
+        params.push(format!("...{}", rest.lexeme));
+    }
+
+    let name = match &declaration.name {
+        Some(name) => format!("fun {}", name.lexeme),
+        None => "fun ".to_string(),
+    };
+
+    format!("{}({})", name, params.join(" "))
+}
+
+fn print_getter(declaration: &FunctionDeclaration) -> String {
+    let name = declaration
+        .name
+        .as_ref()
+        .map_or("", |name| name.lexeme.as_str());
+
+    parenthesise_statements(&format!("get {}", name), &declaration.body)
+}
+
+fn parenthesise_statements(name: &str, statements: &[Statement]) -> String {
+    let mut result = String::new();
+    result.push('(');
+    result.push_str(name);
+    for statement in statements {
+        result.push(' ');
+        result.push_str(&print_statement(statement));
+    }
+    result.push(')');
+    result
+}
+
 fn parenthesise(name: &str, exprs: Vec<&Expression>) -> String {
     let mut result = String::new();
     result.push('(');
@@ -41,7 +441,7 @@ fn parenthesise(name: &str, exprs: Vec<&Expression>) -> String {
 
 #[cfg(test)]
 mod test {
-    use crate::frontend::lex::token::{Literal, Token, TokenType};
+    use crate::frontend::lex::token::{Literal, Span, Token, TokenType};
 
     use super::*;
 
@@ -55,6 +455,7 @@ mod test {
                     lexeme: "-".to_string(),
                     literal: None,
                     line_number: 1,
+                    span: Span::default(),
                 },
                 right: Box::new(Expression::Literal(Some(Literal::Number(123.0)))),
             }),
@@ -63,13 +464,78 @@ mod test {
                 lexeme: "*".to_string(),
                 literal: None,
                 line_number: 1,
+                span: Span::default(),
             },
-            right: Box::new(Expression::Grouping(Box::new(Expression::Literal(Some(
-                Literal::Number(45.67),
-            ))))),
+            right: Box::new(Expression::Grouping {
+                paren: Token {
+                    token_type: TokenType::LeftParen,
+                    lexeme: "(".to_string(),
+                    literal: None,
+                    line_number: 1,
+                    span: Span::default(),
+                },
+                expression: Box::new(Expression::Literal(Some(Literal::Number(45.67)))),
+            }),
         };
         let result = print(&expr);
 
         assert_eq!(result, "(* (- 123) (group 45.67))");
     }
+
+    #[test]
+    fn test_print_statements() {
+        let statements = [
+            Statement::Var {
+                name: Token::new(
+                    TokenType::Identifier,
+                    "a".to_string(),
+                    None,
+                    0,
+                    Span::default(),
+                ),
+                initializer: None,
+            },
+            Statement::Print(Expression::Literal(Some(Literal::Number(1.0)))),
+            Statement::Expression(Expression::Literal(None)),
+        ];
+
+        let printed: Vec<String> = statements.iter().map(print_statement).collect();
+
+        assert_eq!(printed, vec!["(var a)", "(print 1)", "(; nil)"]);
+    }
+
+    #[test]
+    fn test_print_long_chain() {
+        let source = format!("a{};", " + 1".repeat(10_000));
+        let printed = crate::frontend::print_ast_tree(&source).unwrap();
+
+        assert!(printed.starts_with(&format!("expression\n  {}a 1)", "(+ ".repeat(10_000))));
+    }
+
+    #[test]
+    fn test_print_tree() {
+        let source = "var a = 1;
+            fun f(x, y = 2) { return x + y; }
+            while (a < 3) { if (a == 1) print f(a); else a += 1; }";
+        assert_eq!(
+            crate::frontend::print_ast_tree(source).unwrap(),
+            "var a
+  1
+fun f(x y=2)
+  return
+    (+ x y)
+while
+  (< a 3)
+  block
+    if
+      (== a 1)
+      then
+        print
+          (call f a)
+      else
+        expression
+          (= a (+ a 1))
+"
+        );
+    }
 }
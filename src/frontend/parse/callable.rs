@@ -0,0 +1,349 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::frontend::lex::token::{Literal, Token};
+
+use super::{
+    class::LoxInstance,
+    environment::Environment,
+    statement::FunctionDeclaration,
+    tree_walk_interpreter::{Interpreter, RuntimeError},
+};
+
+/**
+ * A value that can be called with a list of arguments
+ */
+pub trait Callable: fmt::Debug + fmt::Display {
+    fn arity(&self) -> usize;
+
+    /**
+     * The most arguments which may be given, or None if there is no limit.
+     * Any beyond `arity` are optional.
+     */
+    fn max_arity(&self) -> Option<usize> {
+        Some(self.arity())
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: &[Option<Literal>],
+    ) -> Result<Option<Literal>, RuntimeError>;
+
+    /**
+     * Calls the function with some arguments bound to parameters by name.
+     * Only functions declared in Lox have named parameters, so others refuse these calls.
+     */
+    fn call_with_keywords(
+        &self,
+        _interpreter: &mut Interpreter,
+        _arguments: &[Option<Literal>],
+        keyword_arguments: &[(Token, Option<Literal>)],
+    ) -> Result<Option<Literal>, RuntimeError> {
+        let name = keyword_arguments.first().map(|(name, _)| name.clone());
+        Err(RuntimeError {
+            message: format!("{} doesn't take keyword arguments.", self),
+            token: name,
+        })
+    }
+}
+
+pub type NativeFn =
+    fn(&mut Interpreter, &[Option<Literal>]) -> Result<Option<Literal>, RuntimeError>;
+
+/**
+ * A function implemented in Rust and exposed to Lox programs
+ */
+pub struct NativeFunction {
+    pub name: &'static str,
+    pub arity: usize,
+    pub max_arity: Option<usize>,
+    pub function: NativeFn,
+}
+
+impl NativeFunction {
+    pub fn new(name: &'static str, arity: usize, function: NativeFn) -> NativeFunction {
+        NativeFunction {
+            name,
+            arity,
+            max_arity: Some(arity),
+            function,
+        }
+    }
+
+    /**
+     * Allows up to `optional_arity` more arguments, which the function must check the presence of
+     */
+    pub fn with_optional(self, optional_arity: usize) -> NativeFunction {
+        NativeFunction {
+            max_arity: Some(self.arity + optional_arity),
+            ..self
+        }
+    }
+
+    /**
+     * Allows any number of arguments after the required ones
+     */
+    pub fn variadic(self) -> NativeFunction {
+        NativeFunction {
+            max_arity: None,
+            ..self
+        }
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        self.max_arity
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: &[Option<Literal>],
+    ) -> Result<Option<Literal>, RuntimeError> {
+        (self.function)(interpreter, arguments)
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+pub type HostFn = Box<dyn Fn(&[Option<Literal>]) -> Result<Option<Literal>, RuntimeError>>;
+
+/**
+ * A native function registered by the program embedding the interpreter
+ */
+pub struct HostFunction {
+    pub name: String,
+    pub arity: usize,
+    pub function: HostFn,
+}
+
+impl Callable for HostFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter,
+        arguments: &[Option<Literal>],
+    ) -> Result<Option<Literal>, RuntimeError> {
+        (self.function)(arguments)
+    }
+}
+
+impl fmt::Debug for HostFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HostFunction({})", self.name)
+    }
+}
+
+impl fmt::Display for HostFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+/**
+ * A function declared in a Lox program, along with the scope it was declared in
+ */
+pub struct LoxFunction {
+    declaration: Rc<FunctionDeclaration>,
+    closure: Rc<RefCell<Environment>>,
+    // The instance a method was accessed on, available to its body as `this`
+    this: Option<Rc<RefCell<LoxInstance>>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(declaration: Rc<FunctionDeclaration>, closure: Rc<RefCell<Environment>>) -> Self {
+        LoxFunction {
+            declaration,
+            closure,
+            this: None,
+            is_initializer: false,
+        }
+    }
+
+    /**
+     * Creates a method of a class. A method named `init` is the class's initializer.
+     */
+    pub fn method(declaration: Rc<FunctionDeclaration>, closure: Rc<RefCell<Environment>>) -> Self {
+        let is_initializer = matches!(&declaration.name, Some(name) if name.lexeme == "init");
+
+        LoxFunction {
+            is_initializer,
+            ..LoxFunction::new(declaration, closure)
+        }
+    }
+
+    pub fn name(&self) -> Option<&Token> {
+        self.declaration.name.as_ref()
+    }
+
+    /**
+     * Creates a copy of this method bound to the given instance
+     */
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> Self {
+        LoxFunction {
+            declaration: self.declaration.clone(),
+            closure: self.closure.clone(),
+            this: Some(instance),
+            is_initializer: self.is_initializer,
+        }
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        // Parameters with defaults all come after those without
+        self.declaration
+            .defaults
+            .iter()
+            .take_while(|default| default.is_none())
+            .count()
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        match self.declaration.rest {
+            Some(_) => None,
+            None => Some(self.declaration.params.len()),
+        }
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: &[Option<Literal>],
+    ) -> Result<Option<Literal>, RuntimeError> {
+        let bound = (0..self.declaration.params.len())
+            .map(|index| arguments.get(index).cloned())
+            .collect();
+
+        self.call_bound(interpreter, bound, arguments)
+    }
+
+    fn call_with_keywords(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: &[Option<Literal>],
+        keyword_arguments: &[(Token, Option<Literal>)],
+    ) -> Result<Option<Literal>, RuntimeError> {
+        let params = &self.declaration.params;
+
+        if self.declaration.rest.is_none() && arguments.len() > params.len() {
+            return RuntimeError::new(format!(
+                "Expected at most {} positional arguments but got {}.",
+                params.len(),
+                arguments.len()
+            ));
+        }
+
+        let mut bound: Vec<Option<Option<Literal>>> = (0..params.len())
+            .map(|index| arguments.get(index).cloned())
+            .collect();
+
+        for (name, argument) in keyword_arguments {
+            let index = match params.iter().position(|param| param.lexeme == name.lexeme) {
+                Some(index) => index,
+                None => {
+                    return RuntimeError::with_token(
+                        format!("{} has no parameter named '{}'.", self, name.lexeme),
+                        name.clone(),
+                    )
+                }
+            };
+
+            if bound[index].is_some() {
+                return RuntimeError::with_token(
+                    format!("Argument '{}' is given more than once.", name.lexeme),
+                    name.clone(),
+                );
+            }
+            bound[index] = Some(argument.clone());
+        }
+
+        let missing = params
+            .iter()
+            .zip(&self.declaration.defaults)
+            .zip(&bound)
+            .find(|((_, default), argument)| default.is_none() && argument.is_none());
+
+        if let Some(((param, _), _)) = missing {
+            return RuntimeError::new(format!(
+                "Missing argument for parameter '{}'.",
+                param.lexeme
+            ));
+        }
+
+        self.call_bound(interpreter, bound, arguments)
+    }
+}
+
+impl LoxFunction {
+    /**
+     * Runs the function with an argument bound to each parameter, or None for those left to their defaults.
+     * Positional arguments beyond the parameters are collected into the rest parameter.
+     */
+    fn call_bound(
+        &self,
+        interpreter: &mut Interpreter,
+        bound: Vec<Option<Option<Literal>>>,
+        arguments: &[Option<Literal>],
+    ) -> Result<Option<Literal>, RuntimeError> {
+        let mut environment = Environment::with_enclosing(self.closure.clone());
+
+        if let Some(this) = &self.this {
+            environment.define("this", Some(Literal::Instance(this.clone())));
+        }
+
+        if let Some(rest) = &self.declaration.rest {
+            let extra = arguments
+                .iter()
+                .skip(self.declaration.params.len())
+                .cloned()
+                .collect();
+            environment.define(&rest.lexeme, Some(Literal::list(extra)));
+        }
+
+        let value = interpreter.execute_function_body(&self.declaration, environment, bound)?;
+
+        // An initializer always gives back the instance it initialised
+        match (&self.this, self.is_initializer) {
+            (Some(this), true) => Ok(Some(Literal::Instance(this.clone()))),
+            _ => Ok(value),
+        }
+    }
+}
+
+impl fmt::Debug for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.declaration.name {
+            Some(name) => write!(f, "LoxFunction({})", name.lexeme),
+            None => write!(f, "LoxFunction"),
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.declaration.name {
+            Some(name) => write!(f, "<fn {}>", name.lexeme),
+            None => write!(f, "<fn>"),
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::frontend::lex::token::Literal;
+
+use super::{
+    callable::{Callable, LoxFunction},
+    environment::Environment,
+    statement::FunctionDeclaration,
+};
+
+/**
+ * A class declared in a Lox program. Calling it creates a new instance.
+ */
+pub struct LoxClass {
+    pub name: String,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    getters: HashMap<String, Rc<LoxFunction>>,
+    class_methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        methods: &[Rc<FunctionDeclaration>],
+        getters: &[Rc<FunctionDeclaration>],
+        class_methods: &[Rc<FunctionDeclaration>],
+        closure: Rc<RefCell<Environment>>,
+    ) -> Self {
+        let by_name = |declarations: &[Rc<FunctionDeclaration>]| {
+            declarations
+                .iter()
+                .filter_map(|declaration| {
+                    let name = declaration.name.as_ref()?.lexeme.clone();
+                    let method = LoxFunction::method(declaration.clone(), closure.clone());
+                    Some((name, Rc::new(method)))
+                })
+                .collect()
+        };
+
+        LoxClass {
+            name,
+            methods: by_name(methods),
+            getters: by_name(getters),
+            class_methods: by_name(class_methods),
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<&Rc<LoxFunction>> {
+        self.methods.get(name)
+    }
+
+    /**
+     * Finds a method declared without parameters, which is run when the property is accessed
+     */
+    pub fn find_getter(&self, name: &str) -> Option<&Rc<LoxFunction>> {
+        self.getters.get(name)
+    }
+
+    /**
+     * Finds a method declared with a leading `class`, which is called on the class itself
+     */
+    pub fn find_class_method(&self, name: &str) -> Option<&Rc<LoxFunction>> {
+        self.class_methods.get(name)
+    }
+
+    /**
+     * The number of arguments needed to create an instance, which are passed to `init`
+     */
+    pub fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+}
+
+impl fmt::Debug for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoxClass({})", self.name)
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/**
+ * An instance of a class, holding its own fields
+ */
+#[derive(Clone)]
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    fields: HashMap<String, Option<Literal>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn get_field(&self, name: &str) -> Option<Option<Literal>> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn set_field(&mut self, name: &str, value: Option<Literal>) {
+        self.fields.insert(name.to_string(), value);
+    }
+}
+
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoxInstance({})", self.class.name)
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use crate::frontend::{run_to_string, LoxScriptError};
+
+    #[rstest]
+    #[case::print_class("class A {} print A;", "A\n")]
+    #[case::print_instance("class A {} print A();", "A instance\n")]
+    #[case::fields("class A {} var a = A(); a.x = 1; a.y = a.x + 1; print a.y;", "2\n")]
+    #[case::set_evaluates_to_value("class A {} var a = A(); print a.x = 3;", "3\n")]
+    #[case::compound_field("class A {} var a = A(); a.x = 1; a.x += 2; print a.x;", "3\n")]
+    #[case::method(
+        "class A { greet(name) { return \"hi \" + name; } } print A().greet(\"b\");",
+        "hi b\n"
+    )]
+    #[case::this(
+        "class A { get() { return this.x; } } var a = A(); a.x = 5; print a.get();",
+        "5\n"
+    )]
+    #[case::bound_method(
+        "class A { get() { return this.x; } } var a = A(); a.x = 1; var get = a.get; a.x = 2; print get();",
+        "2\n"
+    )]
+    #[case::this_in_closure(
+        "class A { f() { return fun () { return this.x; }; } } var a = A(); a.x = 4; print a.f()();",
+        "4\n"
+    )]
+    #[case::init(
+        "class P { init(x, y) { this.x = x; this.y = y; } } var p = P(1, 2); print p.x + p.y;",
+        "3\n"
+    )]
+    #[case::init_returns_instance(
+        "class A { init() { this.x = 1; return; } } var a = A(); print a.init() == a;",
+        "true\n"
+    )]
+    #[case::field_shadows_method(
+        "class A { m() { return 1; } } var a = A(); a.m = 2; print a.m;",
+        "2\n"
+    )]
+    #[case::identity(
+        "class A {} var a = A(); print a == a; print a == A();",
+        "true\nfalse\n"
+    )]
+    fn test_classes(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::area(
+        "class Circle { init(radius) { this.radius = radius; } area { return 3 * this.radius * this.radius; } }
+         print Circle(2).area;",
+        "12\n"
+    )]
+    #[case::runs_on_each_access(
+        "class Counter { init() { this.count = 0; } next { this.count += 1; return this.count; } }
+         var c = Counter(); c.next; c.next; print c.next;",
+        "3\n"
+    )]
+    #[case::field_shadows_getter(
+        "class A { g { return 1; } } var a = A(); a.g = 2; print a.g;",
+        "2\n"
+    )]
+    fn test_getters(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::factory(
+        "class Point { init(x, y) { this.x = x; this.y = y; } class origin() { return Point(0, 0); } }
+         var p = Point.origin(); print p; print p.x + p.y;",
+        "Point instance\n0\n"
+    )]
+    #[case::with_arguments(
+        "class Math { class square(n) { return n * n; } } print Math.square(3);",
+        "9\n"
+    )]
+    #[case::same_name_as_method(
+        "class A { class name() { return \"class\"; } name() { return \"instance\"; } }
+         print A.name(); print A().name();",
+        "class\ninstance\n"
+    )]
+    fn test_class_methods(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::plus(
+        "class Vec2 {
+           init(x, y) { this.x = x; this.y = y; }
+           plus(other) { return Vec2(this.x + other.x, this.y + other.y); }
+         }
+         var v = Vec2(1, 2) + Vec2(3, 4); print v.x; print v.y;",
+        "4\n6\n"
+    )]
+    #[case::all_operators(
+        "class N {
+           init(n) { this.n = n; }
+           plus(o) { return \"plus \" + o; }
+           minus(o) { return \"minus \" + o; }
+           times(o) { return \"times \" + o; }
+           divide(o) { return \"divide \" + o; }
+         }
+         var n = N(1); print n + 1; print n - 2; print n * 3; print n / 4;",
+        "plus 1\nminus 2\ntimes 3\ndivide 4\n"
+    )]
+    #[case::chained(
+        "class C { init(n) { this.n = n; } plus(o) { return C(this.n + o); } }
+         print (C(1) + 2 + 3).n;",
+        "6\n"
+    )]
+    #[case::compound_assignment(
+        "class C { init(n) { this.n = n; } plus(o) { return C(this.n + o); } }
+         var c = C(1); c += 4; print c.n;",
+        "5\n"
+    )]
+    #[case::compound_property_assignment(
+        "class C { init(n) { this.n = n; } plus(o) { return C(this.n + o.n); } }
+         class H {} var h = H(); h.c = C(1); h.c += C(4); print h.c.n;",
+        "5\n"
+    )]
+    #[case::right_operand(
+        "class A { plus(o) { return \"plus \" + o; } times(o) { return \"times \" + o; } }
+         print 1 + A(); print 2 * A();",
+        "plus 1\ntimes 2\n"
+    )]
+    #[case::left_operand_first(
+        "class A { init(name) { this.name = name; } plus(o) { return this.name; } }
+         print A(\"left\") + A(\"right\");",
+        "left\n"
+    )]
+    fn test_operator_overloading(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::no_method("class A {} A() + 1;", "Operands must be numbers or strings. [line 0]")]
+    #[case::right_operand_of_minus(
+        "class A { minus(o) { return 1; } } 1 - A();",
+        "Operands must be numbers. [line 0]"
+    )]
+    #[case::right_operand_of_divide(
+        "class A { divide(o) { return 1; } } 1 / A();",
+        "Operands must be numbers. [line 0]"
+    )]
+    #[case::wrong_arity(
+        "class A { plus() { return 1; } } A() + 1;",
+        "Expected 0 arguments but got 1. [line 0]"
+    )]
+    fn test_operator_overloading_errors(#[case] source: &str, #[case] expected: &str) {
+        match run_to_string(source) {
+            Err(err @ LoxScriptError::Runtime(_)) => assert_eq!(err.to_string(), expected),
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::print(
+        "class P { init(x) { this.x = x; } toString() { return \"P(\" + this.x + \")\"; } } print P(1);",
+        "P(1)\n"
+    )]
+    #[case::concatenate_right(
+        "class A { toString() { return \"a\"; } } print \"it is \" + A();",
+        "it is a\n"
+    )]
+    #[case::concatenate_left("class A { toString() { return \"a\"; } } print A() + \"!\";", "a!\n")]
+    #[case::str_native("class A { toString() { return \"a\"; } } print len(str(A()));", "1\n")]
+    #[case::non_string_result("class A { toString() { return 42; } } print A();", "42\n")]
+    #[case::default_text(
+        "class A {} print A(); print \"x \" + A();",
+        "A instance\nx A instance\n"
+    )]
+    #[case::compound_assignment(
+        "class P { toString() { return \"P!\"; } } var s = \"x\"; s += P(); print s;",
+        "xP!\n"
+    )]
+    #[case::compound_property_assignment(
+        "class P { toString() { return \"P!\"; } } class H {} var h = H(); h.s = \"x\"; h.s += P(); print h.s;",
+        "xP!\n"
+    )]
+    #[case::plus_overload_first(
+        "class A { plus(o) { return \"plus\"; } toString() { return \"a\"; } } print A() + \"!\";",
+        "plus\n"
+    )]
+    fn test_to_string(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::undefined_property("class A {} A().x;", "Undefined property 'x'. [line 0]")]
+    #[case::undefined_class_method("class A {} A.x();", "Undefined class method 'x'. [line 0]")]
+    #[case::class_method_on_instance(
+        "class A { class make() {} } A().make();",
+        "Undefined property 'make'. [line 0]"
+    )]
+    #[case::get_on_non_instance("var a = 1; a.x;", "Only instances have properties. [line 0]")]
+    #[case::set_on_non_instance("var a = 1; a.x = 2;", "Only instances have fields. [line 0]")]
+    #[case::init_arity(
+        "class A { init(a) {} } A();",
+        "Expected 1 arguments but got 0. [line 0]"
+    )]
+    #[case::no_init_arity("class A {} A(1);", "Expected 0 arguments but got 1. [line 0]")]
+    #[case::to_string_arity(
+        "class A { toString(x) {} } print A();",
+        "Expected 1 arguments but got 0. [line 0]"
+    )]
+    #[case::getter_error(
+        "class A { g { return -\"a\"; } } A().g;",
+        "Operands must be numbers. [line 0]"
+    )]
+    fn test_class_errors(#[case] source: &str, #[case] expected: &str) {
+        match run_to_string(source) {
+            Err(err @ LoxScriptError::Runtime(_)) => assert_eq!(err.to_string(), expected),
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::frontend::lex::token::{Literal, Token};
+
+use super::tree_walk_interpreter::RuntimeError;
+
+/**
+ * Stores the values bound to variable names.
+ * Names not found in this scope are looked up in the enclosing scope, if there is one.
+ */
+pub struct Environment {
+    values: HashMap<String, Option<Literal>>,
+    // The names in this scope bound with `const`, which can't be assigned to
+    constants: HashSet<String>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing: None,
+        }
+    }
+
+    /**
+     * Creates a new scope nested inside the given one
+     */
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            constants: HashSet::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /**
+     * Binds a new variable, replacing any existing variable with the same name
+     */
+    pub fn define(&mut self, name: &str, value: Option<Literal>) {
+        self.constants.remove(name);
+        self.values.insert(name.to_string(), value);
+    }
+
+    /**
+     * Binds a new variable which can't be assigned to, replacing any existing variable with the same name
+     */
+    pub fn define_constant(&mut self, name: &str, value: Option<Literal>) {
+        self.values.insert(name.to_string(), value);
+        self.constants.insert(name.to_string());
+    }
+
+    /**
+     * Errors if the name is bound with `const` in this scope, as a constant can't be declared again
+     */
+    pub fn check_redeclaration(&self, name: &Token) -> Result<(), RuntimeError> {
+        if self.constants.contains(&name.lexeme) {
+            return Err(RuntimeError {
+                message: format!("Cannot redeclare constant '{}'.", name.lexeme),
+                token: Some(name.clone()),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Option<Literal>, RuntimeError> {
+        match (self.values.get(&name.lexeme), &self.enclosing) {
+            (Some(value), _) => Ok(value.clone()),
+            (None, Some(enclosing)) => enclosing.borrow().get(name),
+            (None, None) => RuntimeError::undefined_variable(name.clone()),
+        }
+    }
+
+    /**
+     * Assigns to an existing variable, erroring if it has not been defined or is a constant.
+     * Returns the assigned value.
+     */
+    pub fn assign(
+        &mut self,
+        name: &Token,
+        value: Option<Literal>,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        if self.constants.contains(&name.lexeme) {
+            return RuntimeError::with_token(
+                format!("Cannot assign to constant '{}'.", name.lexeme),
+                name.clone(),
+            );
+        }
+
+        match (self.values.get_mut(&name.lexeme), &self.enclosing) {
+            (Some(existing), _) => {
+                *existing = value.clone();
+                Ok(value)
+            }
+            (None, Some(enclosing)) => enclosing.borrow_mut().assign(name, value),
+            (None, None) => RuntimeError::undefined_variable(name.clone()),
+        }
+    }
+}
@@ -1,21 +1,123 @@
+use std::rc::Rc;
+
 use crate::frontend::lex::token::{Literal, Token};
 
-#[derive(Debug, PartialEq, PartialOrd)]
+use super::statement::{FunctionDeclaration, Statement};
+
+#[derive(Debug, PartialEq)]
 pub enum Expression {
+    Assign {
+        name: Token,
+        value: Box<Expression>,
+    },
     Binary {
         left: Box<Expression>,
         operator: Token,
         right: Box<Expression>,
     },
+    Block {
+        brace: Token,
+        statements: Vec<Statement>,
+        value: Option<Box<Expression>>,
+    },
+    Call {
+        callee: Box<Expression>,
+        paren: Token,
+        arguments: Vec<Expression>,
+        // Arguments given as `name: value`, bound to the parameter with that name
+        keyword_arguments: Vec<(Token, Expression)>,
+    },
     Ternary {
         condition: Box<Expression>,
+        question: Token,
         then_branch: Box<Expression>,
         else_branch: Box<Expression>,
     },
-    Grouping(Box<Expression>),
+    Get {
+        object: Box<Expression>,
+        name: Token,
+    },
+    Grouping {
+        paren: Token,
+        expression: Box<Expression>,
+    },
+    Function {
+        keyword: Token,
+        declaration: Rc<FunctionDeclaration>,
+    },
+    Index {
+        object: Box<Expression>,
+        bracket: Token,
+        index: Box<Expression>,
+    },
+    List {
+        bracket: Token,
+        elements: Vec<Expression>,
+    },
     Literal(Option<Literal>),
+    // `object[start..end]`, the part of a string from `start` up to but not including `end`
+    Slice {
+        object: Box<Expression>,
+        bracket: Token,
+        start: Box<Expression>,
+        end: Box<Expression>,
+    },
+    // Only evaluates the right operand if it is needed
+    Logical {
+        left: Box<Expression>,
+        operator: Token,
+        right: Box<Expression>,
+    },
+    Map {
+        brace: Token,
+        entries: Vec<(Expression, Expression)>,
+    },
+    // `x++` or `x--`, which changes the variable and evaluates to its value beforehand
+    Postfix {
+        name: Token,
+        operator: Token,
+    },
+    // A compound assignment such as `a.b += 1` keeps the binary operator to apply
+    Set {
+        object: Box<Expression>,
+        name: Token,
+        operator: Option<Token>,
+        value: Box<Expression>,
+    },
+    This(Token),
     Unary {
         operator: Token,
         right: Box<Expression>,
     },
+    Variable(Token),
+}
+
+impl Expression {
+    /**
+     * The token used to locate runtime errors raised while evaluating this expression.
+     * Literals have no token, as evaluating them can never fail.
+     */
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            Expression::Assign { name, .. } => Some(name),
+            Expression::Binary { operator, .. } => Some(operator),
+            Expression::Block { brace, .. } => Some(brace),
+            Expression::Call { paren, .. } => Some(paren),
+            Expression::Ternary { question, .. } => Some(question),
+            Expression::Get { name, .. } => Some(name),
+            Expression::Grouping { paren, .. } => Some(paren),
+            Expression::Function { keyword, .. } => Some(keyword),
+            Expression::Index { bracket, .. } => Some(bracket),
+            Expression::List { bracket, .. } => Some(bracket),
+            Expression::Literal(_) => None,
+            Expression::Slice { bracket, .. } => Some(bracket),
+            Expression::Logical { operator, .. } => Some(operator),
+            Expression::Map { brace, .. } => Some(brace),
+            Expression::Postfix { operator, .. } => Some(operator),
+            Expression::Set { name, .. } => Some(name),
+            Expression::This(keyword) => Some(keyword),
+            Expression::Unary { operator, .. } => Some(operator),
+            Expression::Variable(name) => Some(name),
+        }
+    }
 }
@@ -1,4 +1,25 @@
 pub mod ast_printer;
+pub mod callable;
+pub mod class;
+pub mod environment;
 pub mod expression;
+pub mod natives;
+pub mod random;
 pub mod recursive_descent;
+pub mod resolver;
+pub mod statement;
 pub mod tree_walk_interpreter;
+
+/**
+ * When less than this much stack is left, `with_stack` continues on a newly allocated stack segment
+ */
+const STACK_RED_ZONE: usize = 128 * 1024;
+const STACK_SEGMENT_SIZE: usize = 2 * 1024 * 1024;
+
+/**
+ * Runs one level of a recursive walk over the tree, growing the stack first if it is nearly full,
+ * so deeply nested code can't overflow it
+ */
+pub fn with_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT_SIZE, f)
+}
@@ -0,0 +1,1302 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::frontend::lex::token::Literal;
+
+use super::{
+    callable::{Callable, NativeFunction},
+    random::Random,
+    tree_walk_interpreter::{
+        stringify, structurally_equal, to_index, to_integer, type_name, Interpreter, RuntimeError,
+    },
+};
+
+type NativeResult = Result<Option<Literal>, RuntimeError>;
+
+/**
+ * The most decimal places `fmt` will format a number to
+ */
+const MAX_DECIMALS: usize = 100;
+
+/**
+ * The most elements `range` will produce, so a mistaken bound can't exhaust memory
+ */
+const MAX_RANGE_LENGTH: i64 = 10_000_000;
+
+/**
+ * The native functions defined in the global environment of every interpreter
+ */
+pub fn globals() -> Vec<NativeFunction> {
+    vec![
+        NativeFunction::new("clock", 0, clock),
+        NativeFunction::new("len", 1, len),
+        NativeFunction::new("substr", 3, substr),
+        NativeFunction::new("ord", 1, ord),
+        NativeFunction::new("chr", 1, chr),
+        NativeFunction::new("upper", 1, upper),
+        NativeFunction::new("lower", 1, lower),
+        NativeFunction::new("str", 1, str),
+        NativeFunction::new("num", 1, num),
+        NativeFunction::new("fmt", 2, fmt),
+        NativeFunction::new("floor", 1, floor),
+        NativeFunction::new("ceil", 1, ceil),
+        NativeFunction::new("round", 1, round),
+        NativeFunction::new("abs", 1, abs),
+        NativeFunction::new("sqrt", 1, sqrt),
+        NativeFunction::new("pow", 2, pow),
+        NativeFunction::new("min", 2, min).variadic(),
+        NativeFunction::new("max", 2, max).variadic(),
+        NativeFunction::new("type", 1, type_of),
+        NativeFunction::new("arity", 1, arity),
+        NativeFunction::new("equals", 2, equals),
+        NativeFunction::new("copy", 1, copy),
+        NativeFunction::new("join", 2, join),
+        NativeFunction::new("split", 1, split).with_optional(1),
+        NativeFunction::new("trim", 1, trim),
+        NativeFunction::new("replace", 3, replace),
+        NativeFunction::new("starts_with", 2, starts_with),
+        NativeFunction::new("ends_with", 2, ends_with),
+        NativeFunction::new("contains", 2, contains),
+        NativeFunction::new("range", 1, range).with_optional(2),
+        NativeFunction::new("push", 2, push),
+        NativeFunction::new("pop", 1, pop),
+        NativeFunction::new("append", 2, append),
+        NativeFunction::new("map", 2, map),
+        NativeFunction::new("filter", 2, filter),
+        NativeFunction::new("reduce", 3, reduce),
+        NativeFunction::new("random", 0, random),
+        NativeFunction::new("seed", 1, seed),
+        NativeFunction::new("write", 1, write),
+        NativeFunction::new("assert", 1, assert).with_optional(1),
+        NativeFunction::new("error", 1, error),
+    ]
+}
+
+/**
+ * Returns the number of seconds since the Unix epoch
+ */
+fn clock(_: &mut Interpreter, _: &[Option<Literal>]) -> NativeResult {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| RuntimeError {
+            message: format!("System clock error: {}", err),
+            token: None,
+        })?;
+
+    Ok(Some(Literal::Number(now.as_secs_f64())))
+}
+
+/**
+ * Returns a pseudo-random number in the range [0, 1)
+ */
+fn random(interpreter: &mut Interpreter, _: &[Option<Literal>]) -> NativeResult {
+    Ok(Some(Literal::Number(interpreter.random().next_f64())))
+}
+
+/**
+ * Seeds the generator used by `random`, so the numbers it returns are the same on every run
+ */
+fn seed(interpreter: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let seed = expect_number("seed", &arguments[0])?;
+    *interpreter.random() = Random::from_seed(seed.to_bits());
+
+    Ok(None)
+}
+
+/**
+ * Writes the printed form of a value without a trailing newline, unlike `print`
+ */
+fn write(interpreter: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let text = interpreter.stringify(&arguments[0])?;
+    interpreter.write(&text)?;
+
+    Ok(None)
+}
+
+/**
+ * Stops the program with an error if the condition is falsey, including the message if one is given
+ */
+fn assert(interpreter: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    if interpreter.is_truthy(&arguments[0]) {
+        return Ok(None);
+    }
+
+    match arguments.get(1) {
+        Some(message) => RuntimeError::new(format!("Assertion failed: {}", stringify(message))),
+        None => RuntimeError::new("Assertion failed.".to_string()),
+    }
+}
+
+/**
+ * Stops the program with the given message as a runtime error
+ */
+fn error(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    RuntimeError::new(stringify(&arguments[0]))
+}
+
+/**
+ * Returns the number of graphemes in a string, counted the same way as the scanner does,
+ * or the number of elements in a list
+ */
+fn len(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let length = match &arguments[0] {
+        Some(Literal::String(string)) => string.graphemes(true).count(),
+        Some(Literal::List(list)) => list.borrow().len(),
+        _ => return RuntimeError::new("'len' expects a string or list argument.".to_string()),
+    };
+
+    Ok(Some(Literal::Number(length as f64)))
+}
+
+/**
+ * Returns the `length` graphemes of a string starting at grapheme `start`, so positions agree with `len`
+ */
+fn substr(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("substr", &arguments[0])?;
+    let start = expect_count("substr", &arguments[1])?;
+    let length = expect_count("substr", &arguments[2])?;
+
+    if start + length > string.graphemes(true).count() {
+        return RuntimeError::new("Substring out of range.".to_string());
+    }
+
+    Ok(Some(Literal::String(
+        string.graphemes(true).skip(start).take(length).collect(),
+    )))
+}
+
+/**
+ * Returns the Unicode code point of a string holding a single character
+ */
+fn ord(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let mut chars = expect_string("ord", &arguments[0])?.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => Ok(Some(Literal::Number(char as u32 as f64))),
+        _ => RuntimeError::new("'ord' expects a string of one character.".to_string()),
+    }
+}
+
+/**
+ * Returns the string holding the single character with the given Unicode code point
+ */
+fn chr(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let code_point = expect_number("chr", &arguments[0])?;
+
+    match to_index(code_point, None)
+        .ok()
+        .and_then(|code_point| u32::try_from(code_point).ok())
+        .and_then(char::from_u32)
+    {
+        Some(char) => Ok(Some(Literal::String(char.to_string()))),
+        None => RuntimeError::new(format!(
+            "'chr' expects a Unicode code point, not {}.",
+            code_point
+        )),
+    }
+}
+
+/**
+ * Returns a copy of a string with every character converted to upper case
+ */
+fn upper(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("upper", &arguments[0])?;
+
+    Ok(Some(Literal::String(string.to_uppercase())))
+}
+
+/**
+ * Returns a copy of a string with every character converted to lower case
+ */
+fn lower(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("lower", &arguments[0])?;
+
+    Ok(Some(Literal::String(string.to_lowercase())))
+}
+
+/**
+ * Converts any value to the string it would be printed as
+ */
+fn str(interpreter: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    Ok(Some(Literal::String(interpreter.stringify(&arguments[0])?)))
+}
+
+/**
+ * Returns the name of the value's type, such as "number" or "nil"
+ */
+fn type_of(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    Ok(Some(Literal::String(type_name(&arguments[0]).to_string())))
+}
+
+/**
+ * Returns the number of parameters a function takes
+ */
+fn arity(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    match &arguments[0] {
+        Some(Literal::Callable(function)) => Ok(Some(Literal::Number(function.arity() as f64))),
+        Some(Literal::Class(class)) => Ok(Some(Literal::Number(class.arity() as f64))),
+        _ => RuntimeError::new("'arity' expects a function argument.".to_string()),
+    }
+}
+
+/**
+ * Returns whether two values are equal, comparing lists and maps by their contents where `==` compares them by identity
+ */
+fn equals(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    Ok(Some(Literal::Boolean(structurally_equal(
+        &arguments[0],
+        &arguments[1],
+    ))))
+}
+
+/**
+ * Returns a shallow copy of a list, map or instance, which can be changed without changing the original.
+ * Any lists, maps or instances inside it are still shared. Other values are returned unchanged.
+ */
+fn copy(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    Ok(match &arguments[0] {
+        Some(Literal::List(list)) => Some(Literal::list(list.borrow().clone())),
+        Some(Literal::Map(map)) => Some(Literal::map(map.borrow().clone())),
+        Some(Literal::Instance(instance)) => Some(Literal::Instance(Rc::new(RefCell::new(
+            instance.borrow().clone(),
+        )))),
+        value => value.clone(),
+    })
+}
+
+/**
+ * Joins the printed form of each element of a list into a string, with `separator` between each
+ */
+fn join(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let list = expect_list("join", &arguments[0])?;
+    let separator = expect_string("join", &arguments[1])?;
+
+    let elements: Vec<String> = list.borrow().iter().map(stringify).collect();
+
+    Ok(Some(Literal::String(elements.join(separator))))
+}
+
+/**
+ * Splits a string into a list of the parts between each `separator`.
+ * Without a separator, or given nil, it splits on runs of whitespace and leaves out empty parts.
+ */
+fn split(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("split", &arguments[0])?;
+
+    let parts: Vec<&str> = match arguments.get(1) {
+        None | Some(None) => string.split_whitespace().collect(),
+        Some(separator) => match expect_string("split", separator)? {
+            "" => return RuntimeError::new("'split' can't split on an empty string.".to_string()),
+            separator => string.split(separator).collect(),
+        },
+    };
+
+    Ok(Some(Literal::list(
+        parts
+            .into_iter()
+            .map(|part| Some(Literal::String(part.to_string())))
+            .collect(),
+    )))
+}
+
+/**
+ * Returns a copy of a string without any whitespace at its start or end
+ */
+fn trim(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("trim", &arguments[0])?;
+
+    Ok(Some(Literal::String(string.trim().to_string())))
+}
+
+/**
+ * Returns a copy of `haystack` with every occurrence of `needle` replaced by `replacement`
+ */
+fn replace(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let haystack = expect_string("replace", &arguments[0])?;
+    let needle = expect_string("replace", &arguments[1])?;
+    let replacement = expect_string("replace", &arguments[2])?;
+
+    if needle.is_empty() {
+        return RuntimeError::new("'replace' can't replace an empty string.".to_string());
+    }
+
+    Ok(Some(Literal::String(haystack.replace(needle, replacement))))
+}
+
+/**
+ * Returns whether a string begins with `prefix`
+ */
+fn starts_with(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("starts_with", &arguments[0])?;
+    let prefix = expect_string("starts_with", &arguments[1])?;
+
+    Ok(Some(Literal::Boolean(string.starts_with(prefix))))
+}
+
+/**
+ * Returns whether a string finishes with `suffix`
+ */
+fn ends_with(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("ends_with", &arguments[0])?;
+    let suffix = expect_string("ends_with", &arguments[1])?;
+
+    Ok(Some(Literal::Boolean(string.ends_with(suffix))))
+}
+
+/**
+ * Returns whether `substring` appears anywhere in a string
+ */
+fn contains(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("contains", &arguments[0])?;
+    let substring = expect_string("contains", &arguments[1])?;
+
+    Ok(Some(Literal::Boolean(string.contains(substring))))
+}
+
+/**
+ * Returns a list of the integers from `start` up to but not including `end`, counting by `step`.
+ * Given one argument, it is the end and the start is 0. A negative step counts down.
+ */
+fn range(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let (start, end) = match arguments.get(1) {
+        Some(end) => (
+            expect_integer("range", &arguments[0])?,
+            expect_integer("range", end)?,
+        ),
+        None => (0, expect_integer("range", &arguments[0])?),
+    };
+    let step = match arguments.get(2) {
+        Some(step) => expect_integer("range", step)?,
+        None => 1,
+    };
+
+    if step == 0 {
+        return RuntimeError::new("'range' can't have a step of 0.".to_string());
+    }
+
+    // The bounds are at most 2^53 in size, so neither the span nor the count can overflow
+    let span = end - start;
+    let length = if span.signum() == step.signum() {
+        (span.abs() + step.abs() - 1) / step.abs()
+    } else {
+        0
+    };
+
+    if length > MAX_RANGE_LENGTH {
+        return RuntimeError::new(format!(
+            "'range' can't produce more than {} elements.",
+            MAX_RANGE_LENGTH
+        ));
+    }
+
+    Ok(Some(Literal::list(
+        (0..length)
+            .map(|index| Some(Literal::Number((start + index * step) as f64)))
+            .collect(),
+    )))
+}
+
+/**
+ * Adds a value to the end of a list
+ */
+fn push(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let list = expect_list("push", &arguments[0])?;
+    list.borrow_mut().push(arguments[1].clone());
+
+    Ok(None)
+}
+
+/**
+ * Removes the last element of a list and returns it, or returns nil if the list is empty
+ */
+fn pop(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let list = expect_list("pop", &arguments[0])?;
+    let element = list.borrow_mut().pop();
+
+    Ok(element.flatten())
+}
+
+/**
+ * Adds every element of the second list to the end of the first
+ */
+fn append(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let list = expect_list("append", &arguments[0])?;
+    // Copied first, as a list may be appended to itself
+    let elements = expect_list("append", &arguments[1])?.borrow().clone();
+    list.borrow_mut().extend(elements);
+
+    Ok(None)
+}
+
+/**
+ * Returns a new list holding the result of calling the function on each element of the list
+ */
+fn map(interpreter: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let list = expect_list_copy("map", &arguments[0])?;
+    let function = expect_function("map", &arguments[1], 1)?;
+
+    let mut mapped = Vec::new();
+    for element in list {
+        mapped.push(interpreter.call_callback(function.as_ref(), std::slice::from_ref(&element))?);
+    }
+
+    Ok(Some(Literal::list(mapped)))
+}
+
+/**
+ * Returns a new list holding the elements of the list the function returns a truthy value for
+ */
+fn filter(interpreter: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let list = expect_list_copy("filter", &arguments[0])?;
+    let function = expect_function("filter", &arguments[1], 1)?;
+
+    let mut kept = Vec::new();
+    for element in list {
+        let keep = interpreter.call_callback(function.as_ref(), std::slice::from_ref(&element))?;
+        if interpreter.is_truthy(&keep) {
+            kept.push(element);
+        }
+    }
+
+    Ok(Some(Literal::list(kept)))
+}
+
+/**
+ * Combines the elements of the list into one value, starting from `initial` and calling the function
+ * with the value so far and each element in turn
+ */
+fn reduce(interpreter: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let list = expect_list_copy("reduce", &arguments[0])?;
+    let function = expect_function("reduce", &arguments[1], 2)?;
+
+    list.into_iter()
+        .try_fold(arguments[2].clone(), |value, element| {
+            interpreter.call_callback(function.as_ref(), &[value, element])
+        })
+}
+
+/**
+ * Rounds a number down to the nearest integer
+ */
+fn floor(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let number = expect_number("floor", &arguments[0])?;
+
+    Ok(Some(Literal::Number(number.floor())))
+}
+
+/**
+ * Rounds a number up to the nearest integer
+ */
+fn ceil(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let number = expect_number("ceil", &arguments[0])?;
+
+    Ok(Some(Literal::Number(number.ceil())))
+}
+
+/**
+ * Rounds a number to the nearest integer, with halves rounded away from zero
+ */
+fn round(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let number = expect_number("round", &arguments[0])?;
+
+    Ok(Some(Literal::Number(number.round())))
+}
+
+/**
+ * Returns the magnitude of a number, without its sign
+ */
+fn abs(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let number = expect_number("abs", &arguments[0])?;
+
+    Ok(Some(Literal::Number(number.abs())))
+}
+
+/**
+ * Returns the square root of a number, which must not be negative
+ */
+fn sqrt(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let number = expect_number("sqrt", &arguments[0])?;
+
+    if number < 0.0 {
+        return RuntimeError::new(format!(
+            "Can't take the square root of a negative number, {}.",
+            number
+        ));
+    }
+
+    Ok(Some(Literal::Number(number.sqrt())))
+}
+
+/**
+ * Raises `base` to the power of `exponent`
+ */
+fn pow(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let base = expect_number("pow", &arguments[0])?;
+    let exponent = expect_number("pow", &arguments[1])?;
+
+    Ok(Some(Literal::Number(base.powf(exponent))))
+}
+
+/**
+ * Returns the smallest of two or more numbers
+ */
+fn min(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    extreme("min", arguments, f64::min)
+}
+
+/**
+ * Returns the largest of two or more numbers
+ */
+fn max(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    extreme("max", arguments, f64::max)
+}
+
+/**
+ * Folds every argument into one number with `pick`, erroring if any argument isn't a number
+ */
+fn extreme(
+    function: &str,
+    arguments: &[Option<Literal>],
+    pick: fn(f64, f64) -> f64,
+) -> NativeResult {
+    let first = expect_number(function, &arguments[0])?;
+
+    arguments[1..]
+        .iter()
+        .try_fold(first, |extreme, argument| {
+            Ok(pick(extreme, expect_number(function, argument)?))
+        })
+        .map(|extreme| Some(Literal::Number(extreme)))
+}
+
+/**
+ * Formats a number as a string with exactly `decimals` digits after the decimal point, rounding as needed
+ */
+fn fmt(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let number = expect_number("fmt", &arguments[0])?;
+    let decimals = expect_count("fmt", &arguments[1])?;
+
+    if decimals > MAX_DECIMALS {
+        return RuntimeError::new(format!(
+            "'fmt' can format at most {} decimal places.",
+            MAX_DECIMALS
+        ));
+    }
+
+    Ok(Some(Literal::String(format!("{:.*}", decimals, number))))
+}
+
+/**
+ * Parses a string as a number
+ */
+fn num(_: &mut Interpreter, arguments: &[Option<Literal>]) -> NativeResult {
+    let string = expect_string("num", &arguments[0])?;
+
+    match string.trim().parse::<f64>() {
+        Ok(number) if number.is_finite() => Ok(Some(Literal::Number(number))),
+        _ => RuntimeError::new(format!("Cannot convert '{}' to a number.", string)),
+    }
+}
+
+fn expect_string<'a>(function: &str, value: &'a Option<Literal>) -> Result<&'a str, RuntimeError> {
+    match value {
+        Some(Literal::String(string)) => Ok(string),
+        _ => Err(RuntimeError {
+            message: format!("'{}' expects a string argument.", function),
+            token: None,
+        }),
+    }
+}
+
+fn expect_list<'a>(
+    function: &str,
+    value: &'a Option<Literal>,
+) -> Result<&'a Rc<RefCell<Vec<Option<Literal>>>>, RuntimeError> {
+    match value {
+        Some(Literal::List(list)) => Ok(list),
+        _ => Err(RuntimeError {
+            message: format!("'{}' expects a list argument.", function),
+            token: None,
+        }),
+    }
+}
+
+/**
+ * Expects a list, returning a copy of its elements so a callback can change the list without
+ * affecting which elements are visited
+ */
+fn expect_list_copy(
+    function: &str,
+    value: &Option<Literal>,
+) -> Result<Vec<Option<Literal>>, RuntimeError> {
+    Ok(expect_list(function, value)?.borrow().clone())
+}
+
+/**
+ * Expects a function which can be called with `argument_count` arguments
+ */
+fn expect_function(
+    function: &str,
+    value: &Option<Literal>,
+    argument_count: usize,
+) -> Result<Rc<dyn Callable>, RuntimeError> {
+    match value {
+        Some(Literal::Callable(callable))
+            if callable.arity() <= argument_count
+                && callable
+                    .max_arity()
+                    .is_none_or(|max_arity| argument_count <= max_arity) =>
+        {
+            Ok(callable.clone())
+        }
+        _ => Err(RuntimeError {
+            message: format!(
+                "'{}' expects a function taking {} {}.",
+                function,
+                argument_count,
+                if argument_count == 1 {
+                    "argument"
+                } else {
+                    "arguments"
+                }
+            ),
+            token: None,
+        }),
+    }
+}
+
+fn expect_number(function: &str, value: &Option<Literal>) -> Result<f64, RuntimeError> {
+    match value {
+        Some(Literal::Number(number)) => Ok(*number),
+        _ => Err(RuntimeError {
+            message: format!("'{}' expects a number argument.", function),
+            token: None,
+        }),
+    }
+}
+
+/**
+ * Expects a number that is an integer small enough to be represented exactly
+ */
+fn expect_integer(function: &str, value: &Option<Literal>) -> Result<i64, RuntimeError> {
+    let number = expect_number(function, value)?;
+
+    to_integer(number, None).map_err(|_| RuntimeError {
+        message: format!("'{}' expects an integer argument.", function),
+        token: None,
+    })
+}
+
+/**
+ * Expects a number that is a non-negative integer, such as a length or an index
+ */
+fn expect_count(function: &str, value: &Option<Literal>) -> Result<usize, RuntimeError> {
+    let number = expect_number(function, value)?;
+
+    to_index(number, None).map_err(|_| RuntimeError {
+        message: format!("'{}' expects a non-negative integer argument.", function),
+        token: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use crate::frontend::{
+        parse::tree_walk_interpreter::Interpreter, run_in_interpreter, run_to_string,
+        LoxScriptError,
+    };
+
+    fn runtime_error(source: &str) -> String {
+        match run_to_string(source) {
+            Err(LoxScriptError::Runtime(err)) => err.message,
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_clock() {
+        assert_eq!(run_to_string("print clock() > 0;").unwrap(), "true\n");
+    }
+
+    #[test]
+    fn test_random_is_reproducible_with_seed() {
+        let source = "seed(7); print random(); print random(); print random();";
+        let first = run_to_string(source).unwrap();
+
+        assert_eq!(run_to_string(source).unwrap(), first);
+        assert_ne!(
+            run_to_string("seed(8); print random(); print random(); print random();").unwrap(),
+            first
+        );
+    }
+
+    #[test]
+    fn test_random_range() {
+        let source = "seed(1); var i = 0; var ok = true;
+            while (i < 100) { var n = random(); ok = ok ? n >= 0 ? n < 1 : false : false; i += 1; }
+            print ok;";
+
+        assert_eq!(run_to_string(source).unwrap(), "true\n");
+    }
+
+    #[test]
+    fn test_write_has_no_newline() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+
+        run_in_interpreter("write(\"a\"); write(\"b\");", &mut interpreter).unwrap();
+        drop(interpreter);
+
+        assert_eq!(String::from_utf8(output).unwrap(), "ab");
+    }
+
+    #[test]
+    fn test_write_then_print() {
+        assert_eq!(
+            run_to_string("write(1); write(nil); print \"!\";").unwrap(),
+            "1nil!\n"
+        );
+    }
+
+    #[rstest]
+    #[case::len("print len(\"abc\") == 3;", "true\n")]
+    #[case::len_empty("print len(\"\");", "0\n")]
+    #[case::len_combining_accent("print len(\"cafe\u{301}\");", "4\n")]
+    #[case::len_precomposed("print len(\"café\");", "4\n")]
+    #[case::len_emoji("print len(\"🦀!\");", "2\n")]
+    #[case::len_list("print len([1, 2, 3]);", "3\n")]
+    #[case::len_nested_list("print len([[1, 2], nil]);", "2\n")]
+    #[case::len_empty_list("print len([]);", "0\n")]
+    #[case::substr("print substr(\"hello\", 1, 3) == \"ell\";", "true\n")]
+    #[case::substr_whole("print substr(\"hello\", 0, 5);", "hello\n")]
+    #[case::substr_graphemes("print substr(\"cafe\u{301}s\", 3, 2);", "e\u{301}s\n")]
+    #[case::substr_empty("print substr(\"hello\", 5, 0);", "\n")]
+    #[case::ord("print ord(\"A\") == 65;", "true\n")]
+    #[case::ord_multi_byte("print ord(\"é\");", "233\n")]
+    #[case::ord_emoji("print ord(\"🦀\");", "129408\n")]
+    #[case::chr("print chr(97) == \"a\";", "true\n")]
+    #[case::chr_emoji("print chr(129408);", "🦀\n")]
+    #[case::chr_ord_round_trip("print chr(ord(\"z\"));", "z\n")]
+    #[case::upper("print upper(\"Hello, world!\");", "HELLO, WORLD!\n")]
+    #[case::upper_non_ascii("print upper(\"café\") == \"CAFÉ\";", "true\n")]
+    #[case::upper_expands("print upper(\"straße\");", "STRASSE\n")]
+    #[case::lower("print lower(\"Hello, World!\");", "hello, world!\n")]
+    #[case::lower_non_ascii("print lower(\"ÀÉÎ\");", "àéî\n")]
+    #[case::lower_empty("print len(lower(\"\"));", "0\n")]
+    #[case::str_number("print str(42) == \"42\";", "true\n")]
+    #[case::str_fraction("print str(3.5) + \"!\";", "3.5!\n")]
+    #[case::str_boolean("print str(true) == \"true\";", "true\n")]
+    #[case::str_nil("print str(nil) == \"nil\";", "true\n")]
+    #[case::str_string("print str(\"a\");", "a\n")]
+    #[case::num("print num(\"3.5\") == 3.5;", "true\n")]
+    #[case::num_integer("print num(\"42\") + 1;", "43\n")]
+    #[case::num_whitespace("print num(\" 7 \");", "7\n")]
+    fn test_string_natives(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::rounds_down("fmt(3.14159, 2)", "3.14")]
+    #[case::rounds_up("fmt(2.71828, 3)", "2.718")]
+    #[case::rounds_up_carry("fmt(9.996, 2)", "10.00")]
+    #[case::pads("fmt(1.5, 3)", "1.500")]
+    #[case::zero_decimals("fmt(2.6, 0)", "3")]
+    #[case::integer("fmt(42, 1)", "42.0")]
+    #[case::negative("fmt(-1.25, 1)", "-1.2")]
+    fn test_fmt(#[case] call: &str, #[case] expected: &str) {
+        let source = format!("print {} == \"{}\";", call, expected);
+
+        assert_eq!(run_to_string(&source).unwrap(), "true\n");
+    }
+
+    #[rstest]
+    #[case::number_type("fmt(\"1\", 2);", "'fmt' expects a number argument.")]
+    #[case::decimals_type("fmt(1, \"2\");", "'fmt' expects a number argument.")]
+    #[case::negative_decimals("fmt(1, -1);", "'fmt' expects a non-negative integer argument.")]
+    #[case::fractional_decimals("fmt(1, 1.5);", "'fmt' expects a non-negative integer argument.")]
+    #[case::too_many_decimals("fmt(1, 101);", "'fmt' can format at most 100 decimal places.")]
+    #[case::arity("fmt(1);", "Expected 2 arguments but got 1.")]
+    fn test_fmt_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::floor_positive("floor(2.7)", "2")]
+    #[case::floor_negative("floor(-2.3)", "-3")]
+    #[case::floor_integer("floor(4)", "4")]
+    #[case::ceil_positive("ceil(2.1)", "3")]
+    #[case::ceil_negative("ceil(-2.7)", "-2")]
+    #[case::ceil_integer("ceil(-4)", "-4")]
+    #[case::round_down("round(2.4)", "2")]
+    #[case::round_up("round(2.6)", "3")]
+    #[case::round_half("round(2.5)", "3")]
+    #[case::round_negative_half("round(-2.5)", "-3")]
+    #[case::abs_positive("abs(1.5)", "1.5")]
+    #[case::abs_negative("abs(-1.5)", "1.5")]
+    #[case::abs_zero("abs(0)", "0")]
+    #[case::sqrt("sqrt(9) == 3", "true")]
+    #[case::sqrt_fraction("sqrt(2.25)", "1.5")]
+    #[case::sqrt_zero("sqrt(0)", "0")]
+    #[case::pow("pow(2, 10) == 1024", "true")]
+    #[case::pow_zero_exponent("pow(5, 0)", "1")]
+    #[case::pow_negative_exponent("pow(2, -1)", "0.5")]
+    #[case::pow_fractional_exponent("pow(16, 0.5)", "4")]
+    fn test_math_natives(#[case] call: &str, #[case] expected: &str) {
+        let source = format!("print {};", call);
+
+        assert_eq!(run_to_string(&source).unwrap(), format!("{}\n", expected));
+    }
+
+    #[rstest]
+    #[case::floor("floor")]
+    #[case::ceil("ceil")]
+    #[case::round("round")]
+    #[case::abs("abs")]
+    #[case::sqrt("sqrt")]
+    fn test_math_native_errors(#[case] function: &str) {
+        assert_eq!(
+            runtime_error(&format!("{}(\"1\");", function)),
+            format!("'{}' expects a number argument.", function)
+        );
+        assert_eq!(
+            runtime_error(&format!("{}();", function)),
+            "Expected 1 arguments but got 0."
+        );
+        assert_eq!(
+            runtime_error(&format!("{}(1, 2);", function)),
+            "Expected 1 arguments but got 2."
+        );
+    }
+
+    #[rstest]
+    #[case::max("max(1, 5, 3) == 5", "true")]
+    #[case::min("min(4, -2, 3) == -2", "true")]
+    #[case::two_arguments("max(1, 2)", "2")]
+    #[case::many_arguments("min(9, 8, 7, 6, 5, 4, 3, 2, 1)", "1")]
+    #[case::equal("max(2, 2)", "2")]
+    fn test_min_max(#[case] call: &str, #[case] expected: &str) {
+        let source = format!("print {};", call);
+
+        assert_eq!(run_to_string(&source).unwrap(), format!("{}\n", expected));
+    }
+
+    #[rstest]
+    #[case::max_no_arguments("max();", "Expected at least 2 arguments but got 0.")]
+    #[case::min_one_argument("min(1);", "Expected at least 2 arguments but got 1.")]
+    #[case::max_type("max(1, \"5\", 3);", "'max' expects a number argument.")]
+    #[case::min_nil("min(1, 2, nil);", "'min' expects a number argument.")]
+    fn test_min_max_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::sqrt_negative("sqrt(-4);", "Can't take the square root of a negative number, -4.")]
+    #[case::pow_base_type("pow(\"2\", 1);", "'pow' expects a number argument.")]
+    #[case::pow_exponent_type("pow(2, nil);", "'pow' expects a number argument.")]
+    #[case::pow_arity("pow(2);", "Expected 2 arguments but got 1.")]
+    fn test_sqrt_and_pow_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::number("1.5", "number")]
+    #[case::string("\"a\"", "string")]
+    #[case::boolean("false", "boolean")]
+    #[case::nil("nil", "nil")]
+    #[case::function("type", "function")]
+    #[case::user_function("f", "function")]
+    #[case::list("[1]", "list")]
+    #[case::map("{}", "map")]
+    #[case::class("A", "class")]
+    #[case::instance("A()", "instance")]
+    #[case::type_result("type(1)", "string")]
+    fn test_type(#[case] value: &str, #[case] expected: &str) {
+        let source = format!("fun f() {{}} class A {{}} print type({});", value);
+
+        assert_eq!(run_to_string(&source).unwrap(), format!("{}\n", expected));
+    }
+
+    #[rstest]
+    #[case::no_parameters("fun f() {} print arity(f);", "0\n")]
+    #[case::parameters("fun f(a, b, c) {} print arity(f);", "3\n")]
+    #[case::lambda("print arity(fun (a) {});", "1\n")]
+    #[case::native_clock("print arity(clock);", "0\n")]
+    #[case::native_substr("print arity(substr);", "3\n")]
+    #[case::class_initializer("class A { init(a, b) {} } print arity(A);", "2\n")]
+    #[case::class_without_initializer("class A {} print arity(A);", "0\n")]
+    fn test_arity(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::numbers("print join([1, 2, 3], \",\") == \"1,2,3\";", "true\n")]
+    #[case::mixed("print join([\"a\", nil, true, 1.5], \" \");", "a nil true 1.5\n")]
+    #[case::nested("print join([[1, 2], {}], \"; \");", "[1, 2]; {}\n")]
+    #[case::single("print join([1], \",\");", "1\n")]
+    #[case::empty("print join([], \",\") == \"\";", "true\n")]
+    fn test_join(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::list(
+        "var a = [1, 2]; var b = copy(a); push(b, 3); print a; print b; print a == b;",
+        "[1, 2]\n[1, 2, 3]\nfalse\n"
+    )]
+    #[case::list_equal_contents("var a = [1, [2]]; print equals(a, copy(a));", "true\n")]
+    #[case::shallow(
+        "var inner = [1]; var a = [inner]; var b = copy(a); push(b[0], 2); print a; print b[0] == inner;",
+        "[[1, 2]]\ntrue\n"
+    )]
+    #[case::map(
+        "var a = {\"k\": [1]}; var b = copy(a); push(b[\"k\"], 2); print a == b; print equals(a, b); print a;",
+        "false\ntrue\n{k: [1, 2]}\n"
+    )]
+    #[case::instance(
+        "class A {} var a = A(); a.x = 1; var b = copy(a); b.x = 2; print a.x; print b.x; print b; print a == b;",
+        "1\n2\nA instance\nfalse\n"
+    )]
+    #[case::instance_methods(
+        "class A { get() { return this.x; } } var a = A(); a.x = 1; var b = copy(a); b.x = 2; print a.get(); print b.get();",
+        "1\n2\n"
+    )]
+    #[case::values(
+        "print copy(1); print copy(\"a\"); print copy(nil); print copy(true); print copy(len) == len;",
+        "1\na\nnil\ntrue\ntrue\n"
+    )]
+    fn test_copy(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::trim("print trim(\"  hi  \") == \"hi\";", "true\n")]
+    #[case::trim_newlines("print trim(\"\n\ta b\n\");", "a b\n")]
+    #[case::trim_nothing("print trim(\"hi\");", "hi\n")]
+    #[case::split("print split(\"a,b,c\", \",\");", "[a, b, c]\n")]
+    #[case::split_length("print len(split(\"a,b,c\", \",\"));", "3\n")]
+    #[case::split_keeps_empty_parts("print len(split(\",a,,b,\", \",\"));", "5\n")]
+    #[case::split_longer_separator("print split(\"1 - 2 - 3\", \" - \");", "[1, 2, 3]\n")]
+    #[case::split_no_separator_found("print split(\"abc\", \",\");", "[abc]\n")]
+    #[case::split_whitespace("print split(\"  a  b\tc\n\");", "[a, b, c]\n")]
+    #[case::split_nil_separator("print split(\"a b\", nil);", "[a, b]\n")]
+    #[case::split_join_round_trip("print join(split(\"a,b\", \",\"), \",\");", "a,b\n")]
+    fn test_trim_and_split(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::trim_type("trim(1);", "'trim' expects a string argument.")]
+    #[case::trim_arity("trim();", "Expected 1 arguments but got 0.")]
+    #[case::split_type("split([1], \",\");", "'split' expects a string argument.")]
+    #[case::split_separator_type("split(\"a\", 1);", "'split' expects a string argument.")]
+    #[case::split_empty_separator("split(\"a\", \"\");", "'split' can't split on an empty string.")]
+    #[case::split_arity("split(\"a\", \",\", 1);", "Expected 1 to 2 arguments but got 3.")]
+    fn test_trim_and_split_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::single("print replace(\"hello world\", \"world\", \"lox\");", "hello lox\n")]
+    #[case::multiple("print replace(\"a-b-c\", \"-\", \", \");", "a, b, c\n")]
+    #[case::no_match("print replace(\"abc\", \"x\", \"y\");", "abc\n")]
+    #[case::remove("print replace(\"banana\", \"an\", \"\");", "ba\n")]
+    #[case::not_overlapping("print replace(\"aaa\", \"aa\", \"b\");", "ba\n")]
+    #[case::leaves_original("var s = \"aa\"; replace(s, \"a\", \"b\"); print s;", "aa\n")]
+    fn test_replace(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::haystack_type("replace(1, \"a\", \"b\");", "'replace' expects a string argument.")]
+    #[case::needle_type("replace(\"a\", nil, \"b\");", "'replace' expects a string argument.")]
+    #[case::replacement_type("replace(\"a\", \"a\", 1);", "'replace' expects a string argument.")]
+    #[case::empty_needle(
+        "replace(\"a\", \"\", \"b\");",
+        "'replace' can't replace an empty string."
+    )]
+    #[case::arity("replace(\"a\", \"a\");", "Expected 3 arguments but got 2.")]
+    fn test_replace_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::starts_with("starts_with(\"hello\", \"he\")", true)]
+    #[case::starts_with_whole("starts_with(\"hello\", \"hello\")", true)]
+    #[case::starts_with_empty("starts_with(\"hello\", \"\")", true)]
+    #[case::starts_with_other("starts_with(\"hello\", \"lo\")", false)]
+    #[case::starts_with_longer("starts_with(\"he\", \"hello\")", false)]
+    #[case::starts_with_case("starts_with(\"Hello\", \"he\")", false)]
+    #[case::ends_with("ends_with(\"hello\", \"lo\")", true)]
+    #[case::ends_with_empty("ends_with(\"\", \"\")", true)]
+    #[case::ends_with_non_ascii("ends_with(\"café\", \"é\")", true)]
+    #[case::ends_with_other("ends_with(\"hello\", \"he\")", false)]
+    #[case::contains("contains(\"hello\", \"ell\")", true)]
+    #[case::contains_whole("contains(\"hello\", \"hello\")", true)]
+    #[case::contains_empty("contains(\"hello\", \"\")", true)]
+    #[case::contains_missing("contains(\"hello\", \"world\")", false)]
+    #[case::contains_in_empty("contains(\"\", \"a\")", false)]
+    fn test_string_predicates(#[case] call: &str, #[case] expected: bool) {
+        let source = format!("print {};", call);
+
+        assert_eq!(run_to_string(&source).unwrap(), format!("{}\n", expected));
+    }
+
+    #[rstest]
+    #[case::starts_with_type("starts_with(1, \"a\");", "'starts_with' expects a string argument.")]
+    #[case::starts_with_prefix_type(
+        "starts_with(\"a\", nil);",
+        "'starts_with' expects a string argument."
+    )]
+    #[case::ends_with_type("ends_with([\"a\"], \"a\");", "'ends_with' expects a string argument.")]
+    #[case::ends_with_suffix_type("ends_with(\"a\", 1);", "'ends_with' expects a string argument.")]
+    #[case::contains_type("contains({}, \"a\");", "'contains' expects a string argument.")]
+    #[case::contains_substring_type(
+        "contains(\"a\", true);",
+        "'contains' expects a string argument."
+    )]
+    #[case::arity("contains(\"a\");", "Expected 2 arguments but got 1.")]
+    fn test_string_predicate_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::end("print range(3);", "[0, 1, 2]\n")]
+    #[case::start_and_end("print range(1, 4);", "[1, 2, 3]\n")]
+    #[case::step("print range(0, 10, 3);", "[0, 3, 6, 9]\n")]
+    #[case::negative_step("print range(3, 0, -1);", "[3, 2, 1]\n")]
+    #[case::negative_start("print range(-2, 1);", "[-2, -1, 0]\n")]
+    #[case::empty("print range(0); print range(3, 1);", "[]\n[]\n")]
+    #[case::step_past_end("print range(3, 0, 1);", "[]\n")]
+    #[case::uneven_negative_step("print range(10, 0, -4);", "[10, 6, 2]\n")]
+    #[case::largest_exact_integers(
+        "print range(9007199254740990, 9007199254740992);",
+        "[9007199254740990, 9007199254740991]\n"
+    )]
+    #[case::for_in("var sum = 0; for (i in range(5)) sum += i; print sum;", "10\n")]
+    fn test_range(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::zero_step("range(0, 3, 0);", "'range' can't have a step of 0.")]
+    #[case::fraction("range(1.5);", "'range' expects an integer argument.")]
+    #[case::fractional_step("range(0, 1, 0.5);", "'range' expects an integer argument.")]
+    #[case::infinite("range(0, pow(10, 400));", "'range' expects an integer argument.")]
+    #[case::beyond_exact_integers(
+        "range(9007199254740992, 9007199254740994);",
+        "'range' expects an integer argument."
+    )]
+    #[case::too_long(
+        "range(0, 1000000000000);",
+        "'range' can't produce more than 10000000 elements."
+    )]
+    #[case::too_long_counting_down(
+        "range(0, -9007199254740992, -1);",
+        "'range' can't produce more than 10000000 elements."
+    )]
+    #[case::type_error("range(\"3\");", "'range' expects a number argument.")]
+    #[case::no_arguments("range();", "Expected 1 to 3 arguments but got 0.")]
+    #[case::too_many_arguments("range(1, 2, 3, 4);", "Expected 1 to 3 arguments but got 4.")]
+    fn test_range_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::push("var l = [1]; push(l, 2); push(l, nil); print l;", "[1, 2, nil]\n")]
+    #[case::push_returns_nil("print push([], 1);", "nil\n")]
+    #[case::push_through_alias("var a = []; var b = a; push(b, 1); print a;", "[1]\n")]
+    #[case::push_in_function(
+        "fun add(list) { push(list, \"x\"); } var l = []; add(l); add(l); print l;",
+        "[x, x]\n"
+    )]
+    #[case::push_into_field(
+        "class Stack { init() { this.items = []; } } var s = Stack(); push(s.items, 1); print s.items;",
+        "[1]\n"
+    )]
+    #[case::pop("var l = [1, 2]; print pop(l); print l;", "2\n[1]\n")]
+    #[case::pop_empty("var l = []; print pop(l); print l;", "nil\n[]\n")]
+    #[case::pop_through_alias("var a = [1, 2]; var b = a; pop(b); print a;", "[1]\n")]
+    #[case::append("var l = [1]; append(l, [2, 3]); print l;", "[1, 2, 3]\n")]
+    #[case::append_itself("var l = [1, 2]; append(l, l); print l;", "[1, 2, 1, 2]\n")]
+    #[case::contains_itself("var l = [1]; push(l, l); print l;", "[1, [...]]\n")]
+    #[case::map_while_pushing(
+        "var l = [1, 2]; print map(l, fun (n) { push(l, n); return n; }); print l;",
+        "[1, 2]\n[1, 2, 1, 2]\n"
+    )]
+    fn test_list_mutation(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::push_not_list("push(1, 2);", "'push' expects a list argument.")]
+    #[case::pop_not_list("pop(\"ab\");", "'pop' expects a list argument.")]
+    #[case::append_not_list("append([], 1);", "'append' expects a list argument.")]
+    #[case::push_arity("push([]);", "Expected 2 arguments but got 1.")]
+    fn test_list_mutation_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::map_double("print map([1, 2, 3], fun (n) { return n * 2; });", "[2, 4, 6]\n")]
+    #[case::map_native("print map([1.5, -2], abs);", "[1.5, 2]\n")]
+    #[case::map_empty("print map([], str);", "[]\n")]
+    #[case::filter_evens(
+        "print filter(range(7), fun (n) { return n - floor(n / 2) * 2 == 0; });",
+        "[0, 2, 4, 6]\n"
+    )]
+    #[case::filter_truthiness(
+        "print filter([1, nil, false, \"a\"], fun (x) { return x; });",
+        "[1, a]\n"
+    )]
+    #[case::reduce_sum(
+        "print reduce([1, 2, 3, 4], fun (sum, n) { return sum + n; }, 0);",
+        "10\n"
+    )]
+    #[case::reduce_order(
+        "print reduce([\"a\", \"b\"], fun (text, c) { return text + c; }, \">\");",
+        ">ab\n"
+    )]
+    #[case::reduce_empty("print reduce([], max, 5);", "5\n")]
+    #[case::default_parameter("print map([1], fun (n, m = 10) { return n + m; });", "[11]\n")]
+    #[case::rest_parameter("print map([1], fun (...all) { return all; });", "[[1]]\n")]
+    #[case::bound_method(
+        "class A { init(k) { this.k = k; } add(n) { return n + this.k; } } print map([1, 2], A(10).add);",
+        "[11, 12]\n"
+    )]
+    fn test_list_functions(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::map_not_list("map(1, str);", "'map' expects a list argument.")]
+    #[case::map_not_function("map([1], 1);", "'map' expects a function taking 1 argument.")]
+    #[case::map_class(
+        "class A {} map([1], A);",
+        "'map' expects a function taking 1 argument."
+    )]
+    #[case::map_too_many_parameters(
+        "map([1], fun (a, b) {});",
+        "'map' expects a function taking 1 argument."
+    )]
+    #[case::filter_no_parameters(
+        "filter([1], fun () {});",
+        "'filter' expects a function taking 1 argument."
+    )]
+    #[case::reduce_one_parameter(
+        "reduce([1], fun (a) {}, 0);",
+        "'reduce' expects a function taking 2 arguments."
+    )]
+    #[case::reduce_arity("reduce([1], max);", "Expected 3 arguments but got 2.")]
+    #[case::error_in_function(
+        "map([1, \"a\"], fun (n) { return -n; });",
+        "Operands must be numbers."
+    )]
+    fn test_list_function_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::len_arity("len();", "Expected 1 arguments but got 0.")]
+    #[case::len_type("len(1);", "'len' expects a string or list argument.")]
+    #[case::len_map("len({});", "'len' expects a string or list argument.")]
+    #[case::substr_arity("substr(\"a\", 1);", "Expected 3 arguments but got 2.")]
+    #[case::substr_string_type("substr(1, 0, 1);", "'substr' expects a string argument.")]
+    #[case::substr_number_type("substr(\"a\", \"0\", 1);", "'substr' expects a number argument.")]
+    #[case::substr_fraction(
+        "substr(\"abc\", 0.5, 1);",
+        "'substr' expects a non-negative integer argument."
+    )]
+    #[case::substr_range("substr(\"abc\", 2, 2);", "Substring out of range.")]
+    #[case::substr_huge_start(
+        "substr(\"abc\", 1000000000000000000000000000000, 1);",
+        "'substr' expects a non-negative integer argument."
+    )]
+    #[case::substr_huge_length(
+        "substr(\"abc\", 1, 1000000000000000000000000000000);",
+        "'substr' expects a non-negative integer argument."
+    )]
+    #[case::ord_empty("ord(\"\");", "'ord' expects a string of one character.")]
+    #[case::ord_multiple("ord(\"ab\");", "'ord' expects a string of one character.")]
+    #[case::ord_combining("ord(\"e\u{301}\");", "'ord' expects a string of one character.")]
+    #[case::ord_type("ord(65);", "'ord' expects a string argument.")]
+    #[case::chr_negative("chr(-1);", "'chr' expects a Unicode code point, not -1.")]
+    #[case::chr_fraction("chr(97.5);", "'chr' expects a Unicode code point, not 97.5.")]
+    #[case::chr_too_large("chr(1114112);", "'chr' expects a Unicode code point, not 1114112.")]
+    #[case::chr_surrogate("chr(55296);", "'chr' expects a Unicode code point, not 55296.")]
+    #[case::chr_type("chr(\"a\");", "'chr' expects a number argument.")]
+    #[case::upper_type("upper(1);", "'upper' expects a string argument.")]
+    #[case::lower_type("lower(nil);", "'lower' expects a string argument.")]
+    #[case::str_arity("str();", "Expected 1 arguments but got 0.")]
+    #[case::num_not_numeric("num(\"x\");", "Cannot convert 'x' to a number.")]
+    #[case::num_infinite("num(\"inf\");", "Cannot convert 'inf' to a number.")]
+    #[case::num_type("num(1);", "'num' expects a string argument.")]
+    #[case::arity_type("arity(1);", "'arity' expects a function argument.")]
+    #[case::seed_type("seed(\"a\");", "'seed' expects a number argument.")]
+    fn test_string_native_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[rstest]
+    #[case::true_condition("assert(true); print 1;", "1\n")]
+    #[case::truthy_condition("assert(0, \"zero is truthy\"); print 1;", "1\n")]
+    #[case::returns_nil("print assert(1 < 2);", "nil\n")]
+    fn test_assert_passes(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::without_message("assert(false);", "Assertion failed.")]
+    #[case::nil("assert(nil);", "Assertion failed.")]
+    #[case::with_message(
+        "assert(1 > 2, \"one is not bigger\");",
+        "Assertion failed: one is not bigger"
+    )]
+    #[case::message_not_string("assert(false, 42);", "Assertion failed: 42")]
+    #[case::no_arguments("assert();", "Expected 1 to 2 arguments but got 0.")]
+    #[case::too_many_arguments("assert(true, 1, 2);", "Expected 1 to 2 arguments but got 3.")]
+    fn test_assert_fails(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[test]
+    fn test_failed_assert_stops_program() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+
+        let result = run_in_interpreter("print 1; assert(false); print 2;", &mut interpreter);
+        drop(interpreter);
+
+        assert!(matches!(result, Err(LoxScriptError::Runtime(_))));
+        assert_eq!(String::from_utf8(output).unwrap(), "1\n");
+    }
+
+    #[rstest]
+    #[case::string("error(\"boom\");", "boom")]
+    #[case::not_string("error(404);", "404")]
+    #[case::from_function("fun fail() { error(\"inner\"); } fail();", "inner")]
+    fn test_error(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+
+    #[test]
+    fn test_error_is_reported_at_call() {
+        let error = run_to_string("print 1;\nerror(\"boom\");").unwrap_err();
+
+        assert_eq!(error.to_string(), "boom [line 1]");
+    }
+
+    #[rstest]
+    #[case::not_list("join(\"abc\", \",\");", "'join' expects a list argument.")]
+    #[case::separator_not_string("join([1, 2], 0);", "'join' expects a string argument.")]
+    #[case::arity("join([1]);", "Expected 2 arguments but got 1.")]
+    fn test_join_errors(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(runtime_error(source), expected);
+    }
+}
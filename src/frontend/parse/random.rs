@@ -0,0 +1,72 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/**
+ * A small xorshift pseudo-random number generator.
+ * The same seed always gives the same sequence, so scripts can be made reproducible.
+ */
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn from_seed(seed: u64) -> Random {
+        // Scramble the seed so that nearby seeds give unrelated sequences, and the state is never zero
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        Random {
+            state: if z == 0 { 1 } else { z },
+        }
+    }
+
+    /**
+     * Seeds the generator from the system clock
+     */
+    pub fn from_clock() -> Random {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |now| now.as_nanos() as u64);
+
+        Random::from_seed(nanos)
+    }
+
+    /**
+     * Returns the next number in the sequence, in the range [0, 1)
+     */
+    pub fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+
+        // The top 53 bits fill an f64's mantissa exactly
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_gives_same_sequence() {
+        let mut a = Random::from_seed(42);
+        let mut b = Random::from_seed(42);
+
+        for _ in 0..100 {
+            assert_eq!(a.next_f64(), b.next_f64());
+        }
+    }
+
+    #[test]
+    fn test_numbers_are_in_range() {
+        let mut random = Random::from_seed(0);
+
+        for _ in 0..1000 {
+            let n = random.next_f64();
+            assert!((0.0..1.0).contains(&n));
+        }
+    }
+}
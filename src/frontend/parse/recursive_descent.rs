@@ -1,5 +1,11 @@
-use super::expression::Expression;
-use crate::frontend::lex::token::{Literal, Token, TokenType};
+use std::rc::Rc;
+
+use super::{
+    expression::Expression,
+    statement::{FunctionDeclaration, Statement},
+    with_stack,
+};
+use crate::frontend::lex::token::{Literal, Span, Token, TokenType, KEYWORDS};
 
 #[derive(Debug)]
 pub struct ParseError {
@@ -7,34 +13,250 @@ pub struct ParseError {
     pub message: String,
 }
 
+impl ParseError {
+    /**
+     * The location in the source of the token the error was reported at
+     */
+    pub fn span(&self) -> Span {
+        self.token.span
+    }
+
+    /**
+     * Reports that the token was found where one of the expected token types should have been
+     */
+    pub fn unexpected_token(token: &Token, expected: &[TokenType]) -> Self {
+        let expected = match expected {
+            [] => "a token".to_string(),
+            [only] => only.to_string(),
+            [rest @ .., last] => format!(
+                "{} or {}",
+                rest.iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                last
+            ),
+        };
+
+        let found = match token.token_type {
+            TokenType::Identifier | TokenType::String | TokenType::Number => {
+                format!("{} '{}'", token.token_type, token.lexeme)
+            }
+            _ => token.token_type.to_string(),
+        };
+
+        ParseError {
+            token: token.clone(),
+            message: format!("Expected {} but found {}", expected, found),
+        }
+    }
+}
+
 type ParseResult<T> = Result<T, ParseError>;
 
+/**
+ * How deeply expressions and statements may nest, such as through parentheses, unary operators or blocks.
+ * The stack is grown as needed while parsing nested code, so this only bounds memory use.
+ */
+const MAX_NESTING_DEPTH: usize = 1024;
+
+/**
+ * How many operators, calls or indexes may be chained in one statement, such as in `1 + 1 + 1`.
+ * Each one nests the expression built before it one level deeper, so this bounds the stack used
+ * when the tree is later printed, resolved or dropped.
+ */
+const MAX_CHAIN_LENGTH: usize = 16_384;
+
 /**
  * Implements a recursive descent parser for the formal grammar:
+ * program      => declaration* EOF ;
+ * declaration  => classDecl | funDecl | varDecl | constDecl | statement ;
+ * classDecl    => "class" IDENTIFIER "{" ( "class"? function | getter )* "}" ;
+ * getter       => IDENTIFIER block ;
+ * funDecl      => "fun" function ;
+ * function     => IDENTIFIER functionBody ;
+ * functionBody => "(" parameters? ")" block ;
+ * parameters   => ( parameter "," )* ( parameter | "..." IDENTIFIER ) ;
+ * parameter    => IDENTIFIER ( "=" assignment )? ;
+ * varDecl      => "var" IDENTIFIER ( "=" value )? ";" ;
+ * constDecl    => "const" IDENTIFIER "=" value ";" ;
+ * statement    => exprStmt | breakStmt | continueStmt | ifStmt | printStmt | returnStmt
+ *              | switchStmt | loopStmt | block ;
+ * breakStmt    => "break" IDENTIFIER? ";" ;
+ * continueStmt => "continue" IDENTIFIER? ";" ;
+ * loopStmt     => ( IDENTIFIER ":" )? ( whileStmt | doWhileStmt | forInStmt ) ;
+ * exprStmt     => expression ";" ;
+ * doWhileStmt  => "do" statement "while" "(" expression ")" ";" ;
+ * forInStmt    => "for" "(" IDENTIFIER "in" expression ")" statement ;
+ * ifStmt       => "if" "(" expression ")" statement ( "else" statement )? ;
+ * printStmt    => "print" expression ";" ;
+ * returnStmt   => "return" expression? ";" ;
+ * switchStmt   => "switch" "(" expression ")" "{" switchCase* defaultCase? "}" ;
+ * switchCase   => "case" expression ":" declaration* ;
+ * defaultCase  => "default" ":" declaration* ;
+ * whileStmt    => "while" "(" expression ")" statement ;
+ * block        => "{" declaration* "}" ;
  * expression   => comma ;
- * comma        => ternary ( "," ternary )* ;
- * ternary      => equality ( "?" expression ":" expression )? ;
+ * comma        => assignment ( "," assignment )* ;
+ * assignment   => ( call "." )? IDENTIFIER ( "=" | "+=" | "-=" | "*=" | "/=" ) ( blockExpr | assignment )
+ *              | ternary ;
+ * ternary      => equality ( "?" expression ":" ternary )? ;
  * equality     => comparison ( ( "!=" | "==" ) comparison )* ;
- * comparison   => term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
+ * comparison   => elvis ( ( ">" | ">=" | "<" | "<=" ) elvis )? ;
+ * elvis        => term ( "?:" elvis )? ;
  * term         => factor ( ( "-" | "+" ) factor )* ;
  * factor       => unary ( ( "/" | "*" ) unary )* ;
  * unary        => ( "!" | "-" ) unary
- *              | primary ;
- * primary      => NUMBER | STRING | "false" | "true" | "nil"
- *              | "(" expression ")" ;
+ *              | postfix ;
+ * postfix      => call ( "++" | "--" )? ;
+ * call         => primary ( "(" callArgs? ")" | "[" expression ( ".." expression )? "]" | "." IDENTIFIER )* ;
+ * callArgs     => ( argument "," )* argument ","? ;
+ * argument     => ( IDENTIFIER ":" )? assignment ;
+ * arguments    => assignment ( "," assignment )* ","? ;
+ * primary      => NUMBER | STRING | "false" | "true" | "nil" | "this"
+ *              | "(" expression ")" | IDENTIFIER | list | map | lambda | ifExpr ;
+ * lambda       => "fun" functionBody ;
+ * ifExpr       => "if" "(" expression ")" ternary "else" ternary ;
+ * list         => "[" arguments? "]" ;
+ * map          => "{" ( entry ( "," entry )* ","? )? "}" ;
+ * entry        => assignment ":" assignment ;
+ * value        => expression | blockExpr ;
+ * blockExpr    => "{" ( declaration | blockExpr )* ( expression | blockExpr )? "}" ;
+ *
+ * Keyword arguments, written `name: value`, must come after all positional arguments.
+ * `in` is only special after a for-in loop's variable, so it can still be used as a name elsewhere.
+ * A "{" starting a statement always begins a block, so a map literal is only parsed in expression position.
+ * A block expression is only parsed as the value of a variable, or nested in another block expression,
+ * where a "{" is a map literal if it parses as one.
+ * Likewise an "if" starting a statement is an if statement, so an if expression is only parsed in expression position.
 */
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    interactive: bool,
+    depth: usize,
+    // The operators, calls and indexes chained so far in the statement being parsed,
+    // which bounds how deeply they can have nested the expressions built from them
+    chain_length: usize,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+        Parser::with_interactive(tokens, false)
+    }
+
+    /**
+     * Creates a parser which, when interactive, lets the final statement leave off its ';'
+     * Comment tokens are ignored.
+     */
+    pub fn with_interactive(tokens: Vec<Token>, interactive: bool) -> Parser {
+        Parser {
+            tokens: tokens
+                .into_iter()
+                .filter(|token| token.token_type != TokenType::Comment)
+                .collect(),
+            current: 0,
+            interactive,
+            depth: 0,
+            chain_length: 0,
+        }
+    }
+
+    /**
+     * Parses every statement, skipping to the next statement after each error so that all of them are reported
+     */
+    pub fn parse(&mut self) -> Result<Vec<Statement>, Vec<ParseError>> {
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(statement) => statements.push(statement),
+                Err(err) => {
+                    errors.push(err);
+                    self.syncronise();
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(statements)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /**
+     * Parses the tokens as a single expression, which must make up the whole input
+     */
+    pub fn parse_expression(&mut self) -> ParseResult<Expression> {
+        let expr = self.expression()?;
+
+        if !self.is_at_end() {
+            return Err(ParseError {
+                token: self.peek().clone(),
+                message: "Expect end of expression.".to_string(),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /**
+     * Parses a sub-expression one level deeper, erroring if expressions are nested too deeply
+     */
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> ParseResult<Expression>,
+    ) -> ParseResult<Expression> {
+        self.descend("Expression", MAX_NESTING_DEPTH, parse)
+    }
+
+    /**
+     * Parses a statement one level deeper, such as the body of a block or loop,
+     * erroring if statements are nested too deeply.
+     * A level is left for the expressions the statement is made of, such as a loop's condition.
+     */
+    fn nested_statement(
+        &mut self,
+        parse: fn(&mut Self) -> ParseResult<Statement>,
+    ) -> ParseResult<Statement> {
+        self.descend("Statement", MAX_NESTING_DEPTH - 1, parse)
+    }
+
+    fn descend<T>(
+        &mut self,
+        kind: &str,
+        limit: usize,
+        parse: fn(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<T> {
+        if self.depth >= limit {
+            return Err(ParseError {
+                token: self.peek().clone(),
+                message: format!("{} is nested too deeply.", kind),
+            });
+        }
+
+        self.depth += 1;
+        let result = with_stack(|| parse(self));
+        self.depth -= 1;
+
+        result
     }
 
-    pub fn parse(&mut self) -> ParseResult<Expression> {
-        self.expression()
+    /**
+     * Counts another operator, call or index chained onto the expression before it
+     */
+    fn link(&mut self) -> ParseResult<()> {
+        if self.chain_length >= MAX_CHAIN_LENGTH {
+            return Err(ParseError {
+                token: self.get_previous().clone(),
+                message: "Expression is nested too deeply.".to_string(),
+            });
+        }
+
+        self.chain_length += 1;
+        Ok(())
     }
 
     fn create_left_associative_binary_expression(
@@ -45,6 +267,7 @@ impl Parser {
         let mut expr = next(self)?;
 
         while self.next_matches(&token_types) {
+            self.link()?;
             expr = Expression::Binary {
                 left: Box::new(expr),
                 operator: self.get_previous().clone(),
@@ -55,220 +278,1925 @@ impl Parser {
         Ok(expr)
     }
 
-    fn expression(&mut self) -> ParseResult<Expression> {
-        self.comma()
-    }
+    /**
+     * Parses a declaration, counting the operators chained in it separately from any enclosing statement
+     */
+    fn declaration(&mut self) -> ParseResult<Statement> {
+        let chain_length = std::mem::replace(&mut self.chain_length, 0);
+        let result = self.nested_statement(Self::parse_declaration);
+        self.chain_length = chain_length;
 
-    fn comma(&mut self) -> ParseResult<Expression> {
-        self.create_left_associative_binary_expression(vec![TokenType::Comma], Self::ternary)
+        result
     }
 
-    fn ternary(&mut self) -> ParseResult<Expression> {
-        let mut expr = self.equality()?;
+    fn parse_declaration(&mut self) -> ParseResult<Statement> {
+        // Without a name, `fun` starts an anonymous function in an expression statement
+        if self.check_next(&TokenType::Fun)
+            && self.tokens.get(self.current + 1).map(|t| &t.token_type)
+                == Some(&TokenType::Identifier)
+        {
+            self.advance();
+            return self.function();
+        }
 
-        if self.next_matches(&vec![TokenType::QuestionMark]) {
-            let then_branch = self.expression()?;
-            self.consume(&TokenType::Colon, "Expected ':' after then branch")?;
-            let else_branch = self.expression()?;
-            expr = Expression::Ternary {
-                condition: Box::new(expr),
-                then_branch: Box::new(then_branch),
-                else_branch: Box::new(else_branch),
-            };
+        if self.next_matches(&vec![TokenType::Class]) {
+            return self.class_declaration();
         }
 
-        Ok(expr)
-    }
+        if self.next_matches(&vec![TokenType::Var]) {
+            return self.var_declaration();
+        }
 
-    fn equality(&mut self) -> ParseResult<Expression> {
-        self.create_left_associative_binary_expression(
-            vec![TokenType::BangEqual, TokenType::EqualEqual],
-            Self::comparison,
-        )
+        if self.next_matches(&vec![TokenType::Const]) {
+            return self.const_declaration();
+        }
+
+        self.statement()
     }
 
-    fn comparison(&mut self) -> ParseResult<Expression> {
-        self.create_left_associative_binary_expression(
-            vec![
-                TokenType::Greater,
-                TokenType::GreaterEqual,
-                TokenType::Less,
-                TokenType::LessEqual,
-            ],
-            Self::term,
-        )
+    fn class_declaration(&mut self) -> ParseResult<Statement> {
+        self.consume(&TokenType::Identifier)?;
+        let name = self.get_previous().clone();
+        self.consume(&TokenType::LeftBrace)?;
+
+        let mut methods = Vec::new();
+        let mut getters = Vec::new();
+        let mut class_methods = Vec::new();
+        while !self.check_next(&TokenType::RightBrace) && !self.is_at_end() {
+            if self.next_matches(&vec![TokenType::Class]) {
+                self.consume(&TokenType::Identifier)?;
+                let method_name = self.get_previous().clone();
+                class_methods.push(self.function_body(Some(method_name))?);
+                continue;
+            }
+
+            self.consume(&TokenType::Identifier)?;
+            let method_name = self.get_previous().clone();
+
+            // A method without a parameter list is a getter
+            if self.next_matches(&vec![TokenType::LeftBrace]) {
+                getters.push(Rc::new(FunctionDeclaration {
+                    name: Some(method_name),
+                    params: Vec::new(),
+                    defaults: Vec::new(),
+                    rest: None,
+                    body: self.block()?,
+                }));
+            } else {
+                methods.push(self.function_body(Some(method_name))?);
+            }
+        }
+        self.consume(&TokenType::RightBrace)?;
+
+        Ok(Statement::Class {
+            name,
+            methods,
+            getters,
+            class_methods,
+        })
     }
 
-    fn term(&mut self) -> ParseResult<Expression> {
-        self.create_left_associative_binary_expression(
-            vec![TokenType::Minus, TokenType::Plus],
-            Self::factor,
-        )
+    fn var_declaration(&mut self) -> ParseResult<Statement> {
+        self.consume(&TokenType::Identifier)?;
+        let name = self.get_previous().clone();
+
+        let initializer = if self.next_matches(&vec![TokenType::Equal]) {
+            Some(self.value(Self::expression)?)
+        } else {
+            None
+        };
+
+        self.consume_statement_end()?;
+        Ok(Statement::Var { name, initializer })
     }
 
-    fn factor(&mut self) -> ParseResult<Expression> {
-        self.create_left_associative_binary_expression(
-            vec![TokenType::Slash, TokenType::Star],
-            Self::unary,
-        )
+    /**
+     * Parses a constant, which must be given its value when it is declared
+     */
+    fn const_declaration(&mut self) -> ParseResult<Statement> {
+        self.consume(&TokenType::Identifier)?;
+        let name = self.get_previous().clone();
+
+        self.consume(&TokenType::Equal)?;
+        let initializer = self.value(Self::expression)?;
+
+        self.consume_statement_end()?;
+        Ok(Statement::Const { name, initializer })
     }
 
-    fn unary(&mut self) -> ParseResult<Expression> {
-        if self.next_matches(&vec![TokenType::Bang, TokenType::Minus]) {
-            Ok(Expression::Unary {
-                operator: self.get_previous().clone(),
-                right: Box::new(self.unary()?),
-            })
-        } else {
-            self.primary()
-        }
+    fn function(&mut self) -> ParseResult<Statement> {
+        self.consume(&TokenType::Identifier)?;
+        let name = self.get_previous().clone();
+
+        Ok(Statement::Function(self.function_body(Some(name))?))
     }
 
-    fn primary(&mut self) -> ParseResult<Expression> {
-        match self.peek().token_type {
-            TokenType::False => {
-                self.advance();
-                Ok(Expression::Literal(Some(Literal::Boolean(false))))
-            }
-            TokenType::True => {
-                self.advance();
-                Ok(Expression::Literal(Some(Literal::Boolean(true))))
-            }
-            TokenType::Nil => {
-                self.advance();
-                Ok(Expression::Literal(None))
-            }
-            TokenType::Number => {
-                self.advance();
-                Ok(Expression::Literal(Some(Literal::Number(
-                    self.get_previous().lexeme.parse().unwrap(),
-                ))))
-            }
-            TokenType::String => {
-                self.advance();
-                Ok(Expression::Literal(Some(Literal::String(
-                    self.get_previous().lexeme.clone(),
-                ))))
-            }
-            TokenType::LeftParen => {
-                self.advance();
-                let expr = self.expression()?;
-                self.consume(&TokenType::RightParen, "Expect ')' after expression.")?;
-                Ok(Expression::Grouping(Box::new(expr)))
+    /**
+     * Parses a function's parameters and body, after its name if it has one
+     */
+    fn function_body(&mut self, name: Option<Token>) -> ParseResult<Rc<FunctionDeclaration>> {
+        self.consume(&TokenType::LeftParen)?;
+        let mut params = Vec::new();
+        let mut defaults = Vec::new();
+        let mut rest = None;
+        if !self.check_next(&TokenType::RightParen) {
+            loop {
+                if self.next_matches(&vec![TokenType::DotDotDot]) {
+                    self.consume(&TokenType::Identifier)?;
+                    rest = Some(self.get_previous().clone());
+
+                    if self.check_next(&TokenType::Comma) {
+                        return Err(ParseError {
+                            token: self.peek().clone(),
+                            message: "A rest parameter must be the last parameter.".to_string(),
+                        });
+                    }
+                    break;
+                }
+
+                self.consume(&TokenType::Identifier)?;
+                let param = self.get_previous().clone();
+
+                let default = if self.next_matches(&vec![TokenType::Equal]) {
+                    Some(self.assignment()?)
+                } else if matches!(defaults.last(), Some(Some(_))) {
+                    return Err(ParseError {
+                        token: param,
+                        message: "A parameter without a default can't follow one with a default."
+                            .to_string(),
+                    });
+                } else {
+                    None
+                };
+
+                params.push(param);
+                defaults.push(default);
+
+                if !self.next_matches(&vec![TokenType::Comma]) {
+                    break;
+                }
             }
-            _ => Err(ParseError {
-                token: self.peek().clone(),
-                message: "Expect expression.".to_string(),
-            }),
         }
+        self.consume(&TokenType::RightParen)?;
+
+        self.consume(&TokenType::LeftBrace)?;
+        let body = self.block()?;
+
+        Ok(Rc::new(FunctionDeclaration {
+            name,
+            params,
+            defaults,
+            rest,
+            body,
+        }))
     }
 
-    fn next_matches(&mut self, token_types: &Vec<TokenType>) -> bool {
-        for token_type in token_types {
-            if self.check_next(token_type) {
-                self.advance();
-                return true;
-            }
+    fn statement(&mut self) -> ParseResult<Statement> {
+        if self.check_name_and_colon() {
+            return self.labeled_statement();
         }
 
-        false
-    }
+        if self.next_matches(&vec![TokenType::Break, TokenType::Continue]) {
+            return self.jump_statement();
+        }
 
-    fn consume(&mut self, token_type: &TokenType, message: &str) -> ParseResult<()> {
-        if self.check_next(token_type) {
-            self.advance();
-            Ok(())
-        } else {
-            Err(ParseError {
-                token: self.peek().clone(),
-                message: message.to_string(),
-            })
+        if self.next_matches(&vec![TokenType::Do]) {
+            return self.do_while_statement(None);
         }
-    }
 
-    fn check_next(&self, token_type: &TokenType) -> bool {
-        if self.is_at_end() {
-            return false;
+        if self.next_matches(&vec![TokenType::For]) {
+            return self.for_in_statement(None);
         }
 
-        &self.peek().token_type == token_type
-    }
+        if self.next_matches(&vec![TokenType::If]) {
+            return self.if_statement();
+        }
 
-    fn advance(&mut self) -> &Token {
-        if !self.is_at_end() {
-            self.current += 1;
+        if self.next_matches(&vec![TokenType::Print]) {
+            return self.print_statement();
         }
 
-        self.get_previous()
+        if self.next_matches(&vec![TokenType::Return]) {
+            return self.return_statement();
+        }
+
+        if self.next_matches(&vec![TokenType::Switch]) {
+            return self.switch_statement();
+        }
+
+        if self.next_matches(&vec![TokenType::While]) {
+            return self.while_statement(None);
+        }
+
+        if self.next_matches(&vec![TokenType::LeftBrace]) {
+            return Ok(Statement::Block(self.block()?));
+        }
+
+        self.expression_statement()
     }
 
-    fn is_at_end(&self) -> bool {
-        self.peek().token_type == TokenType::Eof
+    /**
+     * Parses the declarations in a block, assuming the opening '{' has been consumed
+     */
+    fn block(&mut self) -> ParseResult<Vec<Statement>> {
+        let mut statements = Vec::new();
+
+        while !self.check_next(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(&TokenType::RightBrace)?;
+        Ok(statements)
     }
 
-    fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+    /**
+     * Parses the value given to a variable, which may be a block expression rather than a map literal.
+     * A leading '{' is parsed as a map first, and if that fails, as a block.
+     * If neither parses, the map's error is reported when the '{' is followed by a key and ':'.
+     */
+    fn value(
+        &mut self,
+        parse: fn(&mut Self) -> ParseResult<Expression>,
+    ) -> ParseResult<Expression> {
+        if !self.check_next(&TokenType::LeftBrace) {
+            return parse(self);
+        }
+
+        let start = self.current;
+        parse(self).or_else(|map_error| {
+            self.current = start;
+            self.advance();
+            self.block_expression().map_err(|block_error| {
+                if self.starts_map_entry(start + 1) {
+                    map_error
+                } else {
+                    block_error
+                }
+            })
+        })
     }
 
-    fn get_previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+    /**
+     * Checks whether the tokens from `index` start a map entry, `key:`
+     */
+    fn starts_map_entry(&self, index: usize) -> bool {
+        matches!(
+            self.tokens.get(index + 1),
+            Some(Token {
+                token_type: TokenType::Colon,
+                ..
+            })
+        )
     }
 
-    #[allow(dead_code)]
     /**
-     * Synchronise the parser to the next statement.
-     * This is used to recover from errors by skipping
-     * tokens until we reach a semicolon or a statement
+     * Parses a block which evaluates to its final expression, assuming the opening '{' has been consumed.
+     * The final expression may leave off its ';'.
+     * A block nested in it is parsed as a block expression too, so it can be the final expression.
      */
-    fn syncronise(&mut self) {
-        self.advance();
+    fn block_expression(&mut self) -> ParseResult<Expression> {
+        let brace = self.get_previous().clone();
+        let mut statements = Vec::new();
+        let mut value = None;
 
-        while !self.is_at_end() {
-            if self.get_previous().token_type == TokenType::Semicolon {
-                return;
+        while !self.check_next(&TokenType::RightBrace) && !self.is_at_end() {
+            let start = self.current;
+            if let Ok(expr) = self.expression() {
+                if self.check_next(&TokenType::RightBrace) {
+                    value = Some(expr);
+                    break;
+                }
             }
 
-            match self.peek().token_type {
-                TokenType::Class
-                | TokenType::Fun
-                | TokenType::Var
-                | TokenType::For
-                | TokenType::If
-                | TokenType::While
-                | TokenType::Print
-                | TokenType::Return => return,
-                _ => self.advance(),
-            };
+            self.current = start;
+            if self.next_matches(&vec![TokenType::LeftBrace]) {
+                let block = self.nested(Self::block_expression)?;
+                if self.check_next(&TokenType::RightBrace) {
+                    value = Some(block);
+                    break;
+                }
+
+                statements.push(Statement::Expression(block));
+                continue;
+            }
+
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(&TokenType::RightBrace)?;
+
+        if value.is_none() {
+            if let Some(Statement::Expression(_)) = statements.last() {
+                if let Some(Statement::Expression(expr)) = statements.pop() {
+                    value = Some(expr);
+                }
+            }
         }
+
+        Ok(Expression::Block {
+            brace,
+            statements,
+            value: value.map(Box::new),
+        })
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
+    /**
+     * Parses an if statement. The else branch is a whole statement, so `else if` chains need no extra braces,
+     * and an `else` belongs to the nearest `if` before it.
+     */
+    fn if_statement(&mut self) -> ParseResult<Statement> {
+        let keyword = self.get_previous().clone();
+        self.consume(&TokenType::LeftParen)?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen)?;
 
-    #[test]
-    fn test_parses_simple_expression() {
-        let mut parser = super::Parser::new(vec![
-            Token {
-                token_type: super::TokenType::Number,
-                lexeme: "123".to_string(),
-                literal: Some(super::Literal::Number(123.0)),
+        let then_branch = Box::new(self.nested_statement(Self::statement)?);
+        let else_branch = if self.next_matches(&vec![TokenType::Else]) {
+            Some(Box::new(self.nested_statement(Self::statement)?))
+        } else {
+            None
+        };
+
+        Ok(Statement::If {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn while_statement(&mut self, label: Option<Token>) -> ParseResult<Statement> {
+        let keyword = self.get_previous().clone();
+        self.consume(&TokenType::LeftParen)?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen)?;
+
+        Ok(Statement::While {
+            keyword,
+            condition,
+            body: Box::new(self.nested_statement(Self::statement)?),
+            label,
+        })
+    }
+
+    fn do_while_statement(&mut self, label: Option<Token>) -> ParseResult<Statement> {
+        let body = Box::new(self.nested_statement(Self::statement)?);
+
+        self.consume(&TokenType::While)?;
+        let keyword = self.get_previous().clone();
+        self.consume(&TokenType::LeftParen)?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen)?;
+        self.consume_statement_end()?;
+
+        Ok(Statement::DoWhile {
+            body,
+            keyword,
+            condition,
+            label,
+        })
+    }
+
+    fn for_in_statement(&mut self, label: Option<Token>) -> ParseResult<Statement> {
+        self.consume(&TokenType::LeftParen)?;
+        self.consume(&TokenType::Identifier)?;
+        let variable = self.get_previous().clone();
+
+        if !(self.check_next(&TokenType::Identifier) && self.peek().lexeme == "in") {
+            return Err(ParseError {
+                token: self.peek().clone(),
+                message: "Expected 'in' after the loop variable.".to_string(),
+            });
+        }
+        self.advance();
+
+        let iterable = self.expression()?;
+        self.consume(&TokenType::RightParen)?;
+
+        Ok(Statement::ForIn {
+            variable,
+            iterable,
+            body: Box::new(self.nested_statement(Self::statement)?),
+            label,
+        })
+    }
+
+    /**
+     * Parses a loop with a label, `name:`, which nested loops can `break` or `continue` by name
+     */
+    fn labeled_statement(&mut self) -> ParseResult<Statement> {
+        let label = self.advance().clone();
+        self.advance();
+
+        if self.next_matches(&vec![TokenType::While]) {
+            self.while_statement(Some(label))
+        } else if self.next_matches(&vec![TokenType::Do]) {
+            self.do_while_statement(Some(label))
+        } else if self.next_matches(&vec![TokenType::For]) {
+            self.for_in_statement(Some(label))
+        } else {
+            Err(ParseError {
+                token: label,
+                message: "Only loops can be labeled.".to_string(),
+            })
+        }
+    }
+
+    /**
+     * Parses a `break` or `continue`, assuming the keyword has been consumed
+     */
+    fn jump_statement(&mut self) -> ParseResult<Statement> {
+        let keyword = self.get_previous().clone();
+
+        let label = if self.next_matches(&vec![TokenType::Identifier]) {
+            Some(self.get_previous().clone())
+        } else {
+            None
+        };
+
+        self.consume_statement_end()?;
+
+        match keyword.token_type {
+            TokenType::Break => Ok(Statement::Break { keyword, label }),
+            _ => Ok(Statement::Continue { keyword, label }),
+        }
+    }
+
+    fn switch_statement(&mut self) -> ParseResult<Statement> {
+        self.consume(&TokenType::LeftParen)?;
+        let value = self.expression()?;
+        self.consume(&TokenType::RightParen)?;
+        self.consume(&TokenType::LeftBrace)?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+
+        while !self.check_next(&TokenType::RightBrace) && !self.is_at_end() {
+            if default.is_some() {
+                return Err(ParseError {
+                    token: self.peek().clone(),
+                    message: "The default case must be the last case.".to_string(),
+                });
+            }
+
+            if self.next_matches(&vec![TokenType::Case]) {
+                let case_value = self.expression()?;
+                self.consume(&TokenType::Colon)?;
+                cases.push((case_value, self.case_body()?));
+            } else if self.next_matches(&vec![TokenType::Default]) {
+                self.consume(&TokenType::Colon)?;
+                default = Some(self.case_body()?);
+            } else {
+                return Err(ParseError::unexpected_token(
+                    self.peek(),
+                    &[TokenType::Case, TokenType::Default, TokenType::RightBrace],
+                ));
+            }
+        }
+
+        self.consume(&TokenType::RightBrace)?;
+
+        Ok(Statement::Switch {
+            value,
+            cases,
+            default,
+        })
+    }
+
+    /**
+     * Parses the declarations of a switch case, up to the next case or the end of the switch
+     */
+    fn case_body(&mut self) -> ParseResult<Vec<Statement>> {
+        let mut statements = Vec::new();
+
+        while !self.check_next(&TokenType::Case)
+            && !self.check_next(&TokenType::Default)
+            && !self.check_next(&TokenType::RightBrace)
+            && !self.is_at_end()
+        {
+            statements.push(self.declaration()?);
+        }
+
+        Ok(statements)
+    }
+
+    fn return_statement(&mut self) -> ParseResult<Statement> {
+        let keyword = self.get_previous().clone();
+
+        let value = if self.check_next(&TokenType::Semicolon) || self.is_at_end() {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.consume_statement_end()?;
+        Ok(Statement::Return { keyword, value })
+    }
+
+    fn print_statement(&mut self) -> ParseResult<Statement> {
+        let value = self.expression()?;
+        self.consume_statement_end()?;
+        Ok(Statement::Print(value))
+    }
+
+    fn expression_statement(&mut self) -> ParseResult<Statement> {
+        let expr = self.expression()?;
+        self.consume_statement_end()?;
+        Ok(Statement::Expression(expr))
+    }
+
+    fn expression(&mut self) -> ParseResult<Expression> {
+        self.comma()
+    }
+
+    fn comma(&mut self) -> ParseResult<Expression> {
+        self.create_left_associative_binary_expression(vec![TokenType::Comma], Self::assignment)
+    }
+
+    fn assignment(&mut self) -> ParseResult<Expression> {
+        let start = self.current;
+        let expr = self.nested(Self::ternary)?;
+
+        if self.next_matches(&vec![
+            TokenType::Equal,
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
+            let operator = self.get_previous().clone();
+            let target_end = self.current - 1;
+            let value = self.nested(|parser| parser.value(Self::assignment))?;
+
+            return match expr {
+                Expression::Variable(name) => Ok(Expression::Assign {
+                    value: Box::new(desugar_compound_assignment(&name, &operator, value)),
+                    name,
+                }),
+                Expression::Get { object, name } => Ok(Expression::Set {
+                    object,
+                    name,
+                    operator: compound_operator(&operator),
+                    value: Box::new(value),
+                }),
+                _ => Err(self.invalid_assignment_target(&self.tokens[start..target_end], operator)),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /**
+     * Builds the error for assigning to something other than a variable,
+     * calling out targets that are a single reserved word such as `true` or `nil`
+     */
+    fn invalid_assignment_target(&self, target: &[Token], operator: Token) -> ParseError {
+        if let [keyword] = target {
+            if KEYWORDS.contains_key(&keyword.lexeme) {
+                return ParseError {
+                    token: keyword.clone(),
+                    message: format!(
+                        "Invalid assignment target: '{}' is a reserved word.",
+                        keyword.lexeme
+                    ),
+                };
+            }
+        }
+
+        ParseError {
+            token: operator,
+            message: "Invalid assignment target.".to_string(),
+        }
+    }
+
+    fn ternary(&mut self) -> ParseResult<Expression> {
+        let mut expr = self.equality()?;
+
+        if self.next_matches(&vec![TokenType::QuestionMark]) {
+            let question = self.get_previous().clone();
+            let then_branch = self.expression()?;
+            self.consume(&TokenType::Colon)?;
+            // Recursing into the else branch makes chained conditionals nest to the right
+            let else_branch = self.nested(Self::ternary)?;
+            expr = Expression::Ternary {
+                condition: Box::new(expr),
+                question,
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> ParseResult<Expression> {
+        self.create_left_associative_binary_expression(
+            vec![TokenType::BangEqual, TokenType::EqualEqual],
+            Self::comparison,
+        )
+    }
+
+    /**
+     * Comparisons don't chain, as `1 < 2 < 3` would compare the boolean `1 < 2` with `3`
+     */
+    fn comparison(&mut self) -> ParseResult<Expression> {
+        let operators = vec![
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ];
+
+        let expr = self.elvis()?;
+        if !self.next_matches(&operators) {
+            return Ok(expr);
+        }
+
+        let operator = self.get_previous().clone();
+        let right = self.elvis()?;
+
+        if self.next_matches(&operators) {
+            return Err(ParseError {
+                token: self.get_previous().clone(),
+                message: "Comparisons can't be chained; write `a < b ? b < c : false`.".to_string(),
+            });
+        }
+
+        Ok(Expression::Binary {
+            left: Box::new(expr),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    fn elvis(&mut self) -> ParseResult<Expression> {
+        let expr = self.term()?;
+
+        if self.next_matches(&vec![TokenType::QuestionColon]) {
+            return Ok(Expression::Logical {
+                left: Box::new(expr),
+                operator: self.get_previous().clone(),
+                right: Box::new(self.nested(Self::elvis)?),
+            });
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> ParseResult<Expression> {
+        self.create_left_associative_binary_expression(
+            vec![TokenType::Minus, TokenType::Plus],
+            Self::factor,
+        )
+    }
+
+    fn factor(&mut self) -> ParseResult<Expression> {
+        self.create_left_associative_binary_expression(
+            vec![TokenType::Slash, TokenType::Star],
+            Self::unary,
+        )
+    }
+
+    fn unary(&mut self) -> ParseResult<Expression> {
+        if self.next_matches(&vec![TokenType::Bang, TokenType::Minus]) {
+            Ok(Expression::Unary {
+                operator: self.get_previous().clone(),
+                right: Box::new(self.nested(Self::unary)?),
+            })
+        } else {
+            self.postfix()
+        }
+    }
+
+    /**
+     * Parses `x++` or `x--`, which may only follow a variable.
+     * Repeated operators such as `x++++` are parsed so they can be reported as an invalid target.
+     */
+    fn postfix(&mut self) -> ParseResult<Expression> {
+        let mut expr = self.call()?;
+
+        while self.next_matches(&vec![TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.get_previous().clone();
+
+            expr = match expr {
+                Expression::Variable(name) => Expression::Postfix { name, operator },
+                _ => {
+                    return Err(ParseError {
+                        message: format!("Invalid '{}' target.", operator.lexeme),
+                        token: operator,
+                    })
+                }
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn call(&mut self) -> ParseResult<Expression> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.next_matches(&vec![TokenType::LeftParen]) {
+                self.link()?;
+                expr = self.finish_call(expr)?;
+            } else if self.next_matches(&vec![TokenType::LeftBracket]) {
+                self.link()?;
+                let bracket = self.get_previous().clone();
+                let index = self.expression()?;
+
+                if self.next_matches(&vec![TokenType::DotDot]) {
+                    let end = self.expression()?;
+                    self.consume(&TokenType::RightBracket)?;
+
+                    expr = Expression::Slice {
+                        object: Box::new(expr),
+                        bracket,
+                        start: Box::new(index),
+                        end: Box::new(end),
+                    };
+                } else {
+                    self.consume(&TokenType::RightBracket)?;
+
+                    expr = Expression::Index {
+                        object: Box::new(expr),
+                        bracket,
+                        index: Box::new(index),
+                    };
+                }
+            } else if self.next_matches(&vec![TokenType::Dot]) {
+                if !self.check_next(&TokenType::Identifier) {
+                    return Err(ParseError {
+                        token: self.peek().clone(),
+                        message: "Expected property name after '.'.".to_string(),
+                    });
+                }
+
+                self.link()?;
+                expr = Expression::Get {
+                    object: Box::new(expr),
+                    name: self.advance().clone(),
+                };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    /**
+     * Parses the arguments of a call, after the opening parenthesis.
+     * Arguments are parsed below the comma operator so that commas separate them.
+     * Any keyword arguments, written `name: value`, come after the positional ones and may be given in any order.
+     */
+    fn finish_call(&mut self, callee: Expression) -> ParseResult<Expression> {
+        let mut arguments = Vec::new();
+        let mut keyword_arguments: Vec<(Token, Expression)> = Vec::new();
+
+        while !self.check_next(&TokenType::RightParen) {
+            if self.check_name_and_colon() {
+                let name = self.advance().clone();
+                self.advance();
+
+                if keyword_arguments
+                    .iter()
+                    .any(|(other, _)| other.lexeme == name.lexeme)
+                {
+                    return Err(ParseError {
+                        message: format!("Argument '{}' is given more than once.", name.lexeme),
+                        token: name,
+                    });
+                }
+
+                keyword_arguments.push((name, self.assignment()?));
+            } else if keyword_arguments.is_empty() {
+                arguments.push(self.assignment()?);
+            } else {
+                return Err(ParseError {
+                    token: self.peek().clone(),
+                    message: "A positional argument can't follow a keyword argument.".to_string(),
+                });
+            }
+
+            if !self.next_matches(&vec![TokenType::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(&TokenType::RightParen)?;
+
+        Ok(Expression::Call {
+            callee: Box::new(callee),
+            paren: self.get_previous().clone(),
+            arguments,
+            keyword_arguments,
+        })
+    }
+
+    /**
+     * Checks whether the next tokens are `name:`, which starts a keyword argument or labels a loop
+     */
+    fn check_name_and_colon(&self) -> bool {
+        self.check_next(&TokenType::Identifier)
+            && matches!(
+                self.tokens.get(self.current + 1),
+                Some(token) if token.token_type == TokenType::Colon
+            )
+    }
+
+    /**
+     * Parses items separated by commas up to and including the closing token.
+     * A trailing comma is allowed before the closing token, but empty items are not.
+     */
+    fn comma_separated<T>(
+        &mut self,
+        close: &TokenType,
+        item: fn(&mut Self) -> ParseResult<T>,
+    ) -> ParseResult<Vec<T>> {
+        let mut items = Vec::new();
+
+        while !self.check_next(close) {
+            items.push(item(self)?);
+
+            if !self.next_matches(&vec![TokenType::Comma]) {
+                if !self.check_next(close) {
+                    return Err(ParseError::unexpected_token(
+                        self.peek(),
+                        &[TokenType::Comma, close.clone()],
+                    ));
+                }
+                break;
+            }
+        }
+
+        self.consume(close)?;
+        Ok(items)
+    }
+
+    fn primary(&mut self) -> ParseResult<Expression> {
+        match self.peek().token_type {
+            TokenType::False => {
+                self.advance();
+                Ok(Expression::Literal(Some(Literal::Boolean(false))))
+            }
+            TokenType::True => {
+                self.advance();
+                Ok(Expression::Literal(Some(Literal::Boolean(true))))
+            }
+            TokenType::Nil => {
+                self.advance();
+                Ok(Expression::Literal(None))
+            }
+            TokenType::Number => match &self.advance().literal {
+                Some(literal @ Literal::Number(_)) => {
+                    Ok(Expression::Literal(Some(literal.clone())))
+                }
+                _ => Err(ParseError {
+                    token: self.get_previous().clone(),
+                    message: "Invalid number literal.".to_string(),
+                }),
+            },
+            TokenType::String => match &self.advance().literal {
+                Some(literal @ Literal::String(_)) => {
+                    Ok(Expression::Literal(Some(literal.clone())))
+                }
+                _ => Err(ParseError {
+                    token: self.get_previous().clone(),
+                    message: "Invalid string literal.".to_string(),
+                }),
+            },
+            TokenType::LeftParen => {
+                let paren = self.advance().clone();
+                let expr = self.expression()?;
+                self.consume(&TokenType::RightParen)?;
+                Ok(Expression::Grouping {
+                    paren,
+                    expression: Box::new(expr),
+                })
+            }
+            TokenType::Identifier => {
+                self.advance();
+                Ok(Expression::Variable(self.get_previous().clone()))
+            }
+            TokenType::This => Ok(Expression::This(self.advance().clone())),
+            TokenType::Fun => {
+                let keyword = self.advance().clone();
+                let declaration = self.function_body(None)?;
+
+                Ok(Expression::Function {
+                    keyword,
+                    declaration,
+                })
+            }
+            TokenType::If => self.if_expression(),
+            TokenType::LeftBracket => {
+                let bracket = self.advance().clone();
+                let elements = self.comma_separated(&TokenType::RightBracket, Self::assignment)?;
+
+                Ok(Expression::List { bracket, elements })
+            }
+            TokenType::LeftBrace => {
+                let brace = self.advance().clone();
+                let entries = self.comma_separated(&TokenType::RightBrace, |parser| {
+                    let key = parser.assignment()?;
+                    parser.consume(&TokenType::Colon)?;
+                    Ok((key, parser.assignment()?))
+                })?;
+
+                Ok(Expression::Map { brace, entries })
+            }
+            _ => Err(ParseError::unexpected_token(
+                self.peek(),
+                &[
+                    TokenType::Number,
+                    TokenType::String,
+                    TokenType::Identifier,
+                    TokenType::True,
+                    TokenType::False,
+                    TokenType::Nil,
+                    TokenType::This,
+                    TokenType::LeftParen,
+                    TokenType::LeftBracket,
+                    TokenType::LeftBrace,
+                    TokenType::Fun,
+                    TokenType::If,
+                ],
+            )),
+        }
+    }
+
+    /**
+     * Parses `if (condition) a else b`, which is the same as `condition ? a : b`.
+     * The else branch is required, and like the ternary's, extends as far to the right as it can.
+     */
+    fn if_expression(&mut self) -> ParseResult<Expression> {
+        let keyword = self.advance().clone();
+        self.consume(&TokenType::LeftParen)?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen)?;
+
+        let then_branch = self.nested(Self::ternary)?;
+        self.consume(&TokenType::Else)?;
+        let else_branch = self.nested(Self::ternary)?;
+
+        Ok(Expression::Ternary {
+            condition: Box::new(condition),
+            question: keyword,
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        })
+    }
+
+    fn next_matches(&mut self, token_types: &Vec<TokenType>) -> bool {
+        for token_type in token_types {
+            if self.check_next(token_type) {
+                self.advance();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn consume(&mut self, token_type: &TokenType) -> ParseResult<()> {
+        if self.check_next(token_type) {
+            self.advance();
+            Ok(())
+        } else {
+            Err(ParseError::unexpected_token(
+                self.peek(),
+                std::slice::from_ref(token_type),
+            ))
+        }
+    }
+
+    /**
+     * Consumes the ';' ending a statement, which is optional at the end of interactive input
+     */
+    fn consume_statement_end(&mut self) -> ParseResult<()> {
+        if self.interactive && self.is_at_end() {
+            return Ok(());
+        }
+
+        self.consume(&TokenType::Semicolon)
+    }
+
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        if self.is_at_end() {
+            return false;
+        }
+
+        &self.peek().token_type == token_type
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+
+        self.get_previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == TokenType::Eof
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn get_previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    /**
+     * Synchronise the parser to the next statement.
+     * This is used to recover from errors by skipping
+     * tokens until we reach a semicolon or a statement
+     */
+    fn syncronise(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.get_previous().token_type == TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().token_type {
+                TokenType::Break
+                | TokenType::Class
+                | TokenType::Const
+                | TokenType::Continue
+                | TokenType::Do
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::Switch
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return => return,
+                _ => self.advance(),
+            };
+        }
+    }
+}
+
+/**
+ * Desugars the value of a compound assignment, so `x += e` becomes `x = x + e`.
+ * Values of plain assignments are returned unchanged.
+ */
+fn desugar_compound_assignment(name: &Token, operator: &Token, value: Expression) -> Expression {
+    match compound_operator(operator) {
+        Some(operator) => Expression::Binary {
+            left: Box::new(Expression::Variable(name.clone())),
+            operator,
+            right: Box::new(value),
+        },
+        None => value,
+    }
+}
+
+/**
+ * The binary operator a compound assignment operator such as `+=` applies, or None for `=`
+ */
+fn compound_operator(operator: &Token) -> Option<Token> {
+    let token_type = match operator.token_type {
+        TokenType::PlusEqual => TokenType::Plus,
+        TokenType::MinusEqual => TokenType::Minus,
+        TokenType::StarEqual => TokenType::Star,
+        TokenType::SlashEqual => TokenType::Slash,
+        _ => return None,
+    };
+
+    Some(Token::new(
+        token_type,
+        operator.lexeme[..1].to_string(),
+        None,
+        operator.line_number,
+        operator.span,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use crate::frontend::{lex::scanner::Scanner, parse::ast_printer};
+
+    use super::*;
+
+    fn scan_source(source: &str) -> Vec<Token> {
+        Scanner::scan_tokens(source, false)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect()
+    }
+
+    fn parse_source(source: &str) -> ParseResult<Vec<Statement>> {
+        Parser::new(scan_source(source))
+            .parse()
+            .map_err(|mut errors| errors.remove(0))
+    }
+
+    #[test]
+    fn test_parses_simple_expression() {
+        let mut parser = super::Parser::new(vec![
+            Token {
+                token_type: super::TokenType::Number,
+                lexeme: "123".to_string(),
+                literal: Some(super::Literal::Number(123.0)),
                 line_number: 1,
+                span: Span::default(),
             },
             Token {
                 token_type: super::TokenType::Eof,
                 lexeme: "".to_string(),
                 literal: None,
                 line_number: 1,
+                span: Span::default(),
             },
         ]);
 
-        let expr = parser.parse().unwrap();
+        let expr = parser.parse_expression().unwrap();
 
         assert_eq!(
             expr,
             super::Expression::Literal(Some(super::Literal::Number(123.0)))
         );
     }
+
+    #[test]
+    fn test_parses_print_statement() {
+        let mut parser = super::Parser::new(vec![
+            Token {
+                token_type: super::TokenType::Print,
+                lexeme: "print".to_string(),
+                literal: None,
+                line_number: 1,
+                span: Span::default(),
+            },
+            Token {
+                token_type: super::TokenType::Number,
+                lexeme: "1".to_string(),
+                literal: Some(super::Literal::Number(1.0)),
+                line_number: 1,
+                span: Span::default(),
+            },
+            Token {
+                token_type: super::TokenType::Semicolon,
+                lexeme: ";".to_string(),
+                literal: None,
+                line_number: 1,
+                span: Span::default(),
+            },
+            Token {
+                token_type: super::TokenType::Eof,
+                lexeme: "".to_string(),
+                literal: None,
+                line_number: 1,
+                span: Span::default(),
+            },
+        ]);
+
+        let statements = parser.parse().unwrap();
+
+        assert_eq!(
+            statements,
+            vec![Statement::Print(super::Expression::Literal(Some(
+                super::Literal::Number(1.0)
+            )))]
+        );
+    }
+
+    #[rstest]
+    #[case::chained("a = b = 1;", "(; (= a (= b 1)))")]
+    #[case::chained_compound("a = b += 1;", "(; (= a (= b (+ b 1))))")]
+    #[case::grouped_in_expression("(a = 2) * 3;", "(; (* (group (= a 2)) 3))")]
+    fn test_assignment_is_right_associative(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[test]
+    fn test_desugars_compound_assignment() {
+        let statements = parse_source("x -= 1;").unwrap();
+
+        match &statements[0] {
+            Statement::Expression(Expression::Assign { name, value }) => {
+                assert_eq!(name.lexeme, "x");
+                match value.as_ref() {
+                    Expression::Binary {
+                        left,
+                        operator,
+                        right,
+                    } => {
+                        assert!(
+                            matches!(left.as_ref(), Expression::Variable(v) if v.lexeme == "x")
+                        );
+                        assert_eq!(operator.token_type, TokenType::Minus);
+                        assert_eq!(
+                            right.as_ref(),
+                            &Expression::Literal(Some(Literal::Number(1.0)))
+                        );
+                    }
+                    other => panic!("Expected a binary expression, got {:?}", other),
+                }
+            }
+            other => panic!("Expected an assignment, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parses_call_arguments() {
+        let statements = parse_source("f(1, g(2), 3);").unwrap();
+
+        match &statements[0] {
+            Statement::Expression(Expression::Call {
+                callee, arguments, ..
+            }) => {
+                assert!(matches!(callee.as_ref(), Expression::Variable(v) if v.lexeme == "f"));
+                assert_eq!(arguments.len(), 3);
+                assert!(
+                    matches!(&arguments[1], Expression::Call { arguments, .. } if arguments.len() == 1)
+                );
+            }
+            other => panic!("Expected a call, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::separate_arguments("f(1, 2);", "(; (call f 1 2))")]
+    #[case::grouped_comma("f((1, 2));", "(; (call f (group (, 1 2))))")]
+    #[case::ternary_arguments("f(a ? b : c, d);", "(; (call f (ternary a b c) d))")]
+    fn test_commas_separate_call_arguments(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[test]
+    fn test_nested_ternary_is_right_associative() {
+        let statements = parse_source("a ? b : c ? d : e;").unwrap();
+
+        let variable = |name: &str, start: usize| {
+            Box::new(Expression::Variable(Token::new(
+                TokenType::Identifier,
+                name.to_string(),
+                Some(Literal::Identifier(name.to_string())),
+                0,
+                Span::new(start, start + 1),
+            )))
+        };
+        let question = |start: usize| {
+            Token::new(
+                TokenType::QuestionMark,
+                "?".to_string(),
+                None,
+                0,
+                Span::new(start, start + 1),
+            )
+        };
+
+        assert_eq!(
+            statements,
+            vec![Statement::Expression(Expression::Ternary {
+                condition: variable("a", 0),
+                question: question(2),
+                then_branch: variable("b", 4),
+                else_branch: Box::new(Expression::Ternary {
+                    condition: variable("c", 8),
+                    question: question(10),
+                    then_branch: variable("d", 12),
+                    else_branch: variable("e", 16),
+                }),
+            })]
+        );
+    }
+
+    #[test]
+    fn test_ternary_else_branch_binds_tighter_than_comma() {
+        let statements = parse_source("a ? b : c, d;").unwrap();
+
+        match &statements[0] {
+            Statement::Expression(Expression::Binary { left, operator, .. }) => {
+                assert_eq!(operator.token_type, TokenType::Comma);
+                assert!(matches!(left.as_ref(), Expression::Ternary { .. }));
+            }
+            other => panic!("Expected a comma expression, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::nil("nil = 3;", "Invalid assignment target: 'nil' is a reserved word.")]
+    #[case::true_keyword("true = 1;", "Invalid assignment target: 'true' is a reserved word.")]
+    #[case::compound(
+        "false += 1;",
+        "Invalid assignment target: 'false' is a reserved word."
+    )]
+    #[case::expression("nil + 1 = 3;", "Invalid assignment target.")]
+    #[case::number("1 = 3;", "Invalid assignment target.")]
+    fn test_invalid_assignment_target(#[case] source: &str, #[case] expected: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, expected);
+    }
+
+    #[test]
+    fn test_parse_error_span() {
+        let err = parse_source("print (1 + 2;").unwrap_err();
+
+        assert_eq!(err.message, "Expected ')' but found ';'");
+        assert_eq!(err.span(), Span::new(12, 13));
+    }
+
+    #[rstest]
+    #[case::missing_paren("\n\nprint (1 1);", "Expected ')' but found Number '1'")]
+    #[case::missing_semicolon("print 1", "Expected ';' but found end of file")]
+    #[case::missing_name("var = 1;", "Expected Identifier but found '='")]
+    #[case::const_without_value("const a;", "Expected '=' but found ';'")]
+    #[case::missing_expression(
+        "print ;",
+        "Expected Number, String, Identifier, 'true', 'false', 'nil', 'this', '(', '[', '{', 'fun' or 'if' but found ';'"
+    )]
+    #[case::leading_dot_number(
+        "print .5;",
+        "Expected Number, String, Identifier, 'true', 'false', 'nil', 'this', '(', '[', '{', 'fun' or 'if' but found '.'"
+    )]
+    #[case::trailing_dot_number("print 1.;", "Expected property name after '.'.")]
+    fn test_unexpected_token_message(#[case] source: &str, #[case] expected: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, expected);
+    }
+
+    fn parse_number_token(lexeme: &str, literal: Option<Literal>) -> ParseResult<Expression> {
+        let tokens = vec![
+            Token::new(
+                TokenType::Number,
+                lexeme.to_string(),
+                literal,
+                0,
+                Span::new(0, lexeme.len()),
+            ),
+            Token::new(
+                TokenType::Eof,
+                String::new(),
+                None,
+                0,
+                Span::new(lexeme.len(), lexeme.len()),
+            ),
+        ];
+
+        Parser::new(tokens).parse_expression()
+    }
+
+    #[test]
+    fn test_number_uses_scanned_literal() {
+        // A lexeme that would not parse as an f64, such as one with digit separators
+        let expr = parse_number_token("1_000", Some(Literal::Number(1000.0))).unwrap();
+
+        assert_eq!(expr, Expression::Literal(Some(Literal::Number(1000.0))));
+    }
+
+    #[rstest]
+    #[case::missing_literal("1", None)]
+    #[case::wrong_literal("1", Some(Literal::String("1".to_string())))]
+    #[case::unparseable_lexeme("1.2.3", None)]
+    fn test_invalid_number_token_does_not_panic(
+        #[case] lexeme: &str,
+        #[case] literal: Option<Literal>,
+    ) {
+        let err = parse_number_token(lexeme, literal).unwrap_err();
+
+        assert_eq!(err.message, "Invalid number literal.");
+    }
+
+    #[test]
+    fn test_string_uses_decoded_literal() {
+        // The escaped line break is part of the lexeme, but not the decoded string
+        let statements = parse_source("print \"one \\\ntwo\";").unwrap();
+
+        assert_eq!(
+            statements,
+            vec![Statement::Print(Expression::Literal(Some(
+                Literal::String("one two".to_string())
+            )))]
+        );
+    }
+
+    #[rstest]
+    #[case::print("print 1")]
+    #[case::expression("x = 1")]
+    #[case::var("var x = 1")]
+    #[case::last_of_many("var x = 1; print x")]
+    fn test_interactive_allows_missing_final_semicolon(#[case] source: &str) {
+        let tokens = scan_source(source);
+
+        assert!(Parser::with_interactive(tokens, true).parse().is_ok());
+        assert_eq!(
+            parse_source(source).unwrap_err().message,
+            "Expected ';' but found end of file"
+        );
+    }
+
+    #[test]
+    fn test_interactive_still_separates_statements() {
+        let tokens = scan_source("print 1 print 2");
+
+        let errors = Parser::with_interactive(tokens, true).parse().unwrap_err();
+
+        assert_eq!(errors[0].message, "Expected ';' but found 'print'");
+    }
+
+    #[test]
+    fn test_function_declaration() {
+        let statements = parse_source("fun add(a, b) { return a + b; }").unwrap();
+
+        match &statements[..] {
+            [Statement::Function(declaration)] => {
+                let params: Vec<&str> = declaration
+                    .params
+                    .iter()
+                    .map(|p| p.lexeme.as_str())
+                    .collect();
+
+                assert_eq!(declaration.name.as_ref().unwrap().lexeme, "add");
+                assert_eq!(params, vec!["a", "b"]);
+                assert!(matches!(
+                    declaration.body[..],
+                    [Statement::Return { value: Some(_), .. }]
+                ));
+            }
+            other => panic!("Expected a function declaration, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unterminated_block() {
+        let err = parse_source("{ print 1;").unwrap_err();
+
+        assert_eq!(err.message, "Expected '}' but found end of file");
+    }
+
+    #[rstest]
+    #[case::binds_tighter_than_equality("a ?: b == c", "(== (?: a b) c)")]
+    #[case::binds_looser_than_term("a + b ?: c", "(?: (+ a b) c)")]
+    #[case::right_associative("a ?: b ?: c", "(?: a (?: b c))")]
+    fn test_elvis_precedence(#[case] source: &str, #[case] expected: &str) {
+        let expr = Parser::new(scan_source(source)).parse_expression().unwrap();
+
+        assert_eq!(ast_printer::print(&expr), expected);
+    }
+
+    #[rstest]
+    #[case::less("1 < 2 < 3;", "<")]
+    #[case::mixed("1 <= 2 > 0;", ">")]
+    fn test_chained_comparison(#[case] source: &str, #[case] second_operator: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "Comparisons can't be chained; write `a < b ? b < c : false`."
+        );
+        assert_eq!(err.token.lexeme, second_operator);
+    }
+
+    #[test]
+    fn test_parenthesised_comparison_is_not_chained() {
+        assert!(parse_source("(1 < 2) == (2 < 3);").is_ok());
+        assert!(parse_source("1 < 2 == 2 < 3;").is_ok());
+    }
+
+    #[rstest]
+    #[case::parentheses("(", ")")]
+    #[case::unary("- ", "")]
+    #[case::lists("[", "]")]
+    fn test_deep_nesting(#[case] open: &str, #[case] close: &str) {
+        let nested = |depth| format!("{}1{};", open.repeat(depth), close.repeat(depth));
+
+        assert!(parse_source(&nested(1000)).is_ok());
+        assert_eq!(
+            parse_source(&nested(2000)).unwrap_err().message,
+            "Expression is nested too deeply."
+        );
+    }
+
+    #[rstest]
+    #[case::blocks("{", "}")]
+    #[case::if_branches("if (true) ", "")]
+    #[case::else_branches("if (false) print 0; else ", "")]
+    #[case::loops("while (false) ", "")]
+    #[case::functions("fun f() {", "}")]
+    fn test_deep_statement_nesting(#[case] open: &str, #[case] close: &str) {
+        let nested = |depth| format!("{}print 1;{}", open.repeat(depth), close.repeat(depth));
+
+        assert!(parse_source(&nested(1000)).is_ok());
+        assert_eq!(
+            parse_source(&nested(20_000)).unwrap_err().message,
+            "Statement is nested too deeply."
+        );
+    }
+
+    #[rstest]
+    #[case::operators(" + 1")]
+    #[case::calls("()")]
+    #[case::indexes("[0]")]
+    #[case::properties(".a")]
+    fn test_long_chains(#[case] link: &str) {
+        let chain = |length| format!("a{};", link.repeat(length));
+
+        assert!(parse_source(&chain(MAX_CHAIN_LENGTH)).is_ok());
+        assert_eq!(
+            parse_source(&chain(MAX_CHAIN_LENGTH + 1))
+                .unwrap_err()
+                .message,
+            "Expression is nested too deeply."
+        );
+    }
+
+    #[test]
+    fn test_chains_are_counted_through_parentheses() {
+        let half = " + 1".repeat(MAX_CHAIN_LENGTH / 2 + 1);
+        let source = format!("print (a{}){};", half, half);
+
+        assert_eq!(
+            parse_source(&source).unwrap_err().message,
+            "Expression is nested too deeply."
+        );
+    }
+
+    #[test]
+    fn test_chains_are_counted_per_statement() {
+        let statement = format!("a{};", " + 1".repeat(MAX_CHAIN_LENGTH / 2 + 1));
+
+        assert!(parse_source(&statement.repeat(2)).is_ok());
+    }
+
+    #[rstest]
+    #[case::call("f(a, b,);", "(; (call f a b))")]
+    #[case::list("[1, 2,];", "(; (list 1 2))")]
+    #[case::map("print {1: 2,};", "(print (map 1 2))")]
+    #[case::single("f(a,);", "(; (call f a))")]
+    fn test_trailing_comma(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::leading_comma("f(, a);")]
+    #[case::doubled_comma("[1,, 2];")]
+    #[case::only_comma("[,];")]
+    #[case::doubled_trailing_comma("f(a,,);")]
+    fn test_empty_items_rejected(#[case] source: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.token.lexeme, ",");
+    }
+
+    #[test]
+    fn test_lambda_expression_statement() {
+        let statements = parse_source("fun (a) { return a; }(1);").unwrap();
+
+        assert_eq!(
+            ast_printer::print_statement(&statements[0]),
+            "(; (call (fun (a) (return a)) 1))"
+        );
+    }
+
+    #[test]
+    fn test_property_access() {
+        let statements = parse_source("a.b(1).c;").unwrap();
+
+        assert_eq!(
+            ast_printer::print_statement(&statements[0]),
+            "(; (. (call (. a b) 1) c))"
+        );
+    }
+
+    #[rstest]
+    #[case::end_of_input("a.", "")]
+    #[case::before_semicolon("a.;", ";")]
+    #[case::before_paren("a.(b);", "(")]
+    fn test_missing_property_name(#[case] source: &str, #[case] found: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, "Expected property name after '.'.");
+        assert_eq!(err.token.lexeme, found);
+    }
+
+    #[rstest]
+    #[case::initializer("var x = { var t = 1; t * 2 };", "(var x (block (var t 1) (* t 2)))")]
+    #[case::with_semicolon("var x = { 1; };", "(var x (block 1))")]
+    #[case::no_value("var x = { print 1; };", "(var x (block (print 1)))")]
+    #[case::assignment("x = { 1 };", "(; (= x (block 1)))")]
+    #[case::empty_map("var x = {};", "(var x (map))")]
+    #[case::map("var x = {1: 2};", "(var x (map 1 2))")]
+    #[case::nested_value("var x = { var a = 1; { 2 } };", "(var x (block (var a 1) (block 2)))")]
+    #[case::only_nested("var x = { { 2 } };", "(var x (block (block 2)))")]
+    #[case::nested_statement(
+        "var x = { { print 1; } 2 };",
+        "(var x (block (; (block (print 1))) 2))"
+    )]
+    #[case::nested_map("var x = { {1: 2} };", "(var x (block (map 1 2)))")]
+    fn test_block_expression(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::map_missing_comma(
+        "var m = {\"a\": 1 \"b\": 2};",
+        "Expected ',' or '}' but found String 'b'"
+    )]
+    #[case::block_missing_semicolon("var x = { print 1 };", "Expected ';' but found '}'")]
+    fn test_invalid_block_or_map(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(parse_source(source).unwrap_err().message, expected);
+    }
+
+    #[rstest]
+    #[case::value("print if (a) 1 else 2;", "print a ? 1 : 2;")]
+    #[case::chained("x = if (a) 1 else if (b) 2 else 3;", "x = a ? 1 : b ? 2 : 3;")]
+    fn test_if_expression_parses_as_ternary(#[case] source: &str, #[case] ternary: &str) {
+        let statements = parse_source(source).unwrap();
+        let expected = parse_source(ternary).unwrap();
+
+        assert_eq!(
+            ast_printer::print_statement(&statements[0]),
+            ast_printer::print_statement(&expected[0])
+        );
+    }
+
+    #[test]
+    fn test_if_at_statement_start_is_a_statement() {
+        let statements = parse_source("if (a) print 1; else print 2;").unwrap();
+
+        assert!(matches!(statements[0], Statement::If { .. }));
+    }
+
+    #[test]
+    fn test_if_expression_requires_else() {
+        let err = parse_source("print if (a) 1;").unwrap_err();
+
+        assert_eq!(err.message, "Expected 'else' but found ';'");
+    }
+
+    #[rstest]
+    #[case::index("s[1];", "(; (index s 1))")]
+    #[case::slice("s[1..3];", "(; (slice s 1 3))")]
+    #[case::slice_expressions("s[a + 1..len(s)];", "(; (slice s (+ a 1) (call len s)))")]
+    fn test_parses_index_and_slice(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[test]
+    fn test_parses_const_declaration() {
+        let statements = parse_source("const a = 1;").unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), "(const a 1)");
+    }
+
+    #[test]
+    fn test_parse_reports_every_error() {
+        let errors = Parser::new(scan_source("var = 1;\nprint 1;\nprint (;\nprint 2;"))
+            .parse()
+            .unwrap_err();
+
+        let lines: Vec<usize> = errors.iter().map(|err| err.token.line_number).collect();
+        assert_eq!(lines, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_comment_tokens_are_ignored() {
+        let tokens = Scanner::scan_tokens("print /* one */ 1; // done", true)
+            .into_iter()
+            .map(|t| t.unwrap())
+            .collect();
+
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), "(print 1)");
+    }
+
+    #[rstest]
+    #[case::methods_and_getters(
+        "class A { init(x) { this.x = x; } area { return this.x; } }",
+        "(class A (fun init(x) (; (= (. this x) x))) (get area (return (. this x))))"
+    )]
+    #[case::empty("class A {}", "(class A)")]
+    #[case::class_method(
+        "class A { class make() { return A(); } }",
+        "(class A (class (fun make() (return (call A)))))"
+    )]
+    #[case::compound_set("a.b += 1;", "(; (+= (. a b) 1))")]
+    fn test_class_declaration(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::increment("x++;", "(; (x++))")]
+    #[case::decrement("print x-- * 2;", "(print (* (x--) 2))")]
+    #[case::negated("-x++;", "(; (- (x++)))")]
+    fn test_postfix(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::literal("1++;", "Invalid '++' target.")]
+    #[case::grouping("(x)--;", "Invalid '--' target.")]
+    #[case::property("a.b++;", "Invalid '++' target.")]
+    #[case::twice("x++++;", "Invalid '++' target.")]
+    fn test_invalid_postfix_target(#[case] source: &str, #[case] expected: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, expected);
+    }
+
+    #[rstest]
+    #[case::only_rest("fun sum(...xs) {}", "(fun sum(...xs))")]
+    #[case::params_and_rest("fun f(a, b, ...rest) {}", "(fun f(a b ...rest))")]
+    #[case::lambda("var f = fun (...xs) {};", "(var f (fun (...xs)))")]
+    fn test_rest_parameter(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::default("fun f(a, b = 2) {}", "(fun f(a b=2))")]
+    #[case::default_expression("fun f(a = 1 + 2, b = a) {}", "(fun f(a=(+ 1 2) b=a))")]
+    #[case::default_and_rest("fun f(a = 1, ...rest) {}", "(fun f(a=1 ...rest))")]
+    fn test_default_parameter(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[test]
+    fn test_required_parameter_after_default() {
+        let err = parse_source("fun f(a = 1, b) {}").unwrap_err();
+
+        assert_eq!(
+            err.message,
+            "A parameter without a default can't follow one with a default."
+        );
+        assert_eq!(err.token.lexeme, "b");
+    }
+
+    #[rstest]
+    #[case::not_last("fun f(...xs, y) {}", "A rest parameter must be the last parameter.")]
+    #[case::missing_name("fun f(...) {}", "Expected Identifier but found ')'")]
+    fn test_invalid_rest_parameter(#[case] source: &str, #[case] expected: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, expected);
+    }
+
+    #[rstest]
+    #[case::keywords("f(b: 2, a: 1);", "(; (call f b: 2 a: 1))")]
+    #[case::after_positional("f(1, c: 3,);", "(; (call f 1 c: 3))")]
+    #[case::ternary_argument("f(a ? b : c);", "(; (call f (ternary a b c)))")]
+    #[case::map_argument("f(a: {b: 1});", "(; (call f a: (map b 1)))")]
+    fn test_keyword_arguments(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::positional_after_keyword(
+        "f(a: 1, 2);",
+        "A positional argument can't follow a keyword argument."
+    )]
+    #[case::duplicate("f(a: 1, a: 2);", "Argument 'a' is given more than once.")]
+    fn test_invalid_keyword_arguments(#[case] source: &str, #[case] expected: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, expected);
+    }
+
+    #[rstest]
+    #[case::cases(
+        "switch (x) { case 1: print 1; case 2: print 2; print 3; }",
+        "(switch x (case 1 (print 1)) (case 2 (print 2) (print 3)))"
+    )]
+    #[case::default(
+        "switch (x) { case 1: default: print 0; }",
+        "(switch x (case 1) (default (print 0)))"
+    )]
+    #[case::empty("switch (x) {}", "(switch x)")]
+    fn test_switch_statement(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::default_not_last(
+        "switch (x) { default: case 1: }",
+        "The default case must be the last case."
+    )]
+    #[case::statement_before_case(
+        "switch (x) { print 1; }",
+        "Expected 'case', 'default' or '}' but found 'print'"
+    )]
+    #[case::missing_colon("switch (x) { case 1 print 1; }", "Expected ':' but found 'print'")]
+    fn test_invalid_switch_statement(#[case] source: &str, #[case] expected: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, expected);
+    }
+
+    #[rstest]
+    #[case::block("do { print i; } while (i < 3);", "(do (block (print i)) (< i 3))")]
+    #[case::single_statement("do i += 1; while (i);", "(do (; (= i (+ i 1))) i)")]
+    fn test_do_while_statement(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::break_loop("while (a) break;", "(while a (break))")]
+    #[case::continue_loop("while (a) continue;", "(while a (continue))")]
+    #[case::labeled_while(
+        "outer: while (a) { while (b) break outer; }",
+        "(outer: (while a (block (while b (break outer)))))"
+    )]
+    #[case::labeled_do(
+        "outer: do continue outer; while (a);",
+        "(outer: (do (continue outer) a))"
+    )]
+    fn test_loop_jumps(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::single_statement("for (x in xs) print x;", "(for x in xs (print x))")]
+    #[case::block("for (c in \"ab\") { print c; }", "(for c in ab (block (print c)))")]
+    #[case::labeled(
+        "outer: for (x in xs) break outer;",
+        "(outer: (for x in xs (break outer)))"
+    )]
+    fn test_for_in_statement(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
+
+    #[rstest]
+    #[case::missing_in("for (x xs) {}", "Expected 'in' after the loop variable.")]
+    #[case::missing_variable("for (in xs) {}", "Expected 'in' after the loop variable.")]
+    #[case::c_style(
+        "for (var i = 0; i < 3; i += 1) {}",
+        "Expected Identifier but found 'var'"
+    )]
+    fn test_invalid_for_in_statement(#[case] source: &str, #[case] expected: &str) {
+        let err = parse_source(source).unwrap_err();
+
+        assert_eq!(err.message, expected);
+    }
+
+    #[test]
+    fn test_only_loops_can_be_labeled() {
+        let err = parse_source("outer: print 1;").unwrap_err();
+
+        assert_eq!(err.message, "Only loops can be labeled.");
+        assert_eq!(err.token.lexeme, "outer");
+    }
+
+    #[test]
+    fn test_do_without_while() {
+        let err = parse_source("do print 1; print 2;").unwrap_err();
+
+        assert_eq!(err.message, "Expected 'while' but found 'print'");
+    }
+
+    #[rstest]
+    #[case::if_only("if (a) print 1;", "(if a (print 1))")]
+    #[case::if_else("if (a) print 1; else print 2;", "(if a (print 1) (print 2))")]
+    #[case::else_if_chain(
+        "if (a) {} else if (b) {} else {}",
+        "(if a (block) (if b (block) (block)))"
+    )]
+    #[case::dangling_else(
+        "if (a) if (b) print 1; else print 2;",
+        "(if a (if b (print 1) (print 2)))"
+    )]
+    fn test_if_statement(#[case] source: &str, #[case] expected: &str) {
+        let statements = parse_source(source).unwrap();
+
+        assert_eq!(ast_printer::print_statement(&statements[0]), expected);
+    }
 }
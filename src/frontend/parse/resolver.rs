@@ -0,0 +1,592 @@
+use std::collections::HashSet;
+
+use crate::frontend::lex::token::Token;
+
+use super::{
+    expression::Expression,
+    recursive_descent::ParseError,
+    statement::{FunctionDeclaration, Statement},
+    with_stack,
+};
+
+/**
+ * The kind of method the code being resolved is inside, which decides whether it can use `this`
+ */
+#[derive(Clone, Copy, PartialEq)]
+enum MethodKind {
+    None,
+    Method,
+    ClassMethod,
+}
+
+/**
+ * Checks a parsed program for mistakes which can be found before it runs,
+ * such as declaring the same variable twice in one local scope, a `break` outside of a loop,
+ * a `return` outside of a function, or `this` outside of a method.
+ * Globals aren't tracked, so they may be redeclared, as the REPL relies on.
+ * Mistakes which don't stop the program running, such as unreachable code, are given back as warnings.
+ */
+pub struct Resolver {
+    scopes: Vec<HashSet<String>>,
+    // The label of each loop enclosing the current statement, innermost last
+    loops: Vec<Option<String>>,
+    method: MethodKind,
+    in_function: bool,
+    // Whether the code is in a block expression within the innermost function, which must produce a value
+    in_block_expression: bool,
+    errors: Vec<ParseError>,
+    warnings: Vec<ParseError>,
+}
+
+impl Resolver {
+    /**
+     * Resolves the statements, giving back any warnings if there were no errors
+     */
+    pub fn resolve(statements: &[Statement]) -> Result<Vec<ParseError>, Vec<ParseError>> {
+        let mut resolver = Resolver::new();
+        resolver.statements(statements);
+        resolver.finish()
+    }
+
+    pub fn resolve_expression(expr: &Expression) -> Result<Vec<ParseError>, Vec<ParseError>> {
+        let mut resolver = Resolver::new();
+        resolver.expression(expr);
+        resolver.finish()
+    }
+
+    fn new() -> Resolver {
+        Resolver {
+            scopes: Vec::new(),
+            loops: Vec::new(),
+            method: MethodKind::None,
+            in_function: false,
+            in_block_expression: false,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn finish(self) -> Result<Vec<ParseError>, Vec<ParseError>> {
+        if self.errors.is_empty() {
+            Ok(self.warnings)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        self.unreachable(statements);
+
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        with_stack(|| match statement {
+            Statement::Block(statements) => {
+                self.scopes.push(HashSet::new());
+                self.statements(statements);
+                self.scopes.pop();
+            }
+            Statement::Class {
+                name,
+                methods,
+                getters,
+                class_methods,
+            } => {
+                self.declare(name);
+
+                let enclosing_method = self.method;
+                self.method = MethodKind::Method;
+                for method in methods.iter().chain(getters) {
+                    self.function(method);
+                }
+                self.method = MethodKind::ClassMethod;
+                for method in class_methods {
+                    self.function(method);
+                }
+                self.method = enclosing_method;
+            }
+            Statement::Break { keyword, label } | Statement::Continue { keyword, label } => {
+                self.jump(keyword, label)
+            }
+            Statement::Const { name, initializer } => {
+                self.expression(initializer);
+                self.declare(name);
+            }
+            Statement::DoWhile {
+                body,
+                condition,
+                label,
+                ..
+            } => {
+                self.loop_body(body, label);
+                self.expression(condition);
+            }
+            Statement::Expression(expr) | Statement::Print(expr) => self.expression(expr),
+            Statement::ForIn {
+                variable,
+                iterable,
+                body,
+                label,
+            } => {
+                self.expression(iterable);
+                self.scopes.push(HashSet::new());
+                self.declare(variable);
+                self.loop_body(body, label);
+                self.scopes.pop();
+            }
+            Statement::Function(declaration) => {
+                if let Some(name) = &declaration.name {
+                    self.declare(name);
+                }
+                self.function(declaration);
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            Statement::Return { keyword, value } => {
+                if !self.in_function {
+                    self.errors.push(ParseError {
+                        token: keyword.clone(),
+                        message: "Can't return from top-level code.".to_string(),
+                    });
+                } else if self.in_block_expression {
+                    self.errors.push(ParseError {
+                        token: keyword.clone(),
+                        message: "Can't return from inside a block expression.".to_string(),
+                    });
+                }
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            Statement::Switch {
+                value,
+                cases,
+                default,
+            } => {
+                self.expression(value);
+                for (case_value, statements) in cases {
+                    self.expression(case_value);
+                    self.scopes.push(HashSet::new());
+                    self.statements(statements);
+                    self.scopes.pop();
+                }
+                if let Some(statements) = default {
+                    self.scopes.push(HashSet::new());
+                    self.statements(statements);
+                    self.scopes.pop();
+                }
+            }
+            Statement::Var { name, initializer } => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.declare(name);
+            }
+            Statement::While {
+                condition,
+                body,
+                label,
+                ..
+            } => {
+                self.expression(condition);
+                self.loop_body(body, label);
+            }
+        })
+    }
+
+    fn loop_body(&mut self, body: &Statement, label: &Option<Token>) {
+        self.loops
+            .push(label.as_ref().map(|label| label.lexeme.clone()));
+        self.statement(body);
+        self.loops.pop();
+    }
+
+    /**
+     * Checks a `break` or `continue` is inside a loop, and that any loop it names encloses it
+     */
+    fn jump(&mut self, keyword: &Token, label: &Option<Token>) {
+        let message = match label {
+            None if self.loops.is_empty() => {
+                format!("Can't use '{}' outside of a loop.", keyword.lexeme)
+            }
+            Some(label) if !self.loops.contains(&Some(label.lexeme.clone())) => {
+                format!("No enclosing loop is labeled '{}'.", label.lexeme)
+            }
+            _ => return,
+        };
+
+        self.errors.push(ParseError {
+            token: label.as_ref().unwrap_or(keyword).clone(),
+            message,
+        });
+    }
+
+    /**
+     * Resolves a function's body, in a new scope holding its parameters.
+     * Loops outside the function can't be jumped to from inside it.
+     */
+    fn function(&mut self, declaration: &FunctionDeclaration) {
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let enclosing_in_function = std::mem::replace(&mut self.in_function, true);
+        let enclosing_in_block_expression = std::mem::replace(&mut self.in_block_expression, false);
+        self.scopes.push(HashSet::new());
+
+        // Defaults are evaluated in the function's scope, so they can refer to the parameters before them
+        for (param, default) in declaration.params.iter().zip(&declaration.defaults) {
+            if let Some(default) = default {
+                self.expression(default);
+            }
+            self.declare(param);
+        }
+        if let Some(rest) = &declaration.rest {
+            self.declare(rest);
+        }
+        self.statements(&declaration.body);
+
+        self.scopes.pop();
+        self.in_function = enclosing_in_function;
+        self.in_block_expression = enclosing_in_block_expression;
+        self.loops = enclosing_loops;
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        with_stack(|| match expr {
+            Expression::Assign { value, .. } => self.expression(value),
+            Expression::Binary { left, right, .. } | Expression::Logical { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Block {
+                statements, value, ..
+            } => {
+                // A block expression must produce its value, so it can't jump to a loop around it or return
+                let enclosing_loops = std::mem::take(&mut self.loops);
+                let enclosing_in_block_expression =
+                    std::mem::replace(&mut self.in_block_expression, true);
+                self.scopes.push(HashSet::new());
+                self.statements(statements);
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                self.scopes.pop();
+                self.in_block_expression = enclosing_in_block_expression;
+                self.loops = enclosing_loops;
+            }
+            Expression::Call {
+                callee,
+                arguments,
+                keyword_arguments,
+                ..
+            } => {
+                self.expression(callee);
+                arguments
+                    .iter()
+                    .chain(keyword_arguments.iter().map(|(_, argument)| argument))
+                    .for_each(|argument| self.expression(argument));
+            }
+            Expression::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.expression(condition);
+                self.expression(then_branch);
+                self.expression(else_branch);
+            }
+            Expression::Get { object, .. } => self.expression(object),
+            Expression::Grouping { expression, .. } => self.expression(expression),
+            Expression::Function { declaration, .. } => self.function(declaration),
+            Expression::Index { object, index, .. } => {
+                self.expression(object);
+                self.expression(index);
+            }
+            Expression::List { elements, .. } => {
+                elements.iter().for_each(|element| self.expression(element));
+            }
+            Expression::Slice {
+                object, start, end, ..
+            } => {
+                self.expression(object);
+                self.expression(start);
+                self.expression(end);
+            }
+            Expression::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+            }
+            Expression::Set { object, value, .. } => {
+                self.expression(object);
+                self.expression(value);
+            }
+            Expression::Unary { right, .. } => self.expression(right),
+            Expression::This(keyword) => self.this(keyword),
+            Expression::Literal(_) | Expression::Postfix { .. } | Expression::Variable(_) => {}
+        })
+    }
+
+    /**
+     * Warns about statements following a `return`, `break` or `continue` in the same block, which can never run
+     */
+    fn unreachable(&mut self, statements: &[Statement]) {
+        let jump = statements
+            .iter()
+            .enumerate()
+            .find_map(|(index, statement)| match statement {
+                Statement::Return { keyword, .. }
+                | Statement::Break { keyword, .. }
+                | Statement::Continue { keyword, .. } => Some((index, keyword)),
+                _ => None,
+            });
+
+        if let Some((index, keyword)) = jump {
+            if index + 1 < statements.len() {
+                self.warnings.push(ParseError {
+                    token: keyword.clone(),
+                    message: format!("Code after '{}' is unreachable.", keyword.lexeme),
+                });
+            }
+        }
+    }
+
+    /**
+     * Checks `this` is used inside a method, or a function within one, where it refers to an instance
+     */
+    fn this(&mut self, keyword: &Token) {
+        let message = match self.method {
+            MethodKind::Method => return,
+            MethodKind::ClassMethod => "Can't use 'this' in a class method.",
+            MethodKind::None => "Can't use 'this' outside of a class.",
+        };
+
+        self.errors.push(ParseError {
+            token: keyword.clone(),
+            message: message.to_string(),
+        });
+    }
+
+    /**
+     * Records a variable declared in the innermost local scope, erroring if it is already declared there
+     */
+    fn declare(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.insert(name.lexeme.clone()) {
+                self.errors.push(ParseError {
+                    token: name.clone(),
+                    message: format!("Already a variable named '{}' in this scope.", name.lexeme),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use crate::frontend::{check, run_to_string, LoxScriptError};
+
+    #[rstest]
+    #[case::block("{ var a = 1; var a = 2; }", 0)]
+    #[case::function("fun f() {\n var a = 1;\n var a = 2;\n}", 2)]
+    #[case::parameters("fun f(a, a) {}", 0)]
+    #[case::rest_parameter("fun f(a, ...a) {}", 0)]
+    #[case::parameter_and_local("fun f(a) { var a = 1; }", 0)]
+    #[case::lambda("var f = fun () { var a; var a; };", 0)]
+    #[case::block_expression("var x = { var a = 1; var a = 2; a };", 0)]
+    #[case::local_function("{ fun f() {} fun f() {} }", 0)]
+    fn test_local_redeclaration_is_an_error(#[case] source: &str, #[case] line_number: usize) {
+        match check(source) {
+            Err(LoxScriptError::Syntax(reports)) => {
+                assert_eq!(reports.len(), 1);
+                assert_eq!(reports[0].line_number, line_number);
+                assert_eq!(
+                    reports[0].message,
+                    format!(
+                        "Already a variable named '{}' in this scope.",
+                        reports[0].location
+                    )
+                );
+            }
+            other => panic!("Expected a resolve error, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::break_at_top_level("break;", "Can't use 'break' outside of a loop.")]
+    #[case::continue_in_if("if (true) continue;", "Can't use 'continue' outside of a loop.")]
+    #[case::break_in_function_in_loop(
+        "while (true) { fun f() { break; } }",
+        "Can't use 'break' outside of a loop."
+    )]
+    #[case::break_in_block_expression(
+        "while (true) { var a = { break; 1 }; }",
+        "Can't use 'break' outside of a loop."
+    )]
+    #[case::unknown_label("while (true) break outer;", "No enclosing loop is labeled 'outer'.")]
+    #[case::sibling_label(
+        "outer: while (true) {} while (true) continue outer;",
+        "No enclosing loop is labeled 'outer'."
+    )]
+    #[case::label_outside_function(
+        "outer: while (true) { fun f() { while (true) break outer; } }",
+        "No enclosing loop is labeled 'outer'."
+    )]
+    fn test_invalid_loop_jump(#[case] source: &str, #[case] expected: &str) {
+        match check(source) {
+            Err(LoxScriptError::Syntax(reports)) => {
+                assert_eq!(reports.len(), 1);
+                assert_eq!(reports[0].message, expected);
+            }
+            other => panic!("Expected a resolve error, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::top_level("return;", 0)]
+    #[case::value("print 1;\nreturn 2;", 1)]
+    #[case::in_block("{ return; }", 0)]
+    #[case::in_loop("while (true) {\n return;\n}", 1)]
+    #[case::in_block_expression("var a = { return 1; 2 };", 0)]
+    #[case::after_function("fun f() { return 1; }\nreturn f();", 1)]
+    #[case::in_class_body_method_call("class A { m() { return 1; } } return A().m();", 0)]
+    fn test_return_outside_function(#[case] source: &str, #[case] line_number: usize) {
+        match check(source) {
+            Err(LoxScriptError::Syntax(reports)) => {
+                assert_eq!(reports.len(), 1);
+                assert_eq!(reports[0].message, "Can't return from top-level code.");
+                assert_eq!(reports[0].location, "return");
+                assert_eq!(reports[0].line_number, line_number);
+            }
+            other => panic!("Expected a resolve error, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::in_function("fun f() { var x = { return 1; }; }", 1)]
+    #[case::nested_block("fun f() { var x = { { return 1; } 2 }; }", 1)]
+    #[case::in_function_within_block_expression(
+        "fun f() { var x = { fun g() { return 1; } g() }; }",
+        0
+    )]
+    fn test_return_inside_block_expression(#[case] source: &str, #[case] errors: usize) {
+        let reports = match check(source) {
+            Ok(_) => Vec::new(),
+            Err(LoxScriptError::Syntax(reports)) => reports,
+            other => panic!("Expected a resolve error, got {:?}", other),
+        };
+
+        assert_eq!(reports.len(), errors);
+        for report in reports {
+            assert_eq!(
+                report.message,
+                "Can't return from inside a block expression."
+            );
+        }
+    }
+
+    #[rstest]
+    #[case::after_return("fun f() { return; print 1; }", "return")]
+    #[case::after_return_value("fun f() {\n return 1;\n var a;\n}", "return")]
+    #[case::after_break("while (true) { break; print 1; }", "break")]
+    #[case::after_continue("while (true) { continue; print 1; }", "continue")]
+    #[case::in_nested_block("fun f() { { return; print 1; } }", "return")]
+    #[case::in_switch_case("fun f(a) { switch (a) { case 1: return; print 1; } }", "return")]
+    #[case::several_statements("fun f() { return; print 1; print 2; }", "return")]
+    fn test_unreachable_code_warning(#[case] source: &str, #[case] keyword: &str) {
+        let warnings = check(source).unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].location, keyword);
+        assert_eq!(
+            warnings[0].message,
+            format!("Code after '{}' is unreachable.", keyword)
+        );
+    }
+
+    #[rstest]
+    #[case::return_last("fun f() { print 1; return; }")]
+    #[case::return_in_if("fun f(a) { if (a) return; print 1; }")]
+    #[case::return_in_nested_block("fun f() { { return; } print 1; }")]
+    #[case::break_last_in_loop("while (true) { print 1; break; }")]
+    fn test_reachable_code_has_no_warning(#[case] source: &str) {
+        assert_eq!(check(source).unwrap(), vec![]);
+    }
+
+    #[rstest]
+    #[case::function("fun f() { return 1; } print f();", "1\n")]
+    #[case::lambda("print (fun () { return 2; })();", "2\n")]
+    #[case::method("class A { m() { return 3; } } print A().m();", "3\n")]
+    #[case::nested_in_loop("fun f() { while (true) { return 4; } } print f();", "4\n")]
+    fn test_return_inside_function(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::top_level("print this;", "Can't use 'this' outside of a class.")]
+    #[case::function("fun f() { return this; }", "Can't use 'this' outside of a class.")]
+    #[case::lambda(
+        "var f = fun () { return this.x; };",
+        "Can't use 'this' outside of a class."
+    )]
+    #[case::after_class(
+        "class A { m() { return this; } } this;",
+        "Can't use 'this' outside of a class."
+    )]
+    #[case::class_method(
+        "class A { class make() { return this; } }",
+        "Can't use 'this' in a class method."
+    )]
+    fn test_invalid_this(#[case] source: &str, #[case] expected: &str) {
+        match check(source) {
+            Err(LoxScriptError::Syntax(reports)) => {
+                assert_eq!(reports.len(), 1);
+                assert_eq!(reports[0].message, expected);
+                assert_eq!(reports[0].location, "this");
+            }
+            other => panic!("Expected a resolve error, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::method("class A { m() { return this; } }")]
+    #[case::getter("class A { g { return this.x; } }")]
+    #[case::initializer("class A { init() { this.x = 1; } }")]
+    #[case::closure_in_method("class A { m() { return fun () { return this; }; } }")]
+    #[case::class_in_method("class A { m() { class B { n() { return this; } } return this; } }")]
+    fn test_this_in_method(#[case] source: &str) {
+        assert!(check(source).is_ok());
+    }
+
+    #[rstest]
+    #[case::global("var a = 1; var a = 2; print a;", "2\n")]
+    #[case::global_function("fun f() { return 1; } fun f() { return 2; } print f();", "2\n")]
+    #[case::shadowing("var a = 1; { var a = 2; print a; } print a;", "2\n1\n")]
+    #[case::nested_blocks("{ var a = 1; { var a = 2; print a; } }", "2\n")]
+    #[case::sibling_blocks("{ var a = 1; } { var a = 2; print a; }", "2\n")]
+    fn test_allowed_redeclaration(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(run_to_string(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_resolve_error_stops_program_running() {
+        let result = run_to_string("print 1; { var a; var a; }");
+
+        assert!(matches!(result, Err(LoxScriptError::Syntax(_))));
+    }
+}
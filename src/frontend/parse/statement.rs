@@ -0,0 +1,97 @@
+use std::rc::Rc;
+
+use crate::frontend::lex::token::Token;
+
+use super::expression::Expression;
+
+#[derive(Debug, PartialEq)]
+pub enum Statement {
+    Block(Vec<Statement>),
+    // Stops the innermost loop, or the enclosing loop with the label
+    Break {
+        keyword: Token,
+        label: Option<Token>,
+    },
+    Class {
+        name: Token,
+        methods: Vec<Rc<FunctionDeclaration>>,
+        // Methods declared without parameters, which are run when the property is accessed
+        getters: Vec<Rc<FunctionDeclaration>>,
+        // Methods declared with a leading `class`, which are called on the class rather than an instance
+        class_methods: Vec<Rc<FunctionDeclaration>>,
+    },
+    // A variable which can't be assigned to after it is declared
+    Const {
+        name: Token,
+        initializer: Expression,
+    },
+    // Skips to the next iteration of the innermost loop, or the enclosing loop with the label
+    Continue {
+        keyword: Token,
+        label: Option<Token>,
+    },
+    // A loop which runs its body once before checking the condition
+    DoWhile {
+        body: Box<Statement>,
+        // The `while` before the condition, where a problem with the condition is reported
+        keyword: Token,
+        condition: Expression,
+        label: Option<Token>,
+    },
+    Expression(Expression),
+    // A loop which runs its body once for each element of a list, or grapheme of a string
+    ForIn {
+        variable: Token,
+        iterable: Expression,
+        body: Box<Statement>,
+        label: Option<Token>,
+    },
+    Function(Rc<FunctionDeclaration>),
+    If {
+        // The `if`, where a problem with the condition is reported
+        keyword: Token,
+        condition: Expression,
+        then_branch: Box<Statement>,
+        else_branch: Option<Box<Statement>>,
+    },
+    Print(Expression),
+    Return {
+        keyword: Token,
+        value: Option<Expression>,
+    },
+    Switch {
+        value: Expression,
+        // Each case's value and the statements run when it equals the switch's value
+        cases: Vec<(Expression, Vec<Statement>)>,
+        // Run when no case matches
+        default: Option<Vec<Statement>>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expression>,
+    },
+    While {
+        // The `while`, where a problem with the condition is reported
+        keyword: Token,
+        condition: Expression,
+        body: Box<Statement>,
+        // The name `break` and `continue` can use to target this loop from a nested one
+        label: Option<Token>,
+    },
+}
+
+/**
+ * A function, shared between the AST and every function value created from it.
+ * Anonymous functions have no name.
+ */
+#[derive(Debug, PartialEq)]
+pub struct FunctionDeclaration {
+    pub name: Option<Token>,
+    pub params: Vec<Token>,
+    // The default value of each parameter, used when it isn't given an argument.
+    // Only trailing parameters may have one.
+    pub defaults: Vec<Option<Expression>>,
+    // A trailing `...name` parameter, which collects any extra arguments into a list
+    pub rest: Option<Token>,
+    pub body: Vec<Statement>,
+}
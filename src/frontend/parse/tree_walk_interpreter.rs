@@ -1,6 +1,24 @@
-use crate::frontend::lex::token::{Literal, Token, TokenType};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    io::{self, Write},
+    rc::Rc,
+};
 
-use super::expression::*;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::frontend::lex::token::{Literal, MapKey, Span, Token, TokenType};
+
+use super::{
+    callable::{Callable, HostFn, HostFunction, LoxFunction},
+    class::{LoxClass, LoxInstance},
+    environment::Environment,
+    expression::*,
+    natives,
+    random::Random,
+    statement::{FunctionDeclaration, Statement},
+    with_stack,
+};
 
 #[derive(Debug, PartialEq)]
 pub struct RuntimeError {
@@ -26,514 +44,3140 @@ impl RuntimeError {
     pub fn operands_must_be_numbers(operator: Token) -> Result<Option<Literal>, Self> {
         Self::with_token("Operands must be numbers.".to_string(), operator)
     }
-}
 
-pub fn interpret(expr: &Expression) -> Result<Option<Literal>, RuntimeError> {
-    evaluate_expression(expr)
+    /**
+     * Reports binary operands of the wrong types, saying if one was nil, as that is usually a missing value
+     */
+    pub fn invalid_operands(
+        message: &str,
+        operator: Token,
+        left: &Option<Literal>,
+        right: &Option<Literal>,
+    ) -> Result<Option<Literal>, Self> {
+        let message = match (left, right) {
+            (None, None) => format!("{} Both operands were nil.", message),
+            (None, _) => format!("{} Left operand was nil.", message),
+            (_, None) => format!("{} Right operand was nil.", message),
+            _ => message.to_string(),
+        };
+
+        Self::with_token(message, operator)
+    }
+
+    pub fn undefined_variable(name: Token) -> Result<Option<Literal>, Self> {
+        let message = format!("Undefined variable '{}'.", name.lexeme);
+        Self::with_token(message, name)
+    }
+
+    /**
+     * The location in the source of the token the error was reported at, if there is one
+     */
+    pub fn span(&self) -> Option<Span> {
+        self.token.as_ref().map(|token| token.span)
+    }
 }
 
-fn evaluate_expression(expr: &Expression) -> Result<Option<Literal>, RuntimeError> {
-    match expr {
-        Expression::Binary { .. } => evaluate_binary(expr),
-        Expression::Grouping(_) => evaluate_grouping(expr),
-        Expression::Unary { .. } => evaluate_unary(expr),
-        Expression::Literal(literal) => Ok(literal.clone()),
-        Expression::Ternary {
-            condition,
-            then_branch,
-            else_branch,
-        } => {
-            let condition = evaluate_expression(condition)?;
+/**
+ * Options that change how the interpreter evaluates programs
+ */
+#[derive(Debug, Clone)]
+pub struct InterpreterConfig {
+    // Treat `0` and `""` as falsey, for users porting code from other languages
+    pub loose_truthiness: bool,
+    // Follow IEEE 754 when dividing by zero, giving `inf`, `-inf` or `NaN` instead of an error
+    pub ieee_division: bool,
+    // How deeply calls may nest before the program is stopped, rather than overflowing the host's stack
+    pub max_call_depth: usize,
+    // How many statements and expressions may be evaluated before the program is stopped, if limited
+    pub max_steps: Option<usize>,
+    // How deeply expressions may nest while being evaluated, bounding the memory used by the stack
+    pub max_expression_depth: usize,
+    // Error on `==`, `!=` and switch cases between values of different types, rather than treating them as unequal.
+    // Anything may still be compared with nil.
+    pub strict_equality: bool,
+    // Error when the condition of an `if`, `while` or `?:` isn't a boolean, rather than checking its truthiness
+    pub strict_conditions: bool,
+}
 
-            if is_truthy(&condition) {
-                evaluate_expression(then_branch)
-            } else {
-                evaluate_expression(else_branch)
-            }
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        InterpreterConfig {
+            loose_truthiness: false,
+            ieee_division: false,
+            max_call_depth: 200,
+            max_steps: None,
+            max_expression_depth: 2048,
+            strict_equality: false,
+            strict_conditions: false,
         }
     }
 }
 
-fn evaluate_grouping(group: &Expression) -> Result<Option<Literal>, RuntimeError> {
-    match group {
-        Expression::Grouping(expr) => evaluate_expression(expr),
-        _ => RuntimeError::new(format!(
-            "Unexpected expression, expected Grouping {:?}",
-            group
-        )),
+/**
+ * Why a statement stopped executing before reaching its end
+ */
+enum Unwind {
+    Error(RuntimeError),
+    Return(Option<Literal>),
+    // Jumps out of the innermost loop, or the one with the label
+    Break(Option<String>),
+    Continue(Option<String>),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Unwind::Error(err)
     }
 }
 
-fn evaluate_binary(binary: &Expression) -> Result<Option<Literal>, RuntimeError> {
-    match binary {
-        Expression::Binary {
-            left,
-            operator,
-            right,
-        } => {
-            let left = evaluate_expression(left)?;
-            let right = evaluate_expression(right)?;
-
-            match operator.token_type {
-                TokenType::Minus => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        Ok(Some(Literal::Number(l - r)))
+/**
+ * Executes statements, writing anything they print to the given output sink.
+ * Variables defined by one call to `interpret` remain visible to the next.
+ */
+pub struct Interpreter<'a> {
+    output: &'a mut dyn Write,
+    environment: Rc<RefCell<Environment>>,
+    config: InterpreterConfig,
+    call_depth: usize,
+    expression_depth: usize,
+    steps_remaining: Option<usize>,
+    random: Random,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(output: &'a mut dyn Write) -> Interpreter<'a> {
+        Self::with_config(output, InterpreterConfig::default())
+    }
+
+    pub fn with_config(output: &'a mut dyn Write, config: InterpreterConfig) -> Interpreter<'a> {
+        Interpreter {
+            output,
+            environment: Rc::new(RefCell::new(globals())),
+            steps_remaining: config.max_steps,
+            config,
+            call_depth: 0,
+            expression_depth: 0,
+            random: Random::from_clock(),
+        }
+    }
+
+    /**
+     * Forgets everything scripts have defined, leaving only the built-in natives
+     */
+    pub fn reset(&mut self) {
+        self.environment = Rc::new(RefCell::new(globals()));
+        self.steps_remaining = self.config.max_steps;
+    }
+
+    /**
+     * The generator used by the `random` native, which `seed` can make reproducible
+     */
+    pub fn random(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    /**
+     * Writes text to the output without a trailing newline, flushing it so it is shown straight away
+     */
+    pub fn write(&mut self, text: &str) -> Result<(), RuntimeError> {
+        write!(self.output, "{}", text)
+            .and_then(|_| self.output.flush())
+            .map_err(output_error)
+    }
+
+    /**
+     * Registers a native function, so scripts run afterwards can call it by name
+     */
+    pub fn define_native(&mut self, name: &str, arity: usize, function: HostFn) {
+        let native = HostFunction {
+            name: name.to_string(),
+            arity,
+            function,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(name, Some(Literal::Callable(Rc::new(native))));
+    }
+
+    pub fn interpret(&mut self, statements: &[Statement]) -> Result<(), RuntimeError> {
+        for statement in statements {
+            match self.execute(statement) {
+                Ok(()) => {}
+                Err(Unwind::Error(err)) => return Err(err),
+                // The resolver only allows returns inside a function, and jumps inside a loop
+                Err(Unwind::Return(_) | Unwind::Break(_) | Unwind::Continue(_)) => unreachable!(),
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Runs the body of a function in the given environment, giving the value it returns.
+     * Each parameter is bound to its argument, or to its default if it wasn't passed one.
+     * Defaults are evaluated in the function's scope, so they can refer to the parameters before them.
+     */
+    pub fn execute_function_body(
+        &mut self,
+        declaration: &FunctionDeclaration,
+        environment: Environment,
+        arguments: Vec<Option<Option<Literal>>>,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        let result = self.in_scope(environment, |interpreter| {
+            let params = declaration.params.iter().zip(&declaration.defaults);
+
+            for ((param, default), argument) in params.zip(arguments) {
+                let value = match (argument, default) {
+                    (Some(argument), _) => argument,
+                    (None, Some(default)) => interpreter.evaluate_expression(default)?,
+                    (None, None) => None,
+                };
+                interpreter
+                    .environment
+                    .borrow_mut()
+                    .define(&param.lexeme, value);
+            }
+
+            declaration
+                .body
+                .iter()
+                .try_for_each(|statement| interpreter.execute(statement))
+        });
+
+        match result {
+            Ok(()) => Ok(None),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(err)) => Err(err),
+            // The resolver doesn't allow jumping to a loop outside the function
+            Err(Unwind::Break(_) | Unwind::Continue(_)) => unreachable!(),
+        }
+    }
+
+    /**
+     * Evaluates a single expression and writes its value to the output
+     */
+    pub fn display(&mut self, expr: &Expression) -> Result<(), RuntimeError> {
+        let value = self.evaluate_expression(expr)?;
+        let text = self.stringify(&value)?;
+        self.write_line(&text)
+    }
+
+    /**
+     * Converts a value to the text shown when it is printed.
+     * An instance whose class has a `toString` method is shown as the result of calling it.
+     */
+    pub fn stringify(&mut self, value: &Option<Literal>) -> Result<String, RuntimeError> {
+        if let Some(Literal::Instance(instance)) = value {
+            let class = instance.borrow().class.clone();
+
+            if let Some(method) = class.find_method("toString") {
+                let method = method.bind(instance.clone());
+
+                // Methods are always named, and errors calling one are reported at its name
+                if let Some(name) = method.name().cloned() {
+                    let text = self.call(&method, &[], &name)?;
+                    return Ok(stringify(&text));
+                }
+            }
+        }
+
+        Ok(stringify(value))
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Result<(), Unwind> {
+        self.step()?;
+
+        with_stack(|| match statement {
+            Statement::Block(statements) => {
+                let environment = Environment::with_enclosing(self.environment.clone());
+                self.execute_block(statements, environment)
+            }
+            Statement::Class {
+                name,
+                methods,
+                getters,
+                class_methods,
+            } => {
+                let class = LoxClass::new(
+                    name.lexeme.clone(),
+                    methods,
+                    getters,
+                    class_methods,
+                    self.environment.clone(),
+                );
+                Ok(self.declare(name, Some(Literal::Class(Rc::new(class))))?)
+            }
+            Statement::Function(declaration) => {
+                // Declarations are always named, only function expressions can be anonymous
+                if let Some(name) = &declaration.name {
+                    let function = LoxFunction::new(declaration.clone(), self.environment.clone());
+                    self.declare(name, Some(Literal::Callable(Rc::new(function))))?;
+                }
+                Ok(())
+            }
+            Statement::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.evaluate_expression(value)?,
+                    None => None,
+                };
+
+                Err(Unwind::Return(value))
+            }
+            Statement::Break { label, .. } => Err(Unwind::Break(
+                label.as_ref().map(|label| label.lexeme.clone()),
+            )),
+            Statement::Const { name, initializer } => {
+                let value = self.evaluate_expression(initializer)?;
+
+                let mut environment = self.environment.borrow_mut();
+                environment.check_redeclaration(name)?;
+                environment.define_constant(&name.lexeme, value);
+                Ok(())
+            }
+            Statement::Continue { label, .. } => Err(Unwind::Continue(
+                label.as_ref().map(|label| label.lexeme.clone()),
+            )),
+            Statement::DoWhile {
+                body,
+                keyword,
+                condition,
+                label,
+            } => self.execute_loop(keyword, condition, body, label, true),
+            Statement::Expression(expr) => {
+                self.evaluate_expression(expr)?;
+                Ok(())
+            }
+            Statement::ForIn {
+                variable,
+                iterable,
+                body,
+                label,
+            } => {
+                let value = self.evaluate_expression(iterable)?;
+                let elements = elements(&value).ok_or_else(|| RuntimeError {
+                    message: format!(
+                        "Can only iterate over lists and strings, not {}.",
+                        type_name(&value)
+                    ),
+                    token: Some(iterable.token().unwrap_or(variable).clone()),
+                })?;
+
+                for element in elements {
+                    // Each iteration gets its own variable, so closures capture the element they were made for
+                    let mut environment = Environment::with_enclosing(self.environment.clone());
+                    environment.define(&variable.lexeme, element);
+
+                    match self.execute_block(std::slice::from_ref(body), environment) {
+                        Err(Unwind::Break(target)) if targets_loop(&target, label) => break,
+                        Err(Unwind::Continue(target)) if targets_loop(&target, label) => continue,
+                        result => result?,
                     }
-                    _ => RuntimeError::operands_must_be_numbers(operator.clone()),
-                },
+                }
+
+                Ok(())
+            }
+            Statement::Print(expr) => {
+                let value = self.evaluate_expression(expr)?;
+                let text = self.stringify(&value)?;
+                Ok(self.write_line(&text)?)
+            }
+            Statement::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(initializer) => self.evaluate_expression(initializer)?,
+                    None => None,
+                };
+
+                Ok(self.declare(name, value)?)
+            }
+            Statement::If {
+                keyword,
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if self.evaluate_condition(condition, condition.token().or(Some(keyword)))? {
+                    self.execute(then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch)
+                } else {
+                    Ok(())
+                }
+            }
+            Statement::Switch {
+                value,
+                cases,
+                default,
+            } => {
+                let value = self.evaluate_expression(value)?;
 
-                TokenType::Plus => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        Ok(Some(Literal::Number(l + r)))
+                // Only the first matching case runs, as cases don't fall through
+                for (case_value, statements) in cases {
+                    let case = self.evaluate_expression(case_value)?;
+                    self.check_comparable(&value, &case, "case", case_value.token())?;
+
+                    if evaluate_equal(&value, &case) {
+                        let environment = Environment::with_enclosing(self.environment.clone());
+                        return self.execute_block(statements, environment);
                     }
+                }
 
-                    (Some(Literal::String(l)), r) => Ok(Some(Literal::String(format!(
-                        "{}{}",
-                        l,
-                        match r {
-                            Some(r) => r.to_string(),
-                            None => "nil".to_string(),
-                        }
-                    )))),
+                match default {
+                    Some(statements) => {
+                        let environment = Environment::with_enclosing(self.environment.clone());
+                        self.execute_block(statements, environment)
+                    }
+                    None => Ok(()),
+                }
+            }
+            Statement::While {
+                keyword,
+                condition,
+                body,
+                label,
+            } => self.execute_loop(keyword, condition, body, label, false),
+        })
+    }
 
-                    (l, Some(Literal::String(r))) => Ok(Some(Literal::String(format!(
-                        "{}{}",
-                        match l {
-                            Some(l) => l.to_string(),
-                            None => "nil".to_string(),
-                        },
-                        r
-                    )))),
+    /**
+     * Runs the body for as long as the condition holds, checking it before each iteration
+     * except the first when `body_first` is set.
+     * A `break` or `continue` targeting this loop is handled here.
+     */
+    fn execute_loop(
+        &mut self,
+        keyword: &Token,
+        condition: &Expression,
+        body: &Statement,
+        label: &Option<Token>,
+        body_first: bool,
+    ) -> Result<(), Unwind> {
+        let mut skip_condition = body_first;
+
+        loop {
+            if !skip_condition
+                && !self.evaluate_condition(condition, condition.token().or(Some(keyword)))?
+            {
+                break;
+            }
+            skip_condition = false;
+
+            match self.execute(body) {
+                Err(Unwind::Break(target)) if targets_loop(&target, label) => break,
+                Err(Unwind::Continue(target)) if targets_loop(&target, label) => continue,
+                result => result?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Counts one step of execution against the limit, if there is one
+     */
+    fn step(&mut self) -> Result<(), RuntimeError> {
+        match &mut self.steps_remaining {
+            Some(0) => Err(RuntimeError {
+                message: "Execution limit exceeded.".to_string(),
+                token: None,
+            }),
+            Some(remaining) => {
+                *remaining -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /**
+     * Executes the statements in the given environment, restoring the current one afterwards
+     */
+    fn execute_block(
+        &mut self,
+        statements: &[Statement],
+        environment: Environment,
+    ) -> Result<(), Unwind> {
+        self.in_scope(environment, |interpreter| {
+            statements
+                .iter()
+                .try_for_each(|statement| interpreter.execute(statement))
+        })
+    }
+
+    /**
+     * Binds a variable declared with `var`, `fun` or `class` in the current scope, which can't replace a constant
+     */
+    fn declare(&mut self, name: &Token, value: Option<Literal>) -> Result<(), RuntimeError> {
+        let mut environment = self.environment.borrow_mut();
+        environment.check_redeclaration(name)?;
+        environment.define(&name.lexeme, value);
+        Ok(())
+    }
+
+    /**
+     * Runs with the given environment as the current one, restoring the current one afterwards
+     */
+    fn in_scope<T>(&mut self, environment: Environment, run: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+        let result = run(self);
+        self.environment = previous;
+        result
+    }
+
+    fn evaluate_expression(&mut self, expr: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        // Errors raised without a location, such as those from native functions,
+        // are reported at the innermost enclosing expression
+        let result = if self.expression_depth >= self.config.max_expression_depth {
+            RuntimeError::new("Expression is nested too deeply.".to_string())
+        } else {
+            self.expression_depth += 1;
+            let result = self.step().and_then(|_| with_stack(|| self.evaluate(expr)));
+            self.expression_depth -= 1;
+            result
+        };
+
+        result.map_err(|mut err| {
+            if err.token.is_none() {
+                err.token = expr.token().cloned();
+            }
+            err
+        })
+    }
+
+    fn evaluate(&mut self, expr: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        match expr {
+            Expression::Binary { .. } => self.evaluate_binary(expr),
+            Expression::Grouping { .. } => self.evaluate_grouping(expr),
+            Expression::Unary { .. } => self.evaluate_unary(expr),
+            Expression::Literal(literal) => Ok(literal.clone()),
+            Expression::Variable(name) => self.environment.borrow().get(name),
+            Expression::Assign { name, value } => {
+                let value = self.evaluate_expression(value)?;
+                self.environment.borrow_mut().assign(name, value)
+            }
+            Expression::Postfix { name, operator } => {
+                let value = self.environment.borrow().get(name)?;
+                let step = match operator.token_type {
+                    TokenType::PlusPlus => 1.0,
+                    _ => -1.0,
+                };
 
+                match value {
+                    Some(Literal::Number(n)) => {
+                        self.environment
+                            .borrow_mut()
+                            .assign(name, Some(Literal::Number(n + step)))?;
+                        Ok(value)
+                    }
                     _ => RuntimeError::with_token(
-                        "operands must be numbers or strings.".to_string(),
+                        format!(
+                            "Operand of '{}' must be a number, not {}.",
+                            operator.lexeme,
+                            type_name(&value)
+                        ),
                         operator.clone(),
                     ),
-                },
+                }
+            }
+            Expression::Block {
+                statements, value, ..
+            } => {
+                let environment = Environment::with_enclosing(self.environment.clone());
 
-                TokenType::Slash => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        if r == 0.0 {
-                            return RuntimeError::with_token(
-                                "Division by zero.".to_string(),
-                                operator.clone(),
-                            );
+                self.in_scope(environment, |interpreter| {
+                    for statement in statements {
+                        match interpreter.execute(statement) {
+                            Ok(()) => {}
+                            Err(Unwind::Error(err)) => return Err(err),
+                            // The resolver doesn't allow returning or jumping to a loop outside the block
+                            Err(Unwind::Return(_) | Unwind::Break(_) | Unwind::Continue(_)) => {
+                                unreachable!()
+                            }
                         }
-
-                        Ok(Some(Literal::Number(l / r)))
                     }
-                    _ => RuntimeError::operands_must_be_numbers(operator.clone()),
-                },
 
-                TokenType::Star => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        Ok(Some(Literal::Number(l * r)))
+                    match value {
+                        Some(value) => interpreter.evaluate_expression(value),
+                        None => Ok(None),
                     }
-                    _ => RuntimeError::operands_must_be_numbers(operator.clone()),
+                })
+            }
+            Expression::Call { .. } => self.evaluate_call(expr),
+            Expression::Function { declaration, .. } => {
+                let function = LoxFunction::new(declaration.clone(), self.environment.clone());
+                Ok(Some(Literal::Callable(Rc::new(function))))
+            }
+            Expression::Index { .. } => self.evaluate_index(expr),
+            Expression::Slice { .. } => self.evaluate_slice(expr),
+            Expression::Get { object, name } => match self.evaluate_expression(object)? {
+                Some(Literal::Instance(instance)) => self.get_property(&instance, name),
+                Some(Literal::Class(class)) => match class.find_class_method(&name.lexeme) {
+                    Some(method) => Ok(Some(Literal::Callable(method.clone()))),
+                    None => RuntimeError::with_token(
+                        format!("Undefined class method '{}'.", name.lexeme),
+                        name.clone(),
+                    ),
                 },
-
-                TokenType::Greater => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        Ok(Some(Literal::Boolean(l > r)))
+                _ => RuntimeError::with_token(
+                    "Only instances have properties.".to_string(),
+                    name.clone(),
+                ),
+            },
+            Expression::Set {
+                object,
+                name,
+                operator,
+                value,
+            } => {
+                let instance = match self.evaluate_expression(object)? {
+                    Some(Literal::Instance(instance)) => instance,
+                    _ => {
+                        return RuntimeError::with_token(
+                            "Only instances have fields.".to_string(),
+                            name.clone(),
+                        )
                     }
-                    _ => Ok(Some(Literal::Boolean(false))),
-                },
+                };
 
-                TokenType::GreaterEqual => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        Ok(Some(Literal::Boolean(l >= r)))
+                let value = match operator {
+                    Some(operator) => {
+                        let current = self.get_property(&instance, name)?;
+                        let value = self.evaluate_expression(value)?;
+                        self.binary_operation(operator, current, value)?
                     }
-                    _ => Ok(Some(Literal::Boolean(false))),
-                },
+                    None => self.evaluate_expression(value)?,
+                };
 
-                TokenType::Less => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        Ok(Some(Literal::Boolean(l < r)))
-                    }
-                    _ => Ok(Some(Literal::Boolean(false))),
-                },
+                instance.borrow_mut().set_field(&name.lexeme, value.clone());
+                Ok(value)
+            }
+            Expression::This(keyword) => self.environment.borrow().get(keyword),
+            Expression::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate_expression(left)?;
 
-                TokenType::LessEqual => match (left, right) {
-                    (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
-                        Ok(Some(Literal::Boolean(l <= r)))
-                    }
-                    _ => Ok(Some(Literal::Boolean(false))),
-                },
+                match operator.token_type {
+                    TokenType::QuestionColon if left.is_some() => Ok(left),
+                    TokenType::QuestionColon => self.evaluate_expression(right),
+                    _ => RuntimeError::with_token(
+                        format!("Unknown logical operator '{}'.", operator.lexeme),
+                        operator.clone(),
+                    ),
+                }
+            }
+            Expression::List { elements, .. } => {
+                let mut values = Vec::new();
+                for element in elements {
+                    values.push(self.evaluate_expression(element)?);
+                }
+
+                Ok(Some(Literal::list(values)))
+            }
+            Expression::Map { brace, entries } => {
+                let mut map = BTreeMap::new();
+                for (key, value) in entries {
+                    let key = self.evaluate_expression(key)?;
+                    let key = match MapKey::from_literal(&key) {
+                        Some(key) => key,
+                        None => {
+                            return RuntimeError::with_token(
+                                "Map keys must be strings or numbers.".to_string(),
+                                brace.clone(),
+                            )
+                        }
+                    };
 
-                TokenType::BangEqual => Ok(Some(Literal::Boolean(!evaluate_equal(&left, &right)))),
-                TokenType::EqualEqual => Ok(Some(Literal::Boolean(evaluate_equal(&left, &right)))),
+                    map.insert(key, self.evaluate_expression(value)?);
+                }
 
-                _ => RuntimeError::with_token("Unexpected operator".to_string(), operator.clone()),
+                Ok(Some(Literal::map(map)))
+            }
+            Expression::Ternary {
+                condition,
+                question,
+                then_branch,
+                else_branch,
+            } => {
+                if self.evaluate_condition(condition, Some(question))? {
+                    self.evaluate_expression(then_branch)
+                } else {
+                    self.evaluate_expression(else_branch)
+                }
             }
         }
-        _ => RuntimeError::new("Unexpected expression, expected Binary".to_string()),
     }
-}
 
-fn evaluate_unary(unary: &Expression) -> Result<Option<Literal>, RuntimeError> {
-    match unary {
-        Expression::Unary { operator, right } => {
-            let right = evaluate_expression(right)?;
+    fn evaluate_call(&mut self, call: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        match call {
+            Expression::Call {
+                callee,
+                paren,
+                arguments,
+                keyword_arguments,
+            } => {
+                let callee = self.evaluate_expression(callee)?;
 
-            match operator.token_type {
-                TokenType::Minus => match right {
-                    Some(Literal::Number(n)) => Ok(Some(Literal::Number(-n))),
-                    _ => RuntimeError::operands_must_be_numbers(operator.clone()),
-                },
+                let mut values = Vec::new();
+                for argument in arguments {
+                    values.push(self.evaluate_expression(argument)?);
+                }
 
-                TokenType::Bang => Ok(Some(Literal::Boolean(!is_truthy(&right)))),
+                let mut keyword_values = Vec::new();
+                for (name, argument) in keyword_arguments {
+                    keyword_values.push((name.clone(), self.evaluate_expression(argument)?));
+                }
 
-                _ => RuntimeError::with_token("Unexpected operator".to_string(), operator.clone()),
+                match callee {
+                    Some(Literal::Callable(function)) if keyword_values.is_empty() => {
+                        self.call(function.as_ref(), &values, paren)
+                    }
+                    Some(Literal::Callable(function)) => {
+                        self.call_with_keywords(function.as_ref(), &values, &keyword_values, paren)
+                    }
+                    Some(Literal::Class(class)) => {
+                        self.instantiate(&class, &values, &keyword_values, paren)
+                    }
+                    _ => RuntimeError::with_token(
+                        "Can only call functions and classes.".to_string(),
+                        paren.clone(),
+                    ),
+                }
             }
+            _ => RuntimeError::new("Unexpected expression, expected Call".to_string()),
         }
-        _ => RuntimeError::new("Unexpected expression, expected Unary".to_string()),
     }
-}
 
-fn is_truthy(literal: &Option<Literal>) -> bool {
-    match literal {
-        Some(Literal::Boolean(b)) => *b,
-        None => false,
-        _ => true,
+    /**
+     * Calls the function after checking it was given the right number of arguments,
+     * and that calls aren't nested too deeply
+     */
+    fn call(
+        &mut self,
+        function: &dyn Callable,
+        arguments: &[Option<Literal>],
+        paren: &Token,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        check_arity(function.arity(), function.max_arity(), arguments, paren)?;
+        self.call_nested(Some(paren), |interpreter| {
+            function.call(interpreter, arguments)
+        })
     }
-}
 
-fn evaluate_equal(left: &Option<Literal>, right: &Option<Literal>) -> bool {
-    match (left, right) {
-        (None, None) => true,
-        (Some(_), None) => false,
-        (None, Some(_)) => false,
+    /**
+     * Calls a function given to a native function, such as the one `map` applies to each element.
+     * The native function must have checked the function accepts this many arguments.
+     */
+    pub fn call_callback(
+        &mut self,
+        function: &dyn Callable,
+        arguments: &[Option<Literal>],
+    ) -> Result<Option<Literal>, RuntimeError> {
+        self.call_nested(None, |interpreter| function.call(interpreter, arguments))
+    }
 
-        (Some(Literal::Number(l)), Some(Literal::Number(r))) => l == r,
-        (Some(Literal::Number(_)), Some(_)) => false,
+    /**
+     * Makes a call one level deeper, erroring at the paren, if any, if calls are nested too deeply
+     */
+    fn call_nested(
+        &mut self,
+        paren: Option<&Token>,
+        call: impl FnOnce(&mut Self) -> Result<Option<Literal>, RuntimeError>,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        if self.call_depth >= self.config.max_call_depth {
+            return Err(RuntimeError {
+                message: "Stack overflow.".to_string(),
+                token: paren.cloned(),
+            });
+        }
 
-        (Some(Literal::String(l)), Some(Literal::String(r))) => l == r,
-        (Some(Literal::String(_)), Some(_)) => false,
+        self.call_depth += 1;
+        let result = call(self);
+        self.call_depth -= 1;
 
-        (Some(Literal::Boolean(l)), Some(Literal::Boolean(r))) => l == r,
-        (Some(Literal::Boolean(_)), Some(_)) => false,
+        result
+    }
 
-        (Some(Literal::Identifier(l)), Some(Literal::Identifier(r))) => l == r,
-        (Some(Literal::Identifier(_)), Some(_)) => false,
+    /**
+     * Calls the function with some arguments given by name. The function checks they match its parameters.
+     */
+    fn call_with_keywords(
+        &mut self,
+        function: &dyn Callable,
+        arguments: &[Option<Literal>],
+        keyword_arguments: &[(Token, Option<Literal>)],
+        paren: &Token,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        self.call_nested(Some(paren), |interpreter| {
+            function.call_with_keywords(interpreter, arguments, keyword_arguments)
+        })
     }
-}
 
-#[cfg(test)]
-mod test {
-    use rstest::rstest;
+    /**
+     * Creates an instance of the class, passing the arguments to its `init` method if it has one
+     */
+    fn instantiate(
+        &mut self,
+        class: &Rc<LoxClass>,
+        arguments: &[Option<Literal>],
+        keyword_arguments: &[(Token, Option<Literal>)],
+        paren: &Token,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(class.clone())));
 
-    use super::*;
+        match (class.find_method("init"), keyword_arguments.first()) {
+            (Some(init), None) => {
+                self.call(&init.bind(instance.clone()), arguments, paren)?;
+            }
+            (Some(init), Some(_)) => {
+                let init = init.bind(instance.clone());
+                self.call_with_keywords(&init, arguments, keyword_arguments, paren)?;
+            }
+            (None, None) => check_arity(0, Some(0), arguments, paren)?,
+            (None, Some((name, _))) => {
+                RuntimeError::with_token(
+                    format!("{} has no parameter named '{}'.", class, name.lexeme),
+                    name.clone(),
+                )?;
+            }
+        }
 
-    #[test]
-    fn test_literal_equality() {
-        assert_eq!(Literal::Number(1.0), Literal::Number(1.0));
-        assert_ne!(Literal::Number(1.0), Literal::Number(2.0));
-        assert_eq!(
-            Literal::String("hello".to_string()),
-            Literal::String("hello".to_string())
-        );
-        assert_ne!(
-            Literal::String("hello".to_string()),
-            Literal::String("world".to_string())
-        );
-        assert_eq!(Literal::Boolean(true), Literal::Boolean(true));
-        assert_ne!(Literal::Boolean(true), Literal::Boolean(false));
+        Ok(Some(Literal::Instance(instance)))
     }
 
-    #[rstest]
-    #[case::boolean_true(Literal::Boolean(true), true)]
-    #[case::boolean_false(Literal::Boolean(false), false)]
-    #[case::number(Literal::Number(1.0), true)]
-    #[case::string(Literal::String("hello".to_string()), true)]
-    #[case::string_false(Literal::String("false".to_string()), true)]
-    #[case::string_true(Literal::String("true".to_string()), true)]
-    #[case::string_empty(Literal::String("".to_string()), true)]
-    #[case::identifier(Literal::Identifier("foo".to_string()), true)]
-    fn test_literal_truthiness(#[case] literal: Literal, #[case] expected: bool) {
-        assert_eq!(is_truthy(&Some(literal)), expected);
+    /**
+     * Looks up a property of an instance: a field, then a getter, which is run, then a method
+     */
+    fn get_property(
+        &mut self,
+        instance: &Rc<RefCell<LoxInstance>>,
+        name: &Token,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        if let Some(value) = instance.borrow().get_field(&name.lexeme) {
+            return Ok(value);
+        }
+
+        let class = instance.borrow().class.clone();
+
+        if let Some(getter) = class.find_getter(&name.lexeme) {
+            return self.call(&getter.bind(instance.clone()), &[], name);
+        }
+
+        if let Some(method) = class.find_method(&name.lexeme) {
+            let method = method.bind(instance.clone());
+            return Ok(Some(Literal::Callable(Rc::new(method))));
+        }
+
+        RuntimeError::with_token(
+            format!("Undefined property '{}'.", name.lexeme),
+            name.clone(),
+        )
     }
 
-    #[test]
-    fn test_unary_minus() {
-        let expr = Expression::Unary {
-            operator: Token {
-                token_type: TokenType::Minus,
-                lexeme: "-".to_string(),
-                literal: None,
-                line_number: 0,
-            },
-            right: Box::new(Expression::Literal(Some(Literal::Number(1.0)))),
-        };
+    fn evaluate_index(&mut self, index: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        match index {
+            Expression::Index {
+                object,
+                bracket,
+                index,
+            } => {
+                let object = self.evaluate_expression(object)?;
+                let index = self.evaluate_expression(index)?;
 
-        let result = interpret(&expr);
-        assert_eq!(result, Ok(Some(Literal::Number(-1.0))));
+                match (object, index) {
+                    (Some(Literal::List(elements)), Some(Literal::Number(n))) => {
+                        match elements.borrow().get(to_index(n, Some(bracket))?) {
+                            Some(element) => Ok(element.clone()),
+                            None => RuntimeError::with_token(
+                                format!("List index {} is out of range.", n),
+                                bracket.clone(),
+                            ),
+                        }
+                    }
+                    (Some(Literal::List(_)), _) => RuntimeError::with_token(
+                        "List index must be a number.".to_string(),
+                        bracket.clone(),
+                    ),
+                    (Some(Literal::String(string)), Some(Literal::Number(n))) => {
+                        match string.graphemes(true).nth(to_index(n, Some(bracket))?) {
+                            Some(grapheme) => Ok(Some(Literal::String(grapheme.to_string()))),
+                            None => RuntimeError::with_token(
+                                format!("String index {} is out of range.", n),
+                                bracket.clone(),
+                            ),
+                        }
+                    }
+                    (Some(Literal::String(_)), _) => RuntimeError::with_token(
+                        "String index must be a number.".to_string(),
+                        bracket.clone(),
+                    ),
+                    (Some(Literal::Map(entries)), key) => match MapKey::from_literal(&key) {
+                        Some(key) => match entries.borrow().get(&key) {
+                            Some(value) => Ok(value.clone()),
+                            None => RuntimeError::with_token(
+                                format!("Undefined key '{}'.", key),
+                                bracket.clone(),
+                            ),
+                        },
+                        None => RuntimeError::with_token(
+                            "Map keys must be strings or numbers.".to_string(),
+                            bracket.clone(),
+                        ),
+                    },
+                    _ => RuntimeError::with_token(
+                        "Only lists, maps and strings can be indexed.".to_string(),
+                        bracket.clone(),
+                    ),
+                }
+            }
+            _ => RuntimeError::new("Unexpected expression, expected Index".to_string()),
+        }
     }
 
-    #[rstest]
-    #[case::boolean_true(Literal::Boolean(true), Literal::Boolean(false))]
-    #[case::boolean_false(Literal::Boolean(false), Literal::Boolean(true))]
-    #[case::number(Literal::Number(1.0), Literal::Boolean(false))]
-    #[case::string(Literal::String("hello".to_string()), Literal::Boolean(false))]
-    #[case::string_false(Literal::String("false".to_string()), Literal::Boolean(false))]
-    #[case::string_true(Literal::String("true".to_string()), Literal::Boolean(false))]
-    #[case::string_empty(Literal::String("".to_string()), Literal::Boolean(false))]
-    #[case::identifier(Literal::Identifier("foo".to_string()), Literal::Boolean(false))]
-    fn test_unary_bang(#[case] input: Literal, #[case] expected: Literal) {
-        let expr = Expression::Unary {
-            operator: Token {
-                token_type: TokenType::Bang,
-                lexeme: "!".to_string(),
-                literal: None,
-                line_number: 0,
-            },
-            right: Box::new(Expression::Literal(Some(input))),
-        };
+    /**
+     * Evaluates `string[start..end]`, the graphemes from `start` up to but not including `end`
+     */
+    fn evaluate_slice(&mut self, slice: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        match slice {
+            Expression::Slice {
+                object,
+                bracket,
+                start,
+                end,
+            } => {
+                let object = self.evaluate_expression(object)?;
+                let start = self.evaluate_expression(start)?;
+                let end = self.evaluate_expression(end)?;
 
-        assert_eq!(interpret(&expr), Ok(Some(expected)));
+                let string = match object {
+                    Some(Literal::String(string)) => string,
+                    _ => {
+                        return RuntimeError::with_token(
+                            "Only strings can be sliced.".to_string(),
+                            bracket.clone(),
+                        )
+                    }
+                };
+
+                let (start, end) = match (start, end) {
+                    (Some(Literal::Number(start)), Some(Literal::Number(end))) => (start, end),
+                    _ => {
+                        return RuntimeError::with_token(
+                            "Slice bounds must be numbers.".to_string(),
+                            bracket.clone(),
+                        )
+                    }
+                };
+
+                let length = string.graphemes(true).count();
+                let (from, to) = (
+                    to_index(start, Some(bracket))?,
+                    to_index(end, Some(bracket))?,
+                );
+                if from > to || to > length {
+                    return RuntimeError::with_token(
+                        format!("String slice {}..{} is out of range.", start, end),
+                        bracket.clone(),
+                    );
+                }
+
+                Ok(Some(Literal::String(
+                    string.graphemes(true).skip(from).take(to - from).collect(),
+                )))
+            }
+            _ => RuntimeError::new("Unexpected expression, expected Slice".to_string()),
+        }
     }
 
-    #[rstest]
-    #[case::plus_number(Literal::Number(1.0), Literal::Number(2.0), Literal::Number(3.0))]
-    #[case::plus_string(Literal::String("hello".to_string()), Literal::String("world".to_string()), Literal::String("helloworld".to_string()))]
-    #[case::plus_string_number(Literal::String("hello".to_string()), Literal::Number(1.0), Literal::String("hello1".to_string()))]
-    #[case::plus_number_string(Literal::Number(1.0), Literal::String("hello".to_string()), Literal::String("1hello".to_string()))]
-    #[case::plus_string_empty(Literal::String("hello".to_string()), Literal::String("".to_string()), Literal::String("hello".to_string()))]
-    #[case::plus_string_boolean(Literal::String("hello".to_string()), Literal::Boolean(true), Literal::String("hellotrue".to_string()))]
-    fn test_binary_plus(#[case] left: Literal, #[case] right: Literal, #[case] expected: Literal) {
+    fn evaluate_grouping(&mut self, group: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        match group {
+            Expression::Grouping { expression, .. } => self.evaluate_expression(expression),
+            _ => RuntimeError::new(format!(
+                "Unexpected expression, expected Grouping {:?}",
+                group
+            )),
+        }
+    }
+
+    /**
+     * Evaluates a binary expression. Chains such as `1 + 2 + 3` nest through their left operand,
+     * so the chain is walked with a loop rather than recursion to handle long chains.
+     */
+    fn evaluate_binary(&mut self, binary: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        let mut operations = Vec::new();
+        let mut leftmost = binary;
+
+        while let Expression::Binary {
+            left,
+            operator,
+            right,
+        } = leftmost
+        {
+            // The outermost expression has already been counted by `evaluate_expression`
+            if !operations.is_empty() {
+                self.step()?;
+            }
+
+            operations.push((operator, right));
+            leftmost = left;
+        }
+
+        let mut value = self.evaluate_expression(leftmost)?;
+        for (operator, right) in operations.into_iter().rev() {
+            let right = self.evaluate_expression(right)?;
+            value = self.binary_operation(operator, value, right)?;
+        }
+
+        Ok(value)
+    }
+
+    /**
+     * Applies a binary operator to two values. An instance operand with a method for the operator
+     * handles it instead, and an instance added to a string is converted with `toString`.
+     */
+    fn binary_operation(
+        &mut self,
+        operator: &Token,
+        left: Option<Literal>,
+        right: Option<Literal>,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        let overload = match operator_method(operator, &left) {
+            Some(method) => Some((method, right.clone())),
+            // The order of the operands doesn't matter to `+` and `*`, so an instance on the right can handle them
+            None if matches!(operator.token_type, TokenType::Plus | TokenType::Star) => {
+                operator_method(operator, &right).map(|method| (method, left.clone()))
+            }
+            None => None,
+        };
+
+        match overload {
+            Some((method, other)) => self.call(&method, &[other], operator),
+            None if operator.token_type == TokenType::Plus => {
+                let (left, right) = self.concatenation_operands(left, right)?;
+                self.apply_binary(operator, left, right)
+            }
+            None => self.apply_binary(operator, left, right),
+        }
+    }
+
+    /**
+     * Converts an instance being added to a string into its text, so `toString` is used when concatenating
+     */
+    fn concatenation_operands(
+        &mut self,
+        left: Option<Literal>,
+        right: Option<Literal>,
+    ) -> Result<(Option<Literal>, Option<Literal>), RuntimeError> {
+        match (&left, &right) {
+            (Some(Literal::String(_)), Some(Literal::Instance(_))) => {
+                let right = self.stringify(&right)?;
+                Ok((left, Some(Literal::String(right))))
+            }
+            (Some(Literal::Instance(_)), Some(Literal::String(_))) => {
+                let left = self.stringify(&left)?;
+                Ok((Some(Literal::String(left)), right))
+            }
+            _ => Ok((left, right)),
+        }
+    }
+
+    fn apply_binary(
+        &self,
+        operator: &Token,
+        left: Option<Literal>,
+        right: Option<Literal>,
+    ) -> Result<Option<Literal>, RuntimeError> {
+        match operator.token_type {
+            TokenType::Minus => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    Ok(Some(Literal::Number(l - r)))
+                }
+                (l, r) => RuntimeError::invalid_operands(
+                    "Operands must be numbers.",
+                    operator.clone(),
+                    &l,
+                    &r,
+                ),
+            },
+
+            TokenType::Plus => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    Ok(Some(Literal::Number(l + r)))
+                }
+
+                (Some(Literal::String(l)), r) => Ok(Some(Literal::String(format!(
+                    "{}{}",
+                    l,
+                    match r {
+                        Some(r) => r.to_string(),
+                        None => "nil".to_string(),
+                    }
+                )))),
+
+                (l, Some(Literal::String(r))) => Ok(Some(Literal::String(format!(
+                    "{}{}",
+                    match l {
+                        Some(l) => l.to_string(),
+                        None => "nil".to_string(),
+                    },
+                    r
+                )))),
+
+                (l, r) => RuntimeError::invalid_operands(
+                    "Operands must be numbers or strings.",
+                    operator.clone(),
+                    &l,
+                    &r,
+                ),
+            },
+
+            TokenType::Slash => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    // `-0 == 0`, so dividing by either signed zero is caught here
+                    if r == 0.0 && !self.config.ieee_division {
+                        return RuntimeError::with_token(
+                            "Division by zero.".to_string(),
+                            operator.clone(),
+                        );
+                    }
+
+                    Ok(Some(Literal::Number(l / r)))
+                }
+                (l, r) => RuntimeError::invalid_operands(
+                    "Operands must be numbers.",
+                    operator.clone(),
+                    &l,
+                    &r,
+                ),
+            },
+
+            TokenType::Star => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    Ok(Some(Literal::Number(l * r)))
+                }
+                (Some(Literal::String(s)), Some(Literal::Number(n)))
+                | (Some(Literal::Number(n)), Some(Literal::String(s))) => {
+                    repeat_string(&s, n, operator)
+                }
+                (l, r) => RuntimeError::invalid_operands(
+                    "Operands must be numbers.",
+                    operator.clone(),
+                    &l,
+                    &r,
+                ),
+            },
+
+            TokenType::Greater => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    Ok(Some(Literal::Boolean(l > r)))
+                }
+                _ => Ok(Some(Literal::Boolean(false))),
+            },
+
+            TokenType::GreaterEqual => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    Ok(Some(Literal::Boolean(l >= r)))
+                }
+                _ => Ok(Some(Literal::Boolean(false))),
+            },
+
+            TokenType::Less => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    Ok(Some(Literal::Boolean(l < r)))
+                }
+                _ => Ok(Some(Literal::Boolean(false))),
+            },
+
+            TokenType::LessEqual => match (left, right) {
+                (Some(Literal::Number(l)), Some(Literal::Number(r))) => {
+                    Ok(Some(Literal::Boolean(l <= r)))
+                }
+                _ => Ok(Some(Literal::Boolean(false))),
+            },
+
+            TokenType::BangEqual | TokenType::EqualEqual => {
+                self.check_comparable(&left, &right, &operator.lexeme, Some(operator))?;
+
+                let equal = evaluate_equal(&left, &right);
+                Ok(Some(Literal::Boolean(
+                    equal == (operator.token_type == TokenType::EqualEqual),
+                )))
+            }
+
+            // Both sides have been evaluated for their effects, and the comma gives the right one
+            TokenType::Comma => Ok(right),
+
+            _ => RuntimeError::with_token("Unexpected operator".to_string(), operator.clone()),
+        }
+    }
+
+    /**
+     * With strict equality, errors if two values of different types are compared by `==`, `!=` or a switch's `case`.
+     * Anything may still be compared with nil.
+     */
+    fn check_comparable(
+        &self,
+        left: &Option<Literal>,
+        right: &Option<Literal>,
+        operator: &str,
+        token: Option<&Token>,
+    ) -> Result<(), RuntimeError> {
+        if self.config.strict_equality
+            && left.is_some()
+            && right.is_some()
+            && type_name(left) != type_name(right)
+        {
+            return Err(RuntimeError {
+                message: format!(
+                    "Can't compare {} with {} using '{}'.",
+                    type_name(left),
+                    type_name(right),
+                    operator
+                ),
+                token: token.cloned(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn evaluate_unary(&mut self, unary: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        match unary {
+            Expression::Unary { operator, right } => {
+                let right = self.evaluate_expression(right)?;
+
+                match operator.token_type {
+                    TokenType::Minus => match right {
+                        Some(Literal::Number(n)) => Ok(Some(Literal::Number(-n))),
+                        _ => RuntimeError::operands_must_be_numbers(operator.clone()),
+                    },
+
+                    TokenType::Bang => Ok(Some(Literal::Boolean(!self.is_truthy(&right)))),
+
+                    _ => RuntimeError::with_token(
+                        "Unexpected operator".to_string(),
+                        operator.clone(),
+                    ),
+                }
+            }
+            _ => RuntimeError::new("Unexpected expression, expected Unary".to_string()),
+        }
+    }
+
+    /**
+     * Evaluates the condition of an `if`, `while` or `?:`, reporting a non-boolean at the token
+     * when conditions are strict
+     */
+    fn evaluate_condition(
+        &mut self,
+        condition: &Expression,
+        token: Option<&Token>,
+    ) -> Result<bool, RuntimeError> {
+        let value = self.evaluate_expression(condition)?;
+
+        match value {
+            Some(Literal::Boolean(boolean)) => Ok(boolean),
+            _ if self.config.strict_conditions => Err(RuntimeError {
+                message: format!("Condition must be a boolean, not {}.", type_name(&value)),
+                token: token.cloned(),
+            }),
+            _ => Ok(self.is_truthy(&value)),
+        }
+    }
+
+    /**
+     * Checks truthiness, taking the configured truthiness rules into account
+     */
+    pub fn is_truthy(&self, value: &Option<Literal>) -> bool {
+        if self.config.loose_truthiness {
+            match value {
+                Some(Literal::Number(n)) if *n == 0.0 => return false,
+                Some(Literal::String(s)) if s.is_empty() => return false,
+                _ => {}
+            }
+        }
+
+        is_truthy(value)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), RuntimeError> {
+        writeln!(self.output, "{}", line).map_err(output_error)
+    }
+}
+
+fn output_error(err: io::Error) -> RuntimeError {
+    RuntimeError {
+        message: format!("Failed to write output: {}", err),
+        token: None,
+    }
+}
+
+/**
+ * Creates the global environment, holding the built-in natives
+ */
+fn globals() -> Environment {
+    let mut environment = Environment::new();
+
+    for native in natives::globals() {
+        environment.define(native.name, Some(Literal::Callable(Rc::new(native))));
+    }
+
+    environment
+}
+
+/**
+ * Converts a value to the text shown when it is printed
+ */
+pub fn stringify(value: &Option<Literal>) -> String {
+    match value {
+        Some(literal) => literal.to_string(),
+        None => "nil".to_string(),
+    }
+}
+
+/**
+ * The name of the value's type, as reported to Lox programs
+ */
+pub fn type_name(value: &Option<Literal>) -> &'static str {
+    match value {
+        None => "nil",
+        Some(Literal::Identifier(_)) => "identifier",
+        Some(Literal::String(_)) => "string",
+        Some(Literal::Number(_)) => "number",
+        Some(Literal::Boolean(_)) => "boolean",
+        Some(Literal::Callable(_)) => "function",
+        Some(Literal::Class(_)) => "class",
+        Some(Literal::Instance(_)) => "instance",
+        Some(Literal::List(_)) => "list",
+        Some(Literal::Map(_)) => "map",
+    }
+}
+
+fn is_truthy(literal: &Option<Literal>) -> bool {
+    match literal {
+        Some(Literal::Boolean(b)) => *b,
+        None => false,
+        _ => true,
+    }
+}
+
+/**
+ * The largest number which can be used as an integer, above which not every integer can be represented by an f64
+ */
+const MAX_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/**
+ * Converts a number to a non-negative integer, such as an index or a count.
+ * Numbers which are fractional, negative, not finite or too large are an error, at the token if given,
+ * rather than being truncated or saturated.
+ */
+pub fn to_index(number: f64, token: Option<&Token>) -> Result<usize, RuntimeError> {
+    if !number.is_finite()
+        || number.fract() != 0.0
+        || number < 0.0
+        || number > MAX_INTEGER.min(usize::MAX as f64)
+    {
+        return Err(RuntimeError {
+            message: "Number is not a valid integer index.".to_string(),
+            token: token.cloned(),
+        });
+    }
+
+    Ok(number as usize)
+}
+
+/**
+ * Converts a number to an integer between -2^53 and 2^53, where every integer can be represented exactly.
+ * Numbers which are fractional, not finite or outside that range are an error, at the token if given.
+ */
+pub fn to_integer(number: f64, token: Option<&Token>) -> Result<i64, RuntimeError> {
+    if !number.is_finite() || number.fract() != 0.0 || number.abs() > MAX_INTEGER {
+        return Err(RuntimeError {
+            message: "Number is not a valid integer.".to_string(),
+            token: token.cloned(),
+        });
+    }
+
+    Ok(number as i64)
+}
+
+/**
+ * The longest string, in bytes, that repeating a string may produce, so a mistaken count can't exhaust memory
+ */
+const MAX_REPEATED_LENGTH: usize = 100 * 1024 * 1024;
+
+/**
+ * Repeats the string `count` times, which must be a non-negative integer
+ */
+fn repeat_string(
+    string: &str,
+    count: f64,
+    operator: &Token,
+) -> Result<Option<Literal>, RuntimeError> {
+    let count = to_index(count, Some(operator)).map_err(|err| RuntimeError {
+        message: format!(
+            "Can only repeat a string a non-negative whole number of times, not {}.",
+            count
+        ),
+        ..err
+    })?;
+
+    match string.len().checked_mul(count) {
+        Some(length) if length <= MAX_REPEATED_LENGTH => {
+            Ok(Some(Literal::String(string.repeat(count))))
+        }
+        _ => RuntimeError::with_token(
+            format!(
+                "Repeating a string can't make it longer than {} bytes.",
+                MAX_REPEATED_LENGTH
+            ),
+            operator.clone(),
+        ),
+    }
+}
+
+/**
+ * Finds the method an instance operand defines to overload the operator, such as `plus` for '+',
+ * bound to the instance
+ */
+fn operator_method(operator: &Token, operand: &Option<Literal>) -> Option<LoxFunction> {
+    let name = match operator.token_type {
+        TokenType::Plus => "plus",
+        TokenType::Minus => "minus",
+        TokenType::Star => "times",
+        TokenType::Slash => "divide",
+        _ => return None,
+    };
+
+    match operand {
+        Some(Literal::Instance(instance)) => {
+            let class = instance.borrow().class.clone();
+            let method = class.find_method(name)?;
+            Some(method.bind(instance.clone()))
+        }
+        _ => None,
+    }
+}
+
+/**
+ * Checks whether a `break` or `continue` with the target label, if any, targets a loop with the given label.
+ * One without a label targets the innermost loop.
+ */
+fn targets_loop(target: &Option<String>, label: &Option<Token>) -> bool {
+    match (target, label) {
+        (None, _) => true,
+        (Some(target), Some(label)) => *target == label.lexeme,
+        (Some(_), None) => false,
+    }
+}
+
+/**
+ * The values a `for-in` loop visits: the elements of a list, or the graphemes of a string.
+ * Other values can't be iterated over.
+ */
+fn elements(value: &Option<Literal>) -> Option<Vec<Option<Literal>>> {
+    match value {
+        Some(Literal::List(elements)) => Some(elements.borrow().clone()),
+        Some(Literal::String(string)) => Some(
+            string
+                .graphemes(true)
+                .map(|grapheme| Some(Literal::String(grapheme.to_string())))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/**
+ * Errors unless a function needing `arity` arguments, and accepting up to `max_arity` if limited,
+ * was given a number of arguments it accepts
+ */
+fn check_arity(
+    arity: usize,
+    max_arity: Option<usize>,
+    arguments: &[Option<Literal>],
+    paren: &Token,
+) -> Result<(), RuntimeError> {
+    let too_many = matches!(max_arity, Some(max_arity) if arguments.len() > max_arity);
+
+    if arguments.len() < arity || too_many {
+        let expected = match max_arity {
+            Some(max_arity) if max_arity == arity => arity.to_string(),
+            Some(max_arity) => format!("{} to {}", arity, max_arity),
+            None => format!("at least {}", arity),
+        };
+
+        return Err(RuntimeError {
+            message: format!(
+                "Expected {} arguments but got {}.",
+                expected,
+                arguments.len()
+            ),
+            token: Some(paren.clone()),
+        });
+    }
+
+    Ok(())
+}
+
+fn evaluate_equal(left: &Option<Literal>, right: &Option<Literal>) -> bool {
+    match (left, right) {
+        (None, None) => true,
+        (Some(_), None) => false,
+        (None, Some(_)) => false,
+
+        (Some(Literal::Number(l)), Some(Literal::Number(r))) => l == r,
+        (Some(Literal::Number(_)), Some(_)) => false,
+
+        (Some(Literal::String(l)), Some(Literal::String(r))) => l == r,
+        (Some(Literal::String(_)), Some(_)) => false,
+
+        (Some(Literal::Boolean(l)), Some(Literal::Boolean(r))) => l == r,
+        (Some(Literal::Boolean(_)), Some(_)) => false,
+
+        (Some(Literal::Identifier(l)), Some(Literal::Identifier(r))) => l == r,
+        (Some(Literal::Identifier(_)), Some(_)) => false,
+
+        (Some(Literal::Callable(l)), Some(Literal::Callable(r))) => Rc::ptr_eq(l, r),
+        (Some(Literal::Callable(_)), Some(_)) => false,
+
+        (Some(Literal::Class(l)), Some(Literal::Class(r))) => Rc::ptr_eq(l, r),
+        (Some(Literal::Class(_)), Some(_)) => false,
+
+        // Instances, lists and maps are shared, so they are only equal to themselves
+        (Some(Literal::Instance(l)), Some(Literal::Instance(r))) => Rc::ptr_eq(l, r),
+        (Some(Literal::Instance(_)), Some(_)) => false,
+
+        (Some(Literal::List(l)), Some(Literal::List(r))) => Rc::ptr_eq(l, r),
+        (Some(Literal::List(_)), Some(_)) => false,
+
+        (Some(Literal::Map(l)), Some(Literal::Map(r))) => Rc::ptr_eq(l, r),
+        (Some(Literal::Map(_)), Some(_)) => false,
+    }
+}
+
+/**
+ * Compares lists and maps by their contents rather than by identity, as the `equals` native does.
+ * Everything else, including the elements of lists and maps which aren't themselves lists or maps,
+ * is compared the same way as `==`.
+ */
+pub fn structurally_equal(left: &Option<Literal>, right: &Option<Literal>) -> bool {
+    deep_equal(left, right, &mut Vec::new())
+}
+
+/**
+ * `comparing` holds the pairs of lists or maps being compared around these values.
+ * Meeting the same pair again means the collections contain themselves in the same place,
+ * which can't make them differ, so it is treated as equal rather than compared forever.
+ */
+fn deep_equal(
+    left: &Option<Literal>,
+    right: &Option<Literal>,
+    comparing: &mut Vec<(*const (), *const ())>,
+) -> bool {
+    match (left, right) {
+        (Some(Literal::List(l)), Some(Literal::List(r))) => {
+            let pair = (Rc::as_ptr(l).cast(), Rc::as_ptr(r).cast());
+            if Rc::ptr_eq(l, r) || comparing.contains(&pair) {
+                return true;
+            }
+
+            comparing.push(pair);
+            let (l, r) = (l.borrow(), r.borrow());
+            let equal = l.len() == r.len()
+                && l.iter()
+                    .zip(r.iter())
+                    .all(|(l, r)| deep_equal(l, r, comparing));
+            comparing.pop();
+
+            equal
+        }
+        (Some(Literal::Map(l)), Some(Literal::Map(r))) => {
+            let pair = (Rc::as_ptr(l).cast(), Rc::as_ptr(r).cast());
+            if Rc::ptr_eq(l, r) || comparing.contains(&pair) {
+                return true;
+            }
+
+            comparing.push(pair);
+            let (l, r) = (l.borrow(), r.borrow());
+            let equal = l.len() == r.len()
+                && l.iter()
+                    .zip(r.iter())
+                    .all(|((lk, lv), (rk, rv))| lk == rk && deep_equal(lv, rv, comparing));
+            comparing.pop();
+
+            equal
+        }
+        _ => evaluate_equal(left, right),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    fn interpret(expr: &Expression) -> Result<Option<Literal>, RuntimeError> {
+        let mut output = io::sink();
+        Interpreter::new(&mut output).evaluate_expression(expr)
+    }
+
+    fn token(token_type: TokenType, lexeme: &str, line_number: usize) -> Token {
+        Token::new(
+            token_type,
+            lexeme.to_string(),
+            None,
+            line_number,
+            Span::default(),
+        )
+    }
+
+    fn identifier(name: &str) -> Token {
+        Token {
+            token_type: TokenType::Identifier,
+            lexeme: name.to_string(),
+            literal: Some(Literal::Identifier(name.to_string())),
+            line_number: 0,
+            span: Span::default(),
+        }
+    }
+
+    #[test]
+    fn test_literal_equality() {
+        assert_eq!(Literal::Number(1.0), Literal::Number(1.0));
+        assert_ne!(Literal::Number(1.0), Literal::Number(2.0));
+        assert_eq!(
+            Literal::String("hello".to_string()),
+            Literal::String("hello".to_string())
+        );
+        assert_ne!(
+            Literal::String("hello".to_string()),
+            Literal::String("world".to_string())
+        );
+        assert_eq!(Literal::Boolean(true), Literal::Boolean(true));
+        assert_ne!(Literal::Boolean(true), Literal::Boolean(false));
+    }
+
+    #[rstest]
+    #[case::boolean_true(Some(Literal::Boolean(true)), true)]
+    #[case::boolean_false(Some(Literal::Boolean(false)), false)]
+    #[case::number(Some(Literal::Number(1.0)), true)]
+    #[case::number_zero(Some(Literal::Number(0.0)), true)]
+    #[case::string(Some(Literal::String("hello".to_string())), true)]
+    #[case::string_false(Some(Literal::String("false".to_string())), true)]
+    #[case::string_true(Some(Literal::String("true".to_string())), true)]
+    #[case::string_empty(Some(Literal::String("".to_string())), true)]
+    #[case::identifier(Some(Literal::Identifier("foo".to_string())), true)]
+    #[case::nil(None, false)]
+    fn test_literal_truthiness(#[case] literal: Option<Literal>, #[case] expected: bool) {
+        assert_eq!(is_truthy(&literal), expected);
+    }
+
+    #[rstest]
+    #[case::boolean_true(Some(Literal::Boolean(true)), true)]
+    #[case::boolean_false(Some(Literal::Boolean(false)), false)]
+    #[case::number(Some(Literal::Number(1.0)), true)]
+    #[case::number_zero(Some(Literal::Number(0.0)), false)]
+    #[case::number_negative_zero(Some(Literal::Number(-0.0)), false)]
+    #[case::string(Some(Literal::String("hello".to_string())), true)]
+    #[case::string_empty(Some(Literal::String("".to_string())), false)]
+    #[case::nil(None, false)]
+    fn test_loose_literal_truthiness(#[case] literal: Option<Literal>, #[case] expected: bool) {
+        let mut output = io::sink();
+        let interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                loose_truthiness: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(interpreter.is_truthy(&literal), expected);
+    }
+
+    #[test]
+    fn test_loose_truthiness_in_conditions() {
+        let condition = Expression::Ternary {
+            condition: Box::new(Expression::Literal(Some(Literal::Number(0.0)))),
+            question: token(TokenType::QuestionMark, "?", 0),
+            then_branch: Box::new(Expression::Literal(Some(Literal::Number(1.0)))),
+            else_branch: Box::new(Expression::Literal(Some(Literal::Number(2.0)))),
+        };
+
+        let mut output = io::sink();
+        let mut loose = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                loose_truthiness: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(interpret(&condition), Ok(Some(Literal::Number(1.0))));
+        assert_eq!(
+            loose.evaluate_expression(&condition),
+            Ok(Some(Literal::Number(2.0)))
+        );
+    }
+
+    #[rstest]
+    #[case::string("print \"x\" ? 1 : 2;", "1\n")]
+    #[case::empty_string("print \"\" ? 1 : 2;", "1\n")]
+    #[case::zero("print 0 ? 1 : 2;", "1\n")]
+    #[case::nil("print nil ? 1 : 2;", "2\n")]
+    #[case::list("print [] ? 1 : 2;", "1\n")]
+    fn test_ternary_condition_truthiness(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::else_not_evaluated(
+        "var a = 0; var b = 0; true ? (a = 1) : (b = 1); print a; print b;",
+        "1\n0\n"
+    )]
+    #[case::then_not_evaluated(
+        "var a = 0; var b = 0; false ? (a = 1) : (b = 1); print a; print b;",
+        "0\n1\n"
+    )]
+    #[case::untaken_call(
+        "var calls = 0; fun f() { calls += 1; return calls; } print true ? 1 : f(); print calls;",
+        "1\n0\n"
+    )]
+    #[case::untaken_postfix("var n = 0; print false ? n++ : n; print n;", "0\n0\n")]
+    #[case::untaken_error("print true ? 1 : -\"a\";", "1\n")]
+    fn test_ternary_evaluates_only_taken_branch(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::then_branch("print if (true) 1 else 2;", "print true ? 1 : 2;")]
+    #[case::else_branch("print if (1 > 2) \"a\" else \"b\";", "print 1 > 2 ? \"a\" : \"b\";")]
+    #[case::chained(
+        "var n = 5; print if (n < 0) \"neg\" else if (n == 0) \"zero\" else \"pos\";",
+        "var n = 5; print n < 0 ? \"neg\" : n == 0 ? \"zero\" : \"pos\";"
+    )]
+    #[case::untaken_not_evaluated(
+        "var a = 0; var b = 0; var c = if (true) (a = 1) else (b = 1); print a; print b;",
+        "var a = 0; var b = 0; var c = true ? (a = 1) : (b = 1); print a; print b;"
+    )]
+    #[case::in_arguments(
+        "fun f(x) { return x * 2; } print f(if (false) 1 else 2);",
+        "fun f(x) { return x * 2; } print f(false ? 1 : 2);"
+    )]
+    #[case::else_extends_right("print if (false) 1 else 2 + 3;", "print false ? 1 : 2 + 3;")]
+    fn test_if_expression_matches_ternary(#[case] source: &str, #[case] ternary: &str) {
+        assert_eq!(
+            crate::frontend::run_to_string(source).unwrap(),
+            crate::frontend::run_to_string(ternary).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case::ternary(
+        "print \"x\" ? 1 : 2;",
+        "Condition must be a boolean, not string. [line 0]"
+    )]
+    #[case::ternary_nil("print nil ? 1 : 2;", "Condition must be a boolean, not nil. [line 0]")]
+    #[case::if_statement("if (1) print 1;", "Condition must be a boolean, not number. [line 0]")]
+    #[case::while_literal("while (1) {}", "Condition must be a boolean, not number. [line 0]")]
+    #[case::do_while_literal(
+        "do {} while (\"a\");",
+        "Condition must be a boolean, not string. [line 0]"
+    )]
+    #[case::while_loop(
+        "var a = 1; while (a) a = nil;",
+        "Condition must be a boolean, not number. [line 0]"
+    )]
+    fn test_strict_conditions_reject_non_booleans(#[case] source: &str, #[case] expected: &str) {
+        let mut output = io::sink();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                strict_conditions: true,
+                ..Default::default()
+            },
+        );
+
+        let error = crate::frontend::run_in_interpreter(source, &mut interpreter).unwrap_err();
+
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[test]
+    fn test_strict_conditions_allow_booleans() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                strict_conditions: true,
+                ..Default::default()
+            },
+        );
+
+        let source = "print 1 < 2 ? \"yes\" : \"no\"; if (false) print 1; else print 2;";
+        crate::frontend::run_in_interpreter(source, &mut interpreter).unwrap();
+        drop(interpreter);
+
+        assert_eq!(String::from_utf8(output).unwrap(), "yes\n2\n");
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        let expr = Expression::Unary {
+            operator: Token {
+                token_type: TokenType::Minus,
+                lexeme: "-".to_string(),
+                literal: None,
+                line_number: 0,
+                span: Span::default(),
+            },
+            right: Box::new(Expression::Literal(Some(Literal::Number(1.0)))),
+        };
+
+        let result = interpret(&expr);
+        assert_eq!(result, Ok(Some(Literal::Number(-1.0))));
+    }
+
+    #[rstest]
+    #[case::boolean_true(Literal::Boolean(true), Literal::Boolean(false))]
+    #[case::boolean_false(Literal::Boolean(false), Literal::Boolean(true))]
+    #[case::number(Literal::Number(1.0), Literal::Boolean(false))]
+    #[case::string(Literal::String("hello".to_string()), Literal::Boolean(false))]
+    #[case::string_false(Literal::String("false".to_string()), Literal::Boolean(false))]
+    #[case::string_true(Literal::String("true".to_string()), Literal::Boolean(false))]
+    #[case::string_empty(Literal::String("".to_string()), Literal::Boolean(false))]
+    #[case::identifier(Literal::Identifier("foo".to_string()), Literal::Boolean(false))]
+    fn test_unary_bang(#[case] input: Literal, #[case] expected: Literal) {
+        let expr = Expression::Unary {
+            operator: Token {
+                token_type: TokenType::Bang,
+                lexeme: "!".to_string(),
+                literal: None,
+                line_number: 0,
+                span: Span::default(),
+            },
+            right: Box::new(Expression::Literal(Some(input))),
+        };
+
+        assert_eq!(interpret(&expr), Ok(Some(expected)));
+    }
+
+    #[rstest]
+    #[case::plus_number(Literal::Number(1.0), Literal::Number(2.0), Literal::Number(3.0))]
+    #[case::plus_string(Literal::String("hello".to_string()), Literal::String("world".to_string()), Literal::String("helloworld".to_string()))]
+    #[case::plus_string_number(Literal::String("hello".to_string()), Literal::Number(1.0), Literal::String("hello1".to_string()))]
+    #[case::plus_number_string(Literal::Number(1.0), Literal::String("hello".to_string()), Literal::String("1hello".to_string()))]
+    #[case::plus_string_empty(Literal::String("hello".to_string()), Literal::String("".to_string()), Literal::String("hello".to_string()))]
+    #[case::plus_string_boolean(Literal::String("hello".to_string()), Literal::Boolean(true), Literal::String("hellotrue".to_string()))]
+    fn test_binary_plus(#[case] left: Literal, #[case] right: Literal, #[case] expected: Literal) {
+        let expr = Expression::Binary {
+            left: Box::new(Expression::Literal(Some(left))),
+            operator: Token {
+                token_type: TokenType::Plus,
+                lexeme: "+".to_string(),
+                literal: None,
+                line_number: 0,
+                span: Span::default(),
+            },
+            right: Box::new(Expression::Literal(Some(right))),
+        };
+
+        assert_eq!(interpret(&expr), Ok(Some(expected)));
+    }
+
+    #[rstest]
+    #[case::minus(
+        TokenType::Minus,
+        Literal::Number(3.0),
+        Literal::Number(2.0),
+        Literal::Number(1.0)
+    )]
+    #[case::divide(
+        TokenType::Slash,
+        Literal::Number(6.0),
+        Literal::Number(3.0),
+        Literal::Number(2.0)
+    )]
+    #[case::multiply(
+        TokenType::Star,
+        Literal::Number(2.0),
+        Literal::Number(1.0),
+        Literal::Number(2.0)
+    )]
+    fn test_binary_arithmetic(
+        #[case] operator: TokenType,
+        #[case] left: Literal,
+        #[case] right: Literal,
+        #[case] expected: Literal,
+    ) {
+        let expr = Expression::Binary {
+            left: Box::new(Expression::Literal(Some(left))),
+            operator: Token {
+                lexeme: match operator {
+                    TokenType::Minus => "-".to_string(),
+                    TokenType::Slash => "/".to_string(),
+                    TokenType::Star => "*".to_string(),
+                    _ => panic!("Unexpected operator {:?}", operator),
+                },
+                token_type: operator,
+                literal: None,
+                line_number: 0,
+                span: Span::default(),
+            },
+            right: Box::new(Expression::Literal(Some(right))),
+        };
+
+        assert_eq!(interpret(&expr), Ok(Some(expected)));
+    }
+
+    #[rstest]
+    #[case::greater(
+        TokenType::Greater,
+        Literal::Number(2.0),
+        Literal::Number(1.0),
+        Literal::Boolean(true)
+    )]
+    #[case::greater_equal(
+        TokenType::GreaterEqual,
+        Literal::Number(2.0),
+        Literal::Number(2.0),
+        Literal::Boolean(true)
+    )]
+    #[case::less(
+        TokenType::Less,
+        Literal::Number(1.0),
+        Literal::Number(2.0),
+        Literal::Boolean(true)
+    )]
+    #[case::less_equal(
+        TokenType::LessEqual,
+        Literal::Number(2.0),
+        Literal::Number(2.0),
+        Literal::Boolean(true)
+    )]
+    fn test_binary_comparison(
+        #[case] operator: TokenType,
+        #[case] left: Literal,
+        #[case] right: Literal,
+        #[case] expected: Literal,
+    ) {
+        let expr = Expression::Binary {
+            left: Box::new(Expression::Literal(Some(left))),
+            operator: Token {
+                lexeme: match operator {
+                    TokenType::Greater => ">".to_string(),
+                    TokenType::GreaterEqual => ">=".to_string(),
+                    TokenType::Less => "<".to_string(),
+                    TokenType::LessEqual => "<=".to_string(),
+                    _ => panic!("Unexpected operator {:?}", operator),
+                },
+                token_type: operator,
+                literal: None,
+                line_number: 0,
+                span: Span::default(),
+            },
+            right: Box::new(Expression::Literal(Some(right))),
+        };
+
+        assert_eq!(interpret(&expr), Ok(Some(expected)));
+    }
+
+    #[rstest]
+    #[case::greater_string(
+        TokenType::Greater,
+        Literal::String("hello".to_string()),
+        Literal::String("world".to_string())
+    )]
+    #[case::greater_boolean(TokenType::Greater, Literal::Boolean(true), Literal::Boolean(false))]
+    #[case::greater_equal_string(
+        TokenType::LessEqual,
+        Literal::String("hello".to_string()),
+        Literal::String("world".to_string())
+    )]
+    #[case::greater_equal_boolean(
+        TokenType::LessEqual,
+        Literal::Boolean(true),
+        Literal::Boolean(false)
+    )]
+    #[case::less_string(
+        TokenType::Less,
+        Literal::String("hello".to_string()),
+        Literal::String("world".to_string())
+    )]
+    #[case::less_boolean(TokenType::Less, Literal::Boolean(true), Literal::Boolean(false))]
+    #[case::less_equal_string(
+        TokenType::LessEqual,
+        Literal::String("hello".to_string()),
+        Literal::String("world".to_string())
+    )]
+    #[case::less_equal_boolean(
+        TokenType::LessEqual,
+        Literal::Boolean(true),
+        Literal::Boolean(false)
+    )]
+    fn test_binary_comparison_non_numbers(
+        #[case] operator: TokenType,
+        #[case] left: Literal,
+        #[case] right: Literal,
+    ) {
+        let expr = Expression::Binary {
+            left: Box::new(Expression::Literal(Some(left))),
+            operator: Token {
+                lexeme: match operator {
+                    TokenType::Greater => ">".to_string(),
+                    TokenType::GreaterEqual => ">=".to_string(),
+                    TokenType::Less => "<".to_string(),
+                    TokenType::LessEqual => "<=".to_string(),
+                    _ => panic!("Unexpected operator {:?}", operator),
+                },
+                token_type: operator,
+                literal: None,
+                line_number: 0,
+                span: Span::default(),
+            },
+            right: Box::new(Expression::Literal(Some(right))),
+        };
+
+        assert_eq!(interpret(&expr), Ok(Some(Literal::Boolean(false))));
+    }
+
+    #[rstest]
+    #[case::equal_number(TokenType::EqualEqual, Literal::Number(1.0), Literal::Number(1.0))]
+    #[case::bang_equal_number(TokenType::BangEqual, Literal::Number(1.0), Literal::Number(2.0))]
+    #[case::equal_string(
+        TokenType::EqualEqual,
+        Literal::String("hello".to_string()),
+        Literal::String("hello".to_string()),
+    )]
+    #[case::bang_equal_string(
+        TokenType::BangEqual,
+        Literal::String("hello".to_string()),
+        Literal::String("hello world".to_string()),
+    )]
+    #[case::equal_boolean(TokenType::EqualEqual, Literal::Boolean(true), Literal::Boolean(true))]
+    #[case::bang_equal_boolean(
+        TokenType::BangEqual,
+        Literal::Boolean(true),
+        Literal::Boolean(false)
+    )]
+    fn test_binary_equality(
+        #[case] operator: TokenType,
+        #[case] left: Literal,
+        #[case] right: Literal,
+    ) {
+        let expr = Expression::Binary {
+            left: Box::new(Expression::Literal(Some(left))),
+            operator: Token {
+                lexeme: match operator {
+                    TokenType::EqualEqual => "==".to_string(),
+                    TokenType::BangEqual => "!=".to_string(),
+                    _ => panic!("Unexpected operator {:?}", operator),
+                },
+                token_type: operator,
+                literal: None,
+                line_number: 0,
+                span: Span::default(),
+            },
+            right: Box::new(Expression::Literal(Some(right))),
+        };
+
+        assert_eq!(interpret(&expr), Ok(Some(Literal::Boolean(true))));
+    }
+
+    #[test]
+    fn test_divide_by_zero() {
+        let operator = Token {
+            token_type: TokenType::Slash,
+            lexeme: "/".to_string(),
+            literal: None,
+            line_number: 0,
+            span: Span::default(),
+        };
+
         let expr = Expression::Binary {
-            left: Box::new(Expression::Literal(Some(left))),
-            operator: Token {
-                token_type: TokenType::Plus,
-                lexeme: "+".to_string(),
-                literal: None,
-                line_number: 0,
+            left: Box::new(Expression::Literal(Some(Literal::Number(1.0)))),
+            operator: operator.clone(),
+            right: Box::new(Expression::Literal(Some(Literal::Number(0.0)))),
+        };
+
+        assert_eq!(
+            interpret(&expr),
+            RuntimeError::with_token("Division by zero.".to_string(), operator)
+        );
+    }
+
+    #[test]
+    fn test_print_statement_writes_to_output() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+
+        let result = interpreter.interpret(&[
+            Statement::Print(Expression::Literal(Some(Literal::Number(1.0)))),
+            Statement::Expression(Expression::Literal(Some(Literal::Number(2.0)))),
+            Statement::Print(Expression::Literal(None)),
+        ]);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(String::from_utf8(output).unwrap(), "1\nnil\n");
+    }
+
+    #[test]
+    fn test_variable_declaration_and_assignment() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+
+        let result = interpreter.interpret(&[
+            Statement::Var {
+                name: identifier("a"),
+                initializer: Some(Expression::Literal(Some(Literal::Number(1.0)))),
             },
-            right: Box::new(Expression::Literal(Some(right))),
+            Statement::Print(Expression::Assign {
+                name: identifier("a"),
+                value: Box::new(Expression::Literal(Some(Literal::Number(2.0)))),
+            }),
+            Statement::Print(Expression::Variable(identifier("a"))),
+        ]);
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(String::from_utf8(output).unwrap(), "2\n2\n");
+    }
+
+    #[rstest]
+    #[case::chained("var a; var b; a = b = 1; print a; print b;", "1\n1\n")]
+    #[case::printed("var x; print (x = 5);", "5\n")]
+    #[case::in_larger_expression("var x; print (x = 2) * 3 + x;", "8\n")]
+    #[case::initializer("var a; var c = a = 7; print c; print a;", "7\n7\n")]
+    #[case::compound("var a = 1; print a += 2;", "3\n")]
+    fn test_assignment_evaluates_to_value(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::read("const a = 1; print a + 1;", "2\n")]
+    #[case::in_function("const a = 2; fun f() { return a * 3; } print f();", "6\n")]
+    #[case::shadowed_in_block("const a = 1; { var a = 2; a = 3; print a; } print a;", "3\n1\n")]
+    fn test_constants(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::assign("const a = 1; a = 2;")]
+    #[case::compound("const a = 1; a += 2;")]
+    #[case::increment("const a = 1; a++;")]
+    #[case::in_function("const a = 1; fun f() { a = 2; } f();")]
+    fn test_assign_to_constant(#[case] source: &str) {
+        match crate::frontend::run_to_string(source) {
+            Err(err @ crate::frontend::LoxScriptError::Runtime(_)) => {
+                assert_eq!(err.to_string(), "Cannot assign to constant 'a'. [line 0]")
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    #[case::var("const c = 1; var c = 2; c = 3;")]
+    #[case::constant("const c = 1; const c = 2;")]
+    #[case::function("const c = 1; fun c() {}")]
+    #[case::class("const c = 1; class c {}")]
+    fn test_redeclare_constant(#[case] source: &str) {
+        match crate::frontend::run_to_string(source) {
+            Err(err @ crate::frontend::LoxScriptError::Runtime(_)) => {
+                assert_eq!(err.to_string(), "Cannot redeclare constant 'c'. [line 0]")
+            }
+            other => panic!("Expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_redeclare_constant_in_later_input() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+        crate::frontend::run_in_interpreter("const c = 1;", &mut interpreter).unwrap();
+
+        let error =
+            crate::frontend::run_in_interpreter("var c = 2;", &mut interpreter).unwrap_err();
+        crate::frontend::run_in_interpreter("print c;", &mut interpreter).unwrap();
+
+        assert_eq!(error.to_string(), "Cannot redeclare constant 'c'. [line 0]");
+        drop(interpreter);
+        assert_eq!(String::from_utf8(output).unwrap(), "1\n");
+    }
+
+    #[test]
+    fn test_undefined_variable() {
+        let expr = Expression::Variable(identifier("missing"));
+
+        assert_eq!(
+            interpret(&expr),
+            RuntimeError::with_token(
+                "Undefined variable 'missing'.".to_string(),
+                identifier("missing")
+            )
+        );
+    }
+
+    #[test]
+    fn test_call_non_callable() {
+        let paren = Token {
+            token_type: TokenType::RightParen,
+            lexeme: ")".to_string(),
+            literal: None,
+            line_number: 0,
+            span: Span::default(),
+        };
+
+        let expr = Expression::Call {
+            callee: Box::new(Expression::Literal(Some(Literal::Number(1.0)))),
+            paren: paren.clone(),
+            arguments: vec![],
+            keyword_arguments: vec![],
+        };
+
+        assert_eq!(
+            interpret(&expr),
+            RuntimeError::with_token("Can only call functions and classes.".to_string(), paren)
+        );
+    }
+
+    #[test]
+    fn test_grouping() {
+        let expr = Expression::Grouping {
+            paren: token(TokenType::LeftParen, "(", 0),
+            expression: Box::new(Expression::Literal(Some(Literal::Number(1.0)))),
+        };
+
+        assert_eq!(interpret(&expr), Ok(Some(Literal::Number(1.0))));
+    }
+
+    #[test]
+    fn test_runtime_error_in_grouping_has_location() {
+        // len(1) inside a grouping, with the call's closing paren on line 3
+        let expr = Expression::Grouping {
+            paren: token(TokenType::LeftParen, "(", 2),
+            expression: Box::new(Expression::Call {
+                callee: Box::new(Expression::Variable(identifier("len"))),
+                paren: token(TokenType::RightParen, ")", 3),
+                arguments: vec![Expression::Literal(Some(Literal::Number(1.0)))],
+                keyword_arguments: vec![],
+            }),
         };
 
-        assert_eq!(interpret(&expr), Ok(Some(expected)));
+        let err = interpret(&expr).unwrap_err();
+
+        assert_eq!(err.message, "'len' expects a string or list argument.");
+        assert_eq!(err.token.map(|t| t.line_number), Some(3));
+    }
+
+    #[test]
+    fn test_define_native() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+
+        interpreter.define_native(
+            "double",
+            1,
+            Box::new(|arguments| match arguments {
+                [Some(Literal::Number(n))] => Ok(Some(Literal::Number(n * 2.0))),
+                _ => RuntimeError::new("'double' expects a number argument.".to_string()),
+            }),
+        );
+
+        crate::frontend::run_in_interpreter("print double(21); print double;", &mut interpreter)
+            .unwrap();
+        let result = crate::frontend::run_in_interpreter("double(\"a\");", &mut interpreter);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "'double' expects a number argument. [line 0]"
+        );
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "42\n<native fn double>\n"
+        );
+    }
+
+    #[rstest]
+    #[case::positive_zero("print 1 / 0;", "inf")]
+    #[case::negative_zero("print 1 / -0;", "-inf")]
+    #[case::negative_numerator("print -1 / 0;", "-inf")]
+    #[case::zero_numerator("print 0 / 0;", "NaN")]
+    fn test_divide_by_zero_is_configurable(#[case] source: &str, #[case] ieee: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                ieee_division: true,
+                ..Default::default()
+            },
+        );
+        crate::frontend::run_in_interpreter(source, &mut interpreter).unwrap();
+
+        // Reported separately from operands of the wrong type
+        assert_eq!(error.to_string(), "Division by zero. [line 0]");
+        assert_eq!(String::from_utf8(output).unwrap(), format!("{}\n", ieee));
+    }
+
+    #[rstest]
+    #[case::declaration("fun add(a, b) { return a + b; } print add(1, 2);", "3\n")]
+    #[case::implicit_nil("fun f() {} print f();", "nil\n")]
+    #[case::bare_return("fun f() { return; print 1; } print f();", "nil\n")]
+    #[case::display("fun f() {} print f; print clock;", "<fn f>\n<native fn clock>\n")]
+    #[case::recursion(
+        "fun fib(n) { return n < 2 ? n : fib(n - 1) + fib(n - 2); } print fib(10);",
+        "55\n"
+    )]
+    #[case::closure(
+        "fun counter() { var n = 0; fun next() { n += 1; return n; } return next; }
+         var c = counter(); c(); print c();",
+        "2\n"
+    )]
+    #[case::block_scope("var a = 1; { var a = 2; print a; } print a;", "2\n1\n")]
+    #[case::block_assigns_enclosing("var a = 1; { a = 2; } print a;", "2\n")]
+    fn test_functions_and_blocks(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::two_arguments("fun f(...xs) { return xs; } print f(1, 2);", "[1, 2]\n")]
+    #[case::grouped_comma("fun f(...xs) { return xs; } print f((1, 2));", "[2]\n")]
+    #[case::grouped_comma_effects(
+        "var a = 0; fun f(x) { return x; } print f((a = 1, a + 1)); print a;",
+        "2\n1\n"
+    )]
+    fn test_commas_separate_call_arguments(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::no_arguments("fun sum(...xs) { return xs; } print sum();", "[]\n")]
+    #[case::one_argument("fun sum(...xs) { return xs; } print sum(1);", "[1]\n")]
+    #[case::many_arguments("fun sum(...xs) { return xs; } print sum(1, 2, 3);", "[1, 2, 3]\n")]
+    #[case::after_params(
+        "fun f(a, b, ...rest) { print a + b; print rest; } f(1, 2, 3, nil);",
+        "3\n[3, nil]\n"
+    )]
+    #[case::method(
+        "class A { f(...xs) { return xs; } } print A().f(\"a\", \"b\");",
+        "[a, b]\n"
+    )]
+    #[case::arity("fun f(a, ...rest) {} print arity(f);", "1\n")]
+    fn test_rest_parameter(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::uses_default("fun f(a, b = 2) { return a + b; } print f(1);", "3\n")]
+    #[case::overrides_default("fun f(a, b = 2) { return a + b; } print f(1, 5);", "6\n")]
+    #[case::refers_to_earlier_parameter("fun f(a, b = a * 10) { return b; } print f(3);", "30\n")]
+    #[case::evaluated_each_call(
+        "var n = 0; fun next() { n += 1; return n; } fun f(a = next()) { return a; } f(); f(); print f();",
+        "3\n"
+    )]
+    #[case::not_evaluated_when_given(
+        "var n = 0; fun next() { n += 1; return n; } fun f(a = next()) { return a; } f(7); print n;",
+        "0\n"
+    )]
+    #[case::closure_scope(
+        "var base = 1; fun f(a = base) { return a; } base = 5; print f();",
+        "5\n"
+    )]
+    #[case::nil_default("fun f(a = nil) { return a; } print f();", "nil\n")]
+    #[case::with_rest(
+        "fun f(a = 1, ...rest) { print a; print rest; } f(); f(2, 3);",
+        "1\n[]\n2\n[3]\n"
+    )]
+    #[case::arity("fun f(a, b = 2) {} print arity(f);", "1\n")]
+    fn test_default_parameter(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::too_few(
+        "fun f(a, b = 2) {} f();",
+        "Expected 1 to 2 arguments but got 0. [line 0]"
+    )]
+    #[case::too_many(
+        "fun f(a, b = 2) {} f(1, 2, 3);",
+        "Expected 1 to 2 arguments but got 3. [line 0]"
+    )]
+    fn test_default_parameter_arity(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case::reordered("fun f(a, b) { print a - b; } f(b: 3, a: 10);", "7\n")]
+    #[case::after_positional("fun f(a, b, c) { print a + b + c; } f(1, c: 3, b: 2);", "6\n")]
+    #[case::skips_default(
+        "fun f(a, b = 2, c = 3) { print [a, b, c]; } f(1, c: 5);",
+        "[1, 2, 5]\n"
+    )]
+    #[case::default_sees_keyword("fun f(a = b, b = 1) { return a; } print f(a: 4, b: 2);", "4\n")]
+    #[case::with_rest("fun f(a, ...rest) { print a; print rest; } f(a: 1);", "1\n[]\n")]
+    #[case::method(
+        "class A { f(x, y) { return x / y; } } print A().f(y: 2, x: 8);",
+        "4\n"
+    )]
+    #[case::initializer(
+        "class P { init(x, y) { this.x = x; this.y = y; } } var p = P(y: 1, x: 2); print p.x;",
+        "2\n"
+    )]
+    fn test_keyword_arguments(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::unknown("fun f(a) {} f(b: 1);", "<fn f> has no parameter named 'b'. [line 0]")]
+    #[case::given_positionally(
+        "fun f(a, b) {} f(1, a: 2);",
+        "Argument 'a' is given more than once. [line 0]"
+    )]
+    #[case::missing(
+        "fun f(a, b) {} f(b: 1);",
+        "Missing argument for parameter 'a'. [line 0]"
+    )]
+    #[case::too_many_positional(
+        "fun f(a) {} f(1, 2, a: 3);",
+        "Expected at most 1 positional arguments but got 2. [line 0]"
+    )]
+    #[case::native(
+        "len(s: \"a\");",
+        "<native fn len> doesn't take keyword arguments. [line 0]"
+    )]
+    #[case::class_without_init("class A {} A(x: 1);", "A has no parameter named 'x'. [line 0]")]
+    fn test_keyword_argument_errors(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[test]
+    fn test_rest_parameter_needs_fixed_arguments() {
+        let error = crate::frontend::run_to_string("fun f(a, b, ...rest) {} f(1);").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Expected at least 2 arguments but got 1. [line 0]"
+        );
+    }
+
+    #[test]
+    fn test_return_at_top_level() {
+        let error = crate::frontend::run_to_string("print 1; return 1;").unwrap_err();
+
+        // Reported by the resolver, before anything runs
+        assert_eq!(
+            error.to_string(),
+            "Error on line 0: Can't return from top-level code."
+        );
+    }
+
+    #[test]
+    fn test_block_variables_go_out_of_scope() {
+        let error = crate::frontend::run_to_string("{ var a = 1; } print a;").unwrap_err();
+
+        assert_eq!(error.to_string(), "Undefined variable 'a'. [line 0]");
+    }
+
+    #[test]
+    fn test_call_depth_limit() {
+        let source = "fun down(n) { return n == 0 ? 0 : down(n - 1); }
+                      print down(150);
+                      fun forever() { return forever(); }
+                      forever();";
+
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
+        let error = crate::frontend::run_in_interpreter(source, &mut interpreter).unwrap_err();
+
+        // The interpreter is still usable after the overflow
+        crate::frontend::run_in_interpreter("print down(3);", &mut interpreter).unwrap();
+
+        assert_eq!(error.to_string(), "Stack overflow. [line 2]");
+        assert_eq!(String::from_utf8(output).unwrap(), "0\n0\n");
+    }
+
+    #[test]
+    fn test_call_depth_limit_with_keyword_arguments() {
+        let source = "fun forever(n) { return forever(n: n); } forever(n: 1);";
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), "Stack overflow. [line 0]");
+    }
+
+    #[test]
+    fn test_call_depth_limit_is_configurable() {
+        let mut output = io::sink();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                max_call_depth: 3,
+                ..Default::default()
+            },
+        );
+        let source = "fun down(n) { return n == 0 ? 0 : down(n - 1); }";
+        crate::frontend::run_in_interpreter(source, &mut interpreter).unwrap();
+
+        assert!(crate::frontend::run_in_interpreter("down(2);", &mut interpreter).is_ok());
+        assert!(crate::frontend::run_in_interpreter("down(3);", &mut interpreter).is_err());
+    }
+
+    #[rstest]
+    #[case::first(1, "one\n")]
+    #[case::second(2, "two\n")]
+    #[case::otherwise(3, "other\n")]
+    fn test_else_if_chain(#[case] n: usize, #[case] expected: &str) {
+        let source = format!(
+            "var n = {};
+             if (n == 1) {{ print \"one\"; }} else if (n == 2) {{ print \"two\"; }} else {{ print \"other\"; }}",
+            n
+        );
+
+        assert_eq!(crate::frontend::run_to_string(&source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::then_branch("if (true) print 1; print 2;", "1\n2\n")]
+    #[case::skipped("if (false) print 1; print 2;", "2\n")]
+    #[case::else_branch("if (nil) print 1; else print 2;", "2\n")]
+    #[case::dangling_else("if (true) if (false) print 1; else print 2;", "2\n")]
+    fn test_if_statement(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::zero(0.0, Some(0))]
+    #[case::integer(42.0, Some(42))]
+    #[case::largest(9_007_199_254_740_992.0, Some(9_007_199_254_740_992))]
+    #[case::negative_zero(-0.0, Some(0))]
+    #[case::fractional(0.5, None)]
+    #[case::negative(-1.0, None)]
+    #[case::huge(1e30, None)]
+    #[case::nan(f64::NAN, None)]
+    #[case::infinity(f64::INFINITY, None)]
+    fn test_to_index(#[case] number: f64, #[case] expected: Option<usize>) {
+        let token = Token::new(
+            TokenType::LeftBracket,
+            "[".to_string(),
+            None,
+            0,
+            Span::default(),
+        );
+
+        match (to_index(number, Some(&token)), expected) {
+            (Ok(index), Some(expected)) => assert_eq!(index, expected),
+            (Err(err), None) => assert_eq!(err.message, "Number is not a valid integer index."),
+            (result, _) => panic!("Unexpected result {:?} for {}", result, number),
+        }
+    }
+
+    #[test]
+    fn test_nan_list_index() {
+        let mut output = io::sink();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                ieee_division: true,
+                ..Default::default()
+            },
+        );
+
+        let error =
+            crate::frontend::run_in_interpreter("[1][0 / 0];", &mut interpreter).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Number is not a valid integer index. [line 0]"
+        );
+    }
+
+    #[rstest]
+    #[case::increment("var x = 1; x++; print x == 2;", "true\n")]
+    #[case::returns_original("var x = 1; print x++; print x;", "1\n2\n")]
+    #[case::decrement("var x = 1; print x--; print x;", "1\n0\n")]
+    #[case::in_expression("var x = 3; print x++ + x;", "7\n")]
+    #[case::loop_counter("var i = 0; while (i < 3) print i++;", "0\n1\n2\n")]
+    #[case::closure(
+        "fun counter() { var n = 0; return fun () { return n++; }; } var c = counter(); c(); print c();",
+        "1\n"
+    )]
+    fn test_postfix(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::string(
+        "var x = \"a\"; x++;",
+        "Operand of '++' must be a number, not string. [line 0]"
+    )]
+    #[case::nil("var x; x--;", "Operand of '--' must be a number, not nil. [line 0]")]
+    #[case::undefined("y++;", "Undefined variable 'y'. [line 0]")]
+    fn test_postfix_errors(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case::matched_case(
+        "var x = 2; switch (x) { case 1: print \"one\"; case 2: print \"two\"; }",
+        "two\n"
+    )]
+    #[case::no_fall_through(
+        "switch (1) { case 1: print \"one\"; case 2: print \"two\"; default: print \"other\"; }",
+        "one\n"
+    )]
+    #[case::first_match_only("switch (1) { case 1: print \"a\"; case 1: print \"b\"; }", "a\n")]
+    #[case::default_branch(
+        "switch (3) { case 1: print \"one\"; default: print \"other\"; }",
+        "other\n"
+    )]
+    #[case::no_match_no_default("switch (3) { case 1: print \"one\"; } print \"done\";", "done\n")]
+    #[case::strings("switch (\"b\") { case \"a\": print 1; case \"b\": print 2; }", "2\n")]
+    #[case::nil("switch (nil) { case false: print 1; case nil: print 2; }", "2\n")]
+    #[case::value_evaluated_once(
+        "var n = 0; fun next() { n += 1; return n; } switch (next()) { case 2: print 2; case 3: print 3; } print n;",
+        "1\n"
+    )]
+    #[case::later_cases_not_evaluated(
+        "var n = 0; fun next() { n += 1; return n; } switch (1) { case 1: print 1; case next(): print 2; } print n;",
+        "1\n0\n"
+    )]
+    #[case::case_scope(
+        "var a = 1; switch (1) { case 1: var a = 2; print a; } print a;",
+        "2\n1\n"
+    )]
+    #[case::return_from_case(
+        "fun f(x) { switch (x) { case 1: return \"one\"; } return \"other\"; } print f(1); print f(2);",
+        "one\nother\n"
+    )]
+    fn test_switch_statement(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::runs_once_when_false("do print \"once\"; while (false);", "once\n")]
+    #[case::repeats("var i = 0; do { print i; i += 1; } while (i < 3);", "0\n1\n2\n")]
+    #[case::condition_after_body("var i = 5; do i += 1; while (i < 3); print i;", "6\n")]
+    #[case::return_from_body("fun f() { do { return 1; } while (true); } print f();", "1\n")]
+    fn test_do_while_loop(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::break_loop(
+        "var i = 0; while (true) { if (i == 2) break; print i; i += 1; }",
+        "0\n1\n"
+    )]
+    #[case::continue_loop(
+        "var i = 0; while (i < 4) { i += 1; if (i == 2) continue; print i; }",
+        "1\n3\n4\n"
+    )]
+    #[case::continue_do_checks_condition(
+        "var i = 0; do { i += 1; continue; } while (i < 3); print i;",
+        "3\n"
+    )]
+    #[case::break_from_switch(
+        "var i = 0; while (true) { switch (i) { case 2: break; } print i; i += 1; }",
+        "0\n1\n"
+    )]
+    #[case::break_inner_only(
+        "var i = 0; while (i < 2) { while (true) break; print i; i += 1; }",
+        "0\n1\n"
+    )]
+    #[case::break_outer(
+        "var i = 0; outer: while (true) { var j = 0; while (true) { if (i == 1) if (j == 1) break outer; print [i, j]; j += 1; if (j == 2) break; } i += 1; } print \"done\";",
+        "[0, 0]\n[0, 1]\n[1, 0]\ndone\n"
+    )]
+    #[case::continue_outer(
+        "var i = 0; outer: while (i < 3) { i += 1; var j = 0; while (true) { j += 1; if (j == 2) continue outer; print [i, j]; } }",
+        "[1, 1]\n[2, 1]\n[3, 1]\n"
+    )]
+    #[case::labeled_inner(
+        "var i = 0; while (i < 2) { i += 1; inner: do { break inner; } while (true); print i; }",
+        "1\n2\n"
+    )]
+    fn test_loop_jumps(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::list("for (x in [1, 2, 3]) print x * 2;", "2\n4\n6\n")]
+    #[case::string_graphemes("for (c in \"ae\u{301}🦀\") print c;", "a\ne\u{301}\n🦀\n")]
+    #[case::empty_list("for (x in []) print x; print \"done\";", "done\n")]
+    #[case::iterable_evaluated_once(
+        "var n = 0; fun list() { n += 1; return [1, 2]; } for (x in list()) {} print n;",
+        "1\n"
+    )]
+    #[case::fresh_scope_per_iteration(
+        "var f; var g; for (x in [1, 2]) if (x == 1) f = fun () { return x; }; else g = fun () { return x; }; print f(); print g();",
+        "1\n2\n"
+    )]
+    #[case::variable_not_visible_after("var x = \"outer\"; for (x in [1]) {} print x;", "outer\n")]
+    #[case::break_and_continue(
+        "for (x in [1, 2, 3, 4]) { if (x == 2) continue; if (x == 4) break; print x; }",
+        "1\n3\n"
+    )]
+    #[case::labeled(
+        "outer: for (x in [1, 2]) for (y in \"ab\") { if (y == \"b\") continue outer; print [x, y]; }",
+        "[1, a]\n[2, a]\n"
+    )]
+    #[case::in_is_a_name("var in = [1]; for (x in in) print x;", "1\n")]
+    fn test_for_in_loop(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::number(
+        "for (x in 1) {}",
+        "Can only iterate over lists and strings, not number."
+    )]
+    #[case::nil(
+        "var a; for (x in a) {}",
+        "Can only iterate over lists and strings, not nil."
+    )]
+    fn test_for_in_non_iterable(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), format!("{} [line 0]", expected));
+    }
+
+    #[test]
+    fn test_while_loop() {
+        let source = "var i = 0; while (i < 3) { print i; i += 1; }";
+
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), "0\n1\n2\n");
+    }
+
+    #[test]
+    fn test_step_limit_aborts_infinite_loop() {
+        let mut output = io::sink();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                max_steps: Some(100),
+                ..Default::default()
+            },
+        );
+
+        let error =
+            crate::frontend::run_in_interpreter("while (true) {}", &mut interpreter).unwrap_err();
+
+        assert_eq!(error.to_string(), "Execution limit exceeded.");
+    }
+
+    #[test]
+    fn test_step_limit_counts_statements_and_expressions() {
+        let mut output = io::sink();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                max_steps: Some(4),
+                ..Default::default()
+            },
+        );
+
+        // One statement evaluating three expressions
+        assert!(crate::frontend::run_in_interpreter("print 1 + 2;", &mut interpreter).is_ok());
+        assert!(crate::frontend::run_in_interpreter("print 1;", &mut interpreter).is_err());
     }
 
     #[rstest]
-    #[case::minus(
-        TokenType::Minus,
-        Literal::Number(3.0),
-        Literal::Number(2.0),
-        Literal::Number(1.0)
+    #[case::empty_list("print [];", "[]\n")]
+    #[case::list(
+        "var l = [1, \"a\", nil, [true]]; print l; print l[3][0];",
+        "[1, a, nil, [true]]\ntrue\n"
     )]
-    #[case::divide(
-        TokenType::Slash,
-        Literal::Number(6.0),
-        Literal::Number(3.0),
-        Literal::Number(2.0)
+    #[case::empty_map("print {};", "{}\n")]
+    #[case::map(
+        "var m = {\"b\": 2, \"a\": 1 + 1, 3: \"three\"}; print m; print m[\"a\"]; print m[1 + 2];",
+        "{3: three, a: 2, b: 2}\n2\nthree\n"
     )]
-    #[case::multiply(
-        TokenType::Star,
-        Literal::Number(2.0),
-        Literal::Number(1.0),
-        Literal::Number(2.0)
+    #[case::negative_zero_key("print {0: \"zero\"}[-0];", "zero\n")]
+    #[case::block_at_statement_start("{ print {\"a\": 1}[\"a\"]; }", "1\n")]
+    #[case::equality_is_identity(
+        "var a = [1, 2]; print a == a; print a == [1, 2]; print {\"a\": 1} == {\"a\": 1};",
+        "true\nfalse\nfalse\n"
     )]
-    fn test_binary_arithmetic(
-        #[case] operator: TokenType,
-        #[case] left: Literal,
-        #[case] right: Literal,
-        #[case] expected: Literal,
-    ) {
-        let expr = Expression::Binary {
-            left: Box::new(Expression::Literal(Some(left))),
-            operator: Token {
-                lexeme: match operator {
-                    TokenType::Minus => "-".to_string(),
-                    TokenType::Slash => "/".to_string(),
-                    TokenType::Star => "*".to_string(),
-                    _ => panic!("Unexpected operator {:?}", operator),
-                },
-                token_type: operator,
-                literal: None,
-                line_number: 0,
-            },
-            right: Box::new(Expression::Literal(Some(right))),
-        };
+    fn test_collections(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
 
-        assert_eq!(interpret(&expr), Ok(Some(expected)));
+    #[rstest]
+    #[case::list_alias(
+        "var a = [1]; var b = a; push(b, 2); print a; print a == b;",
+        "[1, 2]\ntrue\n"
+    )]
+    #[case::list_both_ways("var a = []; var b = a; push(a, 1); push(b, 2); print b;", "[1, 2]\n")]
+    #[case::list_in_list(
+        "var inner = []; var outer = [inner]; push(inner, 1); print outer;",
+        "[[1]]\n"
+    )]
+    #[case::list_in_map("var l = []; var m = {\"l\": l}; push(m[\"l\"], 1); print l;", "[1]\n")]
+    #[case::map_alias(
+        "var a = {\"k\": 1}; var b = a; print a == b; print b == {\"k\": 1};",
+        "true\nfalse\n"
+    )]
+    #[case::list_in_field(
+        "class A {} var a = A(); var l = []; a.l = l; push(a.l, 1); print l == a.l; print l;",
+        "true\n[1]\n"
+    )]
+    #[case::values_still_compare_by_value(
+        "print 1 == 1; print \"ab\" == \"a\" + \"b\"; print true == !false;",
+        "true\ntrue\ntrue\n"
+    )]
+    fn test_shared_collections(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
     }
 
     #[rstest]
-    #[case::greater(
-        TokenType::Greater,
-        Literal::Number(2.0),
-        Literal::Number(1.0),
-        Literal::Boolean(true)
+    #[case::equal_lists(
+        "var a = [1, 2]; var b = [1, 2]; print a == b; print equals(a, b);",
+        "false\ntrue\n"
     )]
-    #[case::greater_equal(
-        TokenType::GreaterEqual,
-        Literal::Number(2.0),
-        Literal::Number(2.0),
-        Literal::Boolean(true)
+    #[case::same_list("var a = [1, 2]; print a == a; print equals(a, a);", "true\ntrue\n")]
+    #[case::not_equal(
+        "print [1, 2] != [1, 2]; print equals([1, 2], [2, 1]);",
+        "true\nfalse\n"
     )]
-    #[case::less(
-        TokenType::Less,
-        Literal::Number(1.0),
-        Literal::Number(2.0),
-        Literal::Boolean(true)
+    #[case::different_lengths("print equals([1], [1, 1]);", "false\n")]
+    #[case::nested_lists("print equals([[1], [nil]], [[1], [nil]]);", "true\n")]
+    #[case::equal_maps(
+        "var a = {\"k\": [1]}; var b = {\"k\": [1]}; print a == b; print equals(a, b);",
+        "false\ntrue\n"
     )]
-    #[case::less_equal(
-        TokenType::LessEqual,
-        Literal::Number(2.0),
-        Literal::Number(2.0),
-        Literal::Boolean(true)
+    #[case::different_keys("print equals({\"a\": 1}, {\"b\": 1});", "false\n")]
+    #[case::different_values("print equals({\"a\": 1}, {\"a\": 2});", "false\n")]
+    #[case::list_and_map("print equals([], {});", "false\n")]
+    #[case::values(
+        "print equals(1, 1); print equals(\"a\", \"b\"); print equals(nil, nil);",
+        "true\nfalse\ntrue\n"
     )]
-    fn test_binary_comparison(
-        #[case] operator: TokenType,
-        #[case] left: Literal,
-        #[case] right: Literal,
-        #[case] expected: Literal,
-    ) {
-        let expr = Expression::Binary {
-            left: Box::new(Expression::Literal(Some(left))),
-            operator: Token {
-                lexeme: match operator {
-                    TokenType::Greater => ">".to_string(),
-                    TokenType::GreaterEqual => ">=".to_string(),
-                    TokenType::Less => "<".to_string(),
-                    TokenType::LessEqual => "<=".to_string(),
-                    _ => panic!("Unexpected operator {:?}", operator),
-                },
-                token_type: operator,
-                literal: None,
-                line_number: 0,
-            },
-            right: Box::new(Expression::Literal(Some(right))),
-        };
+    #[case::instances_by_identity("class A {} print equals([A()], [A()]);", "false\n")]
+    #[case::cyclic_lists(
+        "var a = [1]; push(a, a); var b = [1]; push(b, b); print equals(a, b); push(b, 2); print equals(a, b);",
+        "true\nfalse\n"
+    )]
+    fn test_structural_equality(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
 
-        assert_eq!(interpret(&expr), Ok(Some(expected)));
+    #[rstest]
+    #[case::list_in_itself("var l = [1]; push(l, l); print l;", "[1, [...]]\n")]
+    #[case::list_in_itself_twice(
+        "var l = []; push(l, l); push(l, l); print l;",
+        "[[...], [...]]\n"
+    )]
+    #[case::map_in_list_in_map(
+        "var l = []; var m = {\"l\": l}; push(l, m); print m; print l;",
+        "{l: [{...}]}\n[{l: [...]}]\n"
+    )]
+    #[case::shared_but_not_cyclic("var a = [1]; print [a, a];", "[[1], [1]]\n")]
+    #[case::str_of_cycle("var l = [1]; push(l, l); print str(l);", "[1, [...]]\n")]
+    fn test_display_cyclic_collections(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_display_deeply_nested_list() {
+        let source = "var l = []; for (i in range(100)) l = [l]; print l;";
+        let expected = format!("{}...{}\n", "[".repeat(32), "]".repeat(32));
+
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
     }
 
     #[rstest]
-    #[case::greater_string(
-        TokenType::Greater,
-        Literal::String("hello".to_string()),
-        Literal::String("world".to_string())
+    #[case::bool_key("print {true: 1};", "Map keys must be strings or numbers. [line 0]")]
+    #[case::missing_key("print {\"a\": 1}[\"b\"];", "Undefined key 'b'. [line 0]")]
+    #[case::list_out_of_range("[1][1];", "List index 1 is out of range. [line 0]")]
+    #[case::list_negative_index("[1][-1];", "Number is not a valid integer index. [line 0]")]
+    #[case::list_fractional_index("[1][0.5];", "Number is not a valid integer index. [line 0]")]
+    #[case::list_huge_index(
+        "[1][1000000000000000000000000000000];",
+        "Number is not a valid integer index. [line 0]"
     )]
-    #[case::greater_boolean(TokenType::Greater, Literal::Boolean(true), Literal::Boolean(false))]
-    #[case::greater_equal_string(
-        TokenType::LessEqual,
-        Literal::String("hello".to_string()),
-        Literal::String("world".to_string())
+    #[case::list_huge_integer_index(
+        "[1][9007199254740994];",
+        "Number is not a valid integer index. [line 0]"
     )]
-    #[case::greater_equal_boolean(
-        TokenType::LessEqual,
-        Literal::Boolean(true),
-        Literal::Boolean(false)
+    #[case::list_string_index("[1][\"0\"];", "List index must be a number. [line 0]")]
+    #[case::not_indexable("1[0];", "Only lists, maps and strings can be indexed. [line 0]")]
+    fn test_collection_errors(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case::index("print \"hello\"[1];", "e\n")]
+    #[case::last_index("var s = \"abc\"; print s[len(s) - 1];", "c\n")]
+    #[case::multi_byte_index("print \"héllo\"[1];", "é\n")]
+    #[case::combining_index("print \"ne\u{301}e\"[1];", "e\u{301}\n")]
+    #[case::emoji_index("print \"a👍🏽b\"[2];", "b\n")]
+    #[case::slice("print \"hello\"[1..3];", "el\n")]
+    #[case::whole_slice("print \"hello\"[0..5];", "hello\n")]
+    #[case::empty_slice("print len(\"hello\"[2..2]);", "0\n")]
+    #[case::multi_byte_slice("print \"añob👍🏽x\"[1..5];", "ñob👍🏽\n")]
+    #[case::computed_bounds("var s = \"abcdef\"; var n = 2; print s[n..n * 2];", "cd\n")]
+    fn test_string_indexing(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::out_of_range("\"abc\"[3];", "String index 3 is out of range. [line 0]")]
+    #[case::multi_byte_out_of_range("\"é\"[1];", "String index 1 is out of range. [line 0]")]
+    #[case::negative("\"abc\"[-1];", "Number is not a valid integer index. [line 0]")]
+    #[case::string_index("\"abc\"[\"0\"];", "String index must be a number. [line 0]")]
+    #[case::slice_past_end("\"abc\"[1..4];", "String slice 1..4 is out of range. [line 0]")]
+    #[case::slice_reversed("\"abc\"[2..1];", "String slice 2..1 is out of range. [line 0]")]
+    #[case::slice_fractional("\"abc\"[0..1.5];", "Number is not a valid integer index. [line 0]")]
+    #[case::slice_bounds("\"abc\"[0..nil];", "Slice bounds must be numbers. [line 0]")]
+    #[case::slice_list("[1, 2][0..1];", "Only strings can be sliced. [line 0]")]
+    fn test_string_indexing_errors(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case::nil_left("print nil ?: 5 == 5;", "true\n")]
+    #[case::present_left("print 3 ?: 5 == 3;", "true\n")]
+    #[case::false_is_not_nil("print false ?: 5;", "false\n")]
+    #[case::chained("print nil ?: nil ?: 1;", "1\n")]
+    #[case::left_evaluated_once(
+        "var n = 0; fun next() { n += 1; return n; } print next() ?: 10; print n;",
+        "1\n1\n"
     )]
-    #[case::less_string(
-        TokenType::Less,
-        Literal::String("hello".to_string()),
-        Literal::String("world".to_string())
+    #[case::right_not_evaluated(
+        "var n = 0; fun next() { n += 1; return n; } print 1 ?: next(); print n;",
+        "1\n0\n"
     )]
-    #[case::less_boolean(TokenType::Less, Literal::Boolean(true), Literal::Boolean(false))]
-    #[case::less_equal_string(
-        TokenType::LessEqual,
-        Literal::String("hello".to_string()),
-        Literal::String("world".to_string())
+    fn test_elvis(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::string_first("print \"x\" * 3;", "xxx\n")]
+    #[case::number_first("print 3 * \"x\";", "xxx\n")]
+    #[case::multiple_graphemes("print \"ab\" * 3;", "ababab\n")]
+    #[case::zero("print \"x\" * 0 == \"\";", "true\n")]
+    fn test_string_repetition(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::negative(
+        "\"x\" * -1;",
+        "Can only repeat a string a non-negative whole number of times, not -1. [line 0]"
     )]
-    #[case::less_equal_boolean(
-        TokenType::LessEqual,
-        Literal::Boolean(true),
-        Literal::Boolean(false)
+    #[case::fractional(
+        "1.5 * \"x\";",
+        "Can only repeat a string a non-negative whole number of times, not 1.5. [line 0]"
     )]
-    fn test_binary_comparison_non_numbers(
-        #[case] operator: TokenType,
-        #[case] left: Literal,
-        #[case] right: Literal,
-    ) {
-        let expr = Expression::Binary {
-            left: Box::new(Expression::Literal(Some(left))),
-            operator: Token {
-                lexeme: match operator {
-                    TokenType::Greater => ">".to_string(),
-                    TokenType::GreaterEqual => ">=".to_string(),
-                    TokenType::Less => "<".to_string(),
-                    TokenType::LessEqual => "<=".to_string(),
-                    _ => panic!("Unexpected operator {:?}", operator),
-                },
-                token_type: operator,
-                literal: None,
-                line_number: 0,
-            },
-            right: Box::new(Expression::Literal(Some(right))),
-        };
+    #[case::huge(
+        "\"x\" * 1000000000000000000000000000000;",
+        "Can only repeat a string a non-negative whole number of times, not 1000000000000000000000000000000. [line 0]"
+    )]
+    #[case::too_long(
+        "\"ab\" * 10000000000;",
+        "Repeating a string can't make it longer than 104857600 bytes. [line 0]"
+    )]
+    #[case::length_overflows(
+        "\"ab\" * 9007199254740992;",
+        "Repeating a string can't make it longer than 104857600 bytes. [line 0]"
+    )]
+    #[case::two_strings("\"x\" * \"y\";", "Operands must be numbers. [line 0]")]
+    fn test_string_repetition_errors(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
 
-        assert_eq!(interpret(&expr), Ok(Some(Literal::Boolean(false))));
+        assert_eq!(error.to_string(), expected);
     }
 
     #[rstest]
-    #[case::equal_number(TokenType::EqualEqual, Literal::Number(1.0), Literal::Number(1.0))]
-    #[case::bang_equal_number(TokenType::BangEqual, Literal::Number(1.0), Literal::Number(2.0))]
-    #[case::equal_string(
-        TokenType::EqualEqual,
-        Literal::String("hello".to_string()),
-        Literal::String("hello".to_string()),
+    #[case::equal("print 1 == \"1\";", false, Ok("false\n"))]
+    #[case::not_equal("print 1 != \"1\";", false, Ok("true\n"))]
+    #[case::strict_equal(
+        "print 1 == \"1\";",
+        true,
+        Err("Can't compare number with string using '=='. [line 0]")
     )]
-    #[case::bang_equal_string(
-        TokenType::BangEqual,
-        Literal::String("hello".to_string()),
-        Literal::String("hello world".to_string()),
+    #[case::strict_not_equal(
+        "print \"1\" != 1;",
+        true,
+        Err("Can't compare string with number using '!='. [line 0]")
     )]
-    #[case::equal_boolean(TokenType::EqualEqual, Literal::Boolean(true), Literal::Boolean(true))]
-    #[case::bang_equal_boolean(
-        TokenType::BangEqual,
-        Literal::Boolean(true),
-        Literal::Boolean(false)
+    #[case::strict_same_type("print 1 == 1; print \"a\" != \"b\";", true, Ok("true\ntrue\n"))]
+    #[case::strict_nil("print 1 == nil; print nil != \"a\";", true, Ok("false\ntrue\n"))]
+    #[case::switch(
+        "switch (1) { case \"1\": print 1; default: print 2; }",
+        false,
+        Ok("2\n")
     )]
-    fn test_binary_equality(
-        #[case] operator: TokenType,
-        #[case] left: Literal,
-        #[case] right: Literal,
+    #[case::strict_switch(
+        "switch (1) { case \"1\": print 1; default: print 2; }",
+        true,
+        Err("Can't compare number with string using 'case'.")
+    )]
+    #[case::strict_switch_located(
+        "var s = \"1\";\nswitch (1) { case s: print 1; }",
+        true,
+        Err("Can't compare number with string using 'case'. [line 1]")
+    )]
+    #[case::strict_switch_same_type(
+        "switch (1) { case nil: print 0; case 1: print 1; }",
+        true,
+        Ok("1\n")
+    )]
+    fn test_strict_equality(
+        #[case] source: &str,
+        #[case] strict_equality: bool,
+        #[case] expected: Result<&str, &str>,
     ) {
-        let expr = Expression::Binary {
-            left: Box::new(Expression::Literal(Some(left))),
-            operator: Token {
-                lexeme: match operator {
-                    TokenType::EqualEqual => "==".to_string(),
-                    TokenType::BangEqual => "!=".to_string(),
-                    _ => panic!("Unexpected operator {:?}", operator),
-                },
-                token_type: operator,
-                literal: None,
-                line_number: 0,
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::with_config(
+            &mut output,
+            InterpreterConfig {
+                strict_equality,
+                ..Default::default()
             },
-            right: Box::new(Expression::Literal(Some(right))),
-        };
+        );
 
-        assert_eq!(interpret(&expr), Ok(Some(Literal::Boolean(true))));
+        let result = crate::frontend::run_in_interpreter(source, &mut interpreter);
+        drop(interpreter);
+
+        match expected {
+            Ok(expected) => {
+                assert!(result.is_ok());
+                assert_eq!(String::from_utf8(output).unwrap(), expected);
+            }
+            Err(expected) => assert_eq!(result.unwrap_err().to_string(), expected),
+        }
+    }
+
+    #[rstest]
+    #[case::add_both(
+        "nil + nil;",
+        "Operands must be numbers or strings. Both operands were nil."
+    )]
+    #[case::add_left(
+        "nil + 1;",
+        "Operands must be numbers or strings. Left operand was nil."
+    )]
+    #[case::add_right(
+        "true + nil;",
+        "Operands must be numbers or strings. Right operand was nil."
+    )]
+    #[case::subtract("nil - 1;", "Operands must be numbers. Left operand was nil.")]
+    #[case::multiply("2 * nil;", "Operands must be numbers. Right operand was nil.")]
+    #[case::divide("nil / nil;", "Operands must be numbers. Both operands were nil.")]
+    #[case::variable(
+        "var a; print a - 1;",
+        "Operands must be numbers. Left operand was nil."
+    )]
+    #[case::not_nil("true - 1;", "Operands must be numbers.")]
+    #[case::add_not_nil("true + 1;", "Operands must be numbers or strings.")]
+    fn test_invalid_operand_messages(#[case] source: &str, #[case] expected: &str) {
+        let error = crate::frontend::run_to_string(source).unwrap_err();
+
+        assert_eq!(error.to_string(), format!("{} [line 0]", expected));
+    }
+
+    #[rstest]
+    #[case::parentheses(&format!("print {}1{};", "(".repeat(1000), ")".repeat(1000)), "1\n")]
+    #[case::long_chain(&format!("print 0{};", " + 1".repeat(10_000)), "10000\n")]
+    #[case::call_chain(
+        &format!("fun f() {{ return f; }} print f{};", "()".repeat(1000)),
+        "<fn f>\n"
+    )]
+    fn test_deep_expressions(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::blocks(&format!("{}print 1;{}", "{".repeat(1000), "}".repeat(1000)), "1\n")]
+    #[case::else_if(
+        &format!("if (false) print 0;{} else print 1;", " else if (false) print 0;".repeat(1000)),
+        "1\n"
+    )]
+    #[case::loops(&format!("{}print 1;", "for (x in [0]) ".repeat(1000)), "1\n")]
+    fn test_deep_statements(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
     }
 
     #[test]
-    fn test_divide_by_zero() {
-        let operator = Token {
-            token_type: TokenType::Slash,
-            lexeme: "/".to_string(),
-            literal: None,
-            line_number: 0,
-        };
+    fn test_expression_depth_limit() {
+        let source = format!("fun f() {{ return f; }} f{};", "()".repeat(3000));
+        let error = crate::frontend::run_to_string(&source).unwrap_err();
 
-        let expr = Expression::Binary {
-            left: Box::new(Expression::Literal(Some(Literal::Number(1.0)))),
-            operator: operator.clone(),
-            right: Box::new(Expression::Literal(Some(Literal::Number(0.0)))),
-        };
+        assert_eq!(
+            error.to_string(),
+            "Expression is nested too deeply. [line 0]"
+        );
+    }
+
+    #[rstest]
+    #[case::assigned("var add = fun (a, b) { return a + b; }; print add(1, 2);", "3\n")]
+    #[case::display("print fun () {};", "<fn>\n")]
+    #[case::passed_inline(
+        "fun twice(f, x) { return f(f(x)); } print twice(fun (n) { return n * 2; }, 3);",
+        "12\n"
+    )]
+    #[case::closure(
+        "fun adder(n) { return fun (x) { return x + n; }; } var add2 = adder(2); print add2(5);",
+        "7\n"
+    )]
+    #[case::immediately_invoked("fun () { print \"called\"; }();", "called\n")]
+    fn test_lambdas(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_property_access_requires_instance() {
+        let error = crate::frontend::run_to_string("var a = 1; a.b;").unwrap_err();
 
         assert_eq!(
-            interpret(&expr),
-            RuntimeError::with_token("Division by zero.".to_string(), operator)
+            error.to_string(),
+            "Only instances have properties. [line 0]"
         );
     }
 
+    #[rstest]
+    #[case::initializer(
+        "fun compute() { return 4; } var x = { var t = compute(); t * 2 }; print x;",
+        "8\n"
+    )]
+    #[case::scoped("var t = 1; var x = { var t = 2; t }; print t; print x;", "1\n2\n")]
+    #[case::no_value("var x = { print \"side effect\"; }; print x;", "side effect\nnil\n")]
+    #[case::assignment("var x; x = { var a = 1; a + 1 }; print x;", "2\n")]
+    #[case::map_still_preferred("var x = {\"a\": 1}; print x[\"a\"];", "1\n")]
+    #[case::nested("var x = { var a = 1; { a + 1 } }; print x;", "2\n")]
+    fn test_block_expressions(#[case] source: &str, #[case] expected: &str) {
+        assert_eq!(crate::frontend::run_to_string(source).unwrap(), expected);
+    }
+
     #[test]
-    fn test_grouping() {
-        let expr = Expression::Grouping(Box::new(Expression::Literal(Some(Literal::Number(1.0)))));
+    fn test_reset_forgets_definitions() {
+        let mut output = Vec::new();
+        let mut interpreter = Interpreter::new(&mut output);
 
-        assert_eq!(interpret(&expr), Ok(Some(Literal::Number(1.0))));
+        crate::frontend::run_in_interpreter("var a = 1;", &mut interpreter).unwrap();
+        interpreter.reset();
+        let result = crate::frontend::run_in_interpreter("print a;", &mut interpreter);
+        crate::frontend::run_in_interpreter("print len(\"ab\");", &mut interpreter).unwrap();
+        drop(interpreter);
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Undefined variable 'a'. [line 0]"
+        );
+        assert_eq!(output, b"2\n");
     }
 }
@@ -0,0 +1,61 @@
+use std::{error::Error, fmt, io};
+
+use super::{error_report::LoxErrorReport, parse::tree_walk_interpreter::RuntimeError};
+
+/**
+ * The error returned when running a Lox script fails at any stage
+ */
+#[derive(Debug)]
+pub enum LoxScriptError {
+    Io(io::Error),
+    Syntax(Vec<LoxErrorReport>),
+    Runtime(RuntimeError),
+}
+
+impl LoxScriptError {
+    /**
+     * The process exit code for this error, following the conventions in sysexits.h
+     */
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxScriptError::Io(_) => 74,
+            LoxScriptError::Syntax(_) => 65,
+            LoxScriptError::Runtime(_) => 70,
+        }
+    }
+}
+
+impl fmt::Display for LoxScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoxScriptError::Io(err) => write!(f, "{}", err),
+            LoxScriptError::Syntax(reports) => {
+                let lines: Vec<String> = reports
+                    .iter()
+                    .map(|report| {
+                        format!("Error on line {}: {}", report.line_number, report.message)
+                    })
+                    .collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            LoxScriptError::Runtime(err) => match &err.token {
+                Some(token) => write!(f, "{} [line {}]", err.message, token.line_number),
+                None => write!(f, "{}", err.message),
+            },
+        }
+    }
+}
+
+impl Error for LoxScriptError {}
+
+impl From<io::Error> for LoxScriptError {
+    fn from(err: io::Error) -> Self {
+        LoxScriptError::Io(err)
+    }
+}
+
+impl From<RuntimeError> for LoxScriptError {
+    fn from(err: RuntimeError) -> Self {
+        LoxScriptError::Runtime(err)
+    }
+}
@@ -1 +1,7 @@
 pub mod frontend;
+
+pub use frontend::{
+    check, check_file, print_ast, print_ast_tree, run, run_file, run_in_interpreter,
+    run_interactive, run_to_string, run_with_output, scan_file, HostFn, Interpreter,
+    InterpreterConfig, Literal, LoxErrorReport, LoxScriptError, RuntimeError,
+};
@@ -1,23 +1,296 @@
-use std::{env, error::Error};
+use std::{
+    env,
+    error::Error,
+    fs,
+    io::{self, Read},
+    process,
+};
 
-use loxide::frontend::{run_file, run_interactive};
+use loxide::{
+    check_file, print_ast, print_ast_tree, run, run_file, run_interactive, scan_file,
+    LoxScriptError,
+};
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Interactive,
+    Run(String),
+    // Run the script read from stdin, given as `-`
+    RunStdin,
+    Tokens(String),
+    Ast(String),
+    AstTree(String),
+    Check {
+        script: String,
+        // Fail on warnings as well as errors
+        deny_warnings: bool,
+    },
+    Version,
+}
 
 fn print_help() {
     println!(
-        "usage: loxide [script]
-    Run the Loxide interpreter in interactive mode if no script is provided."
+        "usage: loxide [-V | --version] [--tokens | --ast | --ast-tree | --check [--deny-warnings]] [script]
+    Run the Loxide interpreter in interactive mode if no script is provided.
+    If the script is '-', it is read from stdin.
+
+    -V, --version   Print the version of Loxide and exit.
+    --tokens        Print the tokens scanned from the script instead of running it.
+    --ast           Print the parsed syntax tree of the script instead of running it.
+    --ast-tree      Print the parsed syntax tree of the script as an indented tree instead.
+    --check         Report any syntax errors in the script without running it.
+    --deny-warnings With '--check', report warnings such as unreachable code as errors."
     );
 }
 
+fn version() -> String {
+    format!("loxide {}", env!("CARGO_PKG_VERSION"))
+}
+
+/**
+ * Works out what to do from the arguments, not including the program name.
+ * Flags must come before the script. Asking for the version ignores anything else given.
+ */
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    let mut dump = None;
+    let mut deny_warnings = false;
+    let mut args = args.iter();
+
+    let script = loop {
+        match args.next().map(String::as_str) {
+            Some("--version" | "-V") => return Ok(Command::Version),
+            Some("--deny-warnings") => deny_warnings = true,
+            Some(flag @ ("--tokens" | "--ast" | "--ast-tree" | "--check")) => {
+                if dump.replace(flag).is_some() {
+                    return Err(
+                        "Only one of '--tokens', '--ast', '--ast-tree' and '--check' may be given."
+                            .to_string(),
+                    );
+                }
+            }
+            Some(flag) if flag.starts_with("--") => {
+                return Err(format!("Unknown flag '{}'.", flag));
+            }
+            Some(script) => break Some(script.to_string()),
+            None => break None,
+        }
+    };
+
+    if args.next().is_some() {
+        return Err("Incorrect number of arguments.".to_string());
+    }
+
+    if deny_warnings && dump != Some("--check") {
+        return Err("'--deny-warnings' can only be used with '--check'.".to_string());
+    }
+
+    match (script, dump) {
+        (Some(script), None) if script == "-" => Ok(Command::RunStdin),
+        (Some(script), Some(flag)) if script == "-" => {
+            Err(format!("'{}' can't read the script from stdin.", flag))
+        }
+        (Some(script), Some("--tokens")) => Ok(Command::Tokens(script)),
+        (Some(script), Some("--check")) => Ok(Command::Check {
+            script,
+            deny_warnings,
+        }),
+        (Some(script), Some("--ast-tree")) => Ok(Command::AstTree(script)),
+        (Some(script), Some(_)) => Ok(Command::Ast(script)),
+        (Some(script), None) => Ok(Command::Run(script)),
+        (None, Some(flag)) => Err(format!("'{}' requires a script.", flag)),
+        (None, None) => Ok(Command::Interactive),
+    }
+}
+
+fn print_tokens(file_path: &str) -> Result<(), LoxScriptError> {
+    for token in scan_file(file_path)? {
+        let literal = token.literal.map(|l| l.to_string()).unwrap_or_default();
+
+        println!(
+            "{} {:?} '{}' {}",
+            token.line_number, token.token_type, token.lexeme, literal
+        );
+    }
+
+    Ok(())
+}
+
+/**
+ * Checks the script for errors, printing any warnings, which are errors too if they are denied
+ */
+fn check_script(file_path: &str, deny_warnings: bool) -> Result<(), LoxScriptError> {
+    let warnings = check_file(file_path)?;
+
+    if deny_warnings && !warnings.is_empty() {
+        return Err(LoxScriptError::Syntax(warnings));
+    }
+
+    for warning in warnings {
+        println!(
+            "Warning on line {}: {}",
+            warning.line_number, warning.message
+        );
+    }
+
+    Ok(())
+}
+
+fn run_stdin() -> Result<(), LoxScriptError> {
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source)?;
+    run(&source)
+}
+
+fn dump_ast(file_path: &str) -> Result<String, LoxScriptError> {
+    print_ast(&fs::read_to_string(file_path)?)
+}
+
+fn dump_ast_tree(file_path: &str) -> Result<String, LoxScriptError> {
+    print_ast_tree(&fs::read_to_string(file_path)?)
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
 
-    match args.len() {
-        1 => Ok(run_interactive()?),
-        2 => Ok(run_file(&args[1])?),
-        _ => {
+    let command = match parse_args(&args) {
+        Ok(command) => command,
+        Err(message) => {
             print_help();
-            Err("Incorrect number of arguments.")?
+            Err(message)?
+        }
+    };
+
+    let result = match command {
+        Command::Interactive => return Ok(run_interactive()?),
+        Command::Version => {
+            println!("{}", version());
+            return Ok(());
         }
+        Command::Run(file_path) => run_file(&file_path),
+        Command::RunStdin => run_stdin(),
+        Command::Tokens(file_path) => print_tokens(&file_path),
+        Command::Ast(file_path) => dump_ast(&file_path).map(|ast| print!("{}", ast)),
+        Command::AstTree(file_path) => dump_ast_tree(&file_path).map(|tree| print!("{}", tree)),
+        Command::Check {
+            script,
+            deny_warnings,
+        } => check_script(&script, deny_warnings),
+    };
+
+    if let Err(err) = result {
+        println!("{}", err);
+        process::exit(err.exit_code());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[rstest]
+    #[case::no_arguments(&[], Ok(Command::Interactive))]
+    #[case::script(&["a.lox"], Ok(Command::Run("a.lox".to_string())))]
+    #[case::stdin(&["-"], Ok(Command::RunStdin))]
+    #[case::stdin_with_dump(&["--ast", "-"], Err("'--ast' can't read the script from stdin.".to_string()))]
+    #[case::stdin_then_script(&["-", "a.lox"], Err("Incorrect number of arguments.".to_string()))]
+    #[case::tokens(&["--tokens", "a.lox"], Ok(Command::Tokens("a.lox".to_string())))]
+    #[case::ast(&["--ast", "a.lox"], Ok(Command::Ast("a.lox".to_string())))]
+    #[case::ast_tree(&["--ast-tree", "a.lox"], Ok(Command::AstTree("a.lox".to_string())))]
+    #[case::ast_without_script(&["--ast"], Err("'--ast' requires a script.".to_string()))]
+    #[case::both_dumps(
+        &["--ast", "--tokens", "a.lox"],
+        Err("Only one of '--tokens', '--ast', '--ast-tree' and '--check' may be given.".to_string())
+    )]
+    #[case::check(
+        &["--check", "a.lox"],
+        Ok(Command::Check { script: "a.lox".to_string(), deny_warnings: false })
+    )]
+    #[case::check_deny_warnings(
+        &["--check", "--deny-warnings", "a.lox"],
+        Ok(Command::Check { script: "a.lox".to_string(), deny_warnings: true })
+    )]
+    #[case::deny_warnings_first(
+        &["--deny-warnings", "--check", "a.lox"],
+        Ok(Command::Check { script: "a.lox".to_string(), deny_warnings: true })
+    )]
+    #[case::deny_warnings_without_check(
+        &["--deny-warnings", "a.lox"],
+        Err("'--deny-warnings' can only be used with '--check'.".to_string())
+    )]
+    #[case::check_without_script(&["--check"], Err("'--check' requires a script.".to_string()))]
+    #[case::tokens_without_script(&["--tokens"], Err("'--tokens' requires a script.".to_string()))]
+    #[case::flag_after_script(&["a.lox", "--tokens"], Err("Incorrect number of arguments.".to_string()))]
+    #[case::unknown_flag(&["--nope", "a.lox"], Err("Unknown flag '--nope'.".to_string()))]
+    #[case::version(&["--version"], Ok(Command::Version))]
+    #[case::version_short(&["-V"], Ok(Command::Version))]
+    #[case::version_with_script(&["--version", "a.lox"], Ok(Command::Version))]
+    #[case::version_after_flag(&["--ast", "-V"], Ok(Command::Version))]
+    fn test_parse_args(#[case] input: &[&str], #[case] expected: Result<Command, String>) {
+        assert_eq!(parse_args(&args(input)), expected);
+    }
+
+    #[test]
+    fn test_version() {
+        assert_eq!(version(), "loxide 0.1.0");
+    }
+
+    #[test]
+    fn test_dump_ast() {
+        let path = env::temp_dir().join(format!("loxide-{}-ast.lox", process::id()));
+        fs::write(&path, "var a = 1;\nprint -a * (2 + 3);\nprint (;").unwrap();
+        let invalid = dump_ast(path.to_str().unwrap());
+
+        fs::write(&path, "var a = 1;\nprint -a * (2 + 3);").unwrap();
+        let valid = dump_ast(path.to_str().unwrap());
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(
+            valid.unwrap(),
+            "(var a 1)\n(print (* (- a) (group (+ 2 3))))\n"
+        );
+        assert!(matches!(invalid, Err(LoxScriptError::Syntax(_))));
+    }
+
+    #[test]
+    fn test_check_script_deny_warnings() {
+        let path = env::temp_dir().join(format!("loxide-{}-warnings.lox", process::id()));
+        fs::write(&path, "fun f() {\n  return;\n  print 1;\n}").unwrap();
+        let allowed = check_script(path.to_str().unwrap(), false);
+        let denied = check_script(path.to_str().unwrap(), true);
+        fs::remove_file(path).unwrap();
+
+        assert!(allowed.is_ok());
+        assert_eq!(
+            denied.unwrap_err().to_string(),
+            "Error on line 1: Code after 'return' is unreachable."
+        );
+    }
+
+    #[test]
+    fn test_check_file() {
+        let path = env::temp_dir().join(format!("loxide-{}-check.lox", process::id()));
+        fs::write(&path, "var a = 1;\nprint a;").unwrap();
+        let valid = check_file(path.to_str().unwrap());
+
+        fs::write(&path, "var = 1;\nprint a;\nprint (;").unwrap();
+        let invalid = check_file(path.to_str().unwrap());
+        fs::remove_file(path).unwrap();
+
+        assert!(valid.is_ok());
+        let err = invalid.unwrap_err();
+        assert_eq!(err.exit_code(), 65);
+        assert_eq!(
+            err.to_string(),
+            "Error on line 0: Expected Identifier but found '='\n\
+             Error on line 2: Expected Number, String, Identifier, 'true', 'false', 'nil', 'this', '(', '[', '{', 'fun' or 'if' but found ';'"
+        );
     }
 }